@@ -0,0 +1,587 @@
+//! Canonical block and transaction types.
+//!
+//! Every type has exactly one RLP encoding: decoding rejects non-canonical
+//! integers, trailing bytes and oversized input, so `from_rlp(to_rlp(x))`
+//! round-trips and hashes over the encoding are unambiguous.
+
+mod transaction;
+
+pub use transaction::Transaction;
+
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use sha3::Sha3_256;
+use thiserror::Error;
+
+use crate::difficulty::{CostParams, DifficultySchedule};
+use crate::utils::address::Address;
+use crate::utils::hashing::merkle::merkle_root;
+use crate::utils::hashing::{ConsensusHasher, Domain};
+
+pub type Hash = [u8; 32];
+
+/// Largest encoded transaction accepted, with room for post-quantum keys and signatures.
+pub const MAX_TRANSACTION_SIZE: usize = 128 * 1024;
+
+/// Largest encoded block accepted.
+pub const MAX_BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Largest `extra_data` a proposer may put in a header.
+pub const MAX_EXTRA_DATA_LEN: usize = 32;
+
+/// Accepted lengths of the Argon2 salt in a header.
+pub const POW_SALT_LEN: std::ops::RangeInclusive<usize> = 8..=64;
+
+/// Number of RLP fields of a header.
+const HEADER_FIELDS: usize = 12;
+
+/// Number of RLP fields of the cost parameters.
+const COST_PARAMS_FIELDS: usize = 4;
+
+/// Custom error type for block encoding and validation
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BlockError {
+    #[error("Invalid RLP encoding: {0}")]
+    Decode(#[from] DecoderError),
+    #[error("Encoding is {size} bytes, over the limit of {max}")]
+    TooLarge { size: usize, max: usize },
+    #[error("Trailing bytes after the encoding")]
+    TrailingBytes,
+    #[error("Extra data is {0} bytes, over the limit of {MAX_EXTRA_DATA_LEN}")]
+    ExtraDataTooLarge(usize),
+    #[error("Transactions root does not match the body")]
+    TransactionsRootMismatch,
+    #[error("Transaction {0} has an invalid signature")]
+    InvalidSignature(usize),
+    #[error("Cost parameters do not match the schedule for difficulty {0}")]
+    CostParamsMismatch(u64),
+    #[error("Proof-of-work salt is {0} bytes, outside the accepted range")]
+    InvalidPowSalt(usize),
+    #[error("Proof-of-work hash does not match the header")]
+    PowMismatch,
+}
+
+fn hash_of(domain: Domain, bytes: &[u8]) -> Hash {
+    ConsensusHasher::digest(domain, &[bytes]).try_into().expect("SHA3-256 digest is 32 bytes")
+}
+
+/// Decodes exactly one canonical RLP item of at most `max_size` bytes.
+fn decode_canonical<T: Decodable>(bytes: &[u8], max_size: usize) -> Result<T, BlockError> {
+    if bytes.len() > max_size {
+        return Err(BlockError::TooLarge { size: bytes.len(), max: max_size });
+    }
+    let rlp = Rlp::new(bytes);
+    let payload = rlp.payload_info()?;
+    if payload.header_len + payload.value_len != bytes.len() {
+        return Err(BlockError::TrailingBytes);
+    }
+    Ok(rlp.as_val()?)
+}
+
+fn hash_at(rlp: &Rlp, index: usize) -> Result<Hash, DecoderError> {
+    let bytes: Vec<u8> = rlp.val_at(index)?;
+    bytes.try_into().map_err(|_| DecoderError::Custom("hash must be 32 bytes"))
+}
+
+fn address_from_rlp(bytes: &[u8]) -> Result<Address, DecoderError> {
+    Address::from_slice(bytes).map_err(|_| DecoderError::Custom("address must be 20 bytes"))
+}
+
+/// Block header. Its hash identifies the block and is what validators finalize.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+    #[serde(with = "hex")]
+    pub parent_hash: Hash,
+    pub height: u64,
+    /// Unix time in seconds.
+    pub timestamp: u64,
+    pub proposer: Address,
+    /// Root of the state trie after applying the block.
+    #[serde(with = "hex")]
+    pub state_root: Hash,
+    /// Domain-tagged Merkle root of the body's transaction hashes.
+    #[serde(with = "hex")]
+    pub transactions_root: Hash,
+    /// Chain difficulty the block was forged at.
+    pub difficulty: u64,
+    /// Cost parameters the block was forged with, derived from `difficulty`.
+    pub cost_params: CostParams,
+    /// Argon2 salt the proof of work was forged with.
+    #[serde(with = "hex")]
+    pub pow_salt: Vec<u8>,
+    /// Puzzle data solved as part of the proof of work.
+    #[serde(with = "hex")]
+    pub puzzle_input: Vec<u8>,
+    /// Proof-of-work hash of `seal_hash`, from `forge_block_with_params`.
+    #[serde(with = "hex")]
+    pub pow_hash: Hash,
+    /// Free-form proposer data, at most `MAX_EXTRA_DATA_LEN` bytes.
+    #[serde(with = "hex")]
+    pub extra_data: Vec<u8>,
+}
+
+impl Header {
+    pub fn hash(&self) -> Hash {
+        hash_of(Domain::BlockHeader, &self.to_rlp())
+    }
+
+    pub fn to_rlp(&self) -> Vec<u8> {
+        rlp::encode(self).to_vec()
+    }
+
+    pub fn from_rlp(bytes: &[u8]) -> Result<Self, BlockError> {
+        decode_canonical(bytes, MAX_BLOCK_SIZE)
+    }
+
+    /// Hash of every field but `pow_hash`: the input the proof of work is done over.
+    pub fn seal_hash(&self) -> Hash {
+        let mut stream = RlpStream::new_list(HEADER_FIELDS - 1);
+        self.append_fields(&mut stream, false);
+        hash_of(Domain::HeaderSeal, &stream.out())
+    }
+
+    fn append_fields(&self, stream: &mut RlpStream, with_pow_hash: bool) {
+        stream
+            .append(&self.parent_hash.as_slice())
+            .append(&self.height)
+            .append(&self.timestamp)
+            .append(&self.proposer.as_bytes().as_slice())
+            .append(&self.state_root.as_slice())
+            .append(&self.transactions_root.as_slice())
+            .append(&self.difficulty)
+            .append(&self.cost_params)
+            .append(&self.pow_salt)
+            .append(&self.puzzle_input);
+        if with_pow_hash {
+            stream.append(&self.pow_hash.as_slice());
+        }
+        stream.append(&self.extra_data);
+    }
+
+    /// Commitment to the cost parameters, as bound into the proof-of-work hash.
+    pub fn cost_commitment(&self) -> Vec<u8> {
+        self.cost_params.commitment()
+    }
+
+    /// Checks that `cost_params` are what `schedule` derives for `difficulty`.
+    pub fn validate_cost_params(&self, schedule: &DifficultySchedule) -> Result<(), BlockError> {
+        if schedule.derive(self.difficulty) != self.cost_params {
+            return Err(BlockError::CostParamsMismatch(self.difficulty));
+        }
+        Ok(())
+    }
+}
+
+impl Encodable for CostParams {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        stream
+            .begin_list(COST_PARAMS_FIELDS)
+            .append(&self.argon2_mem_cost)
+            .append(&self.argon2_time_cost)
+            .append(&self.matrix_size)
+            .append(&self.puzzle_difficulty);
+    }
+}
+
+impl Decodable for CostParams {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != COST_PARAMS_FIELDS {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(Self {
+            argon2_mem_cost: rlp.val_at(0)?,
+            argon2_time_cost: rlp.val_at(1)?,
+            matrix_size: rlp.val_at(2)?,
+            puzzle_difficulty: rlp.val_at(3)?,
+        })
+    }
+}
+
+impl Encodable for Header {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        stream.begin_list(HEADER_FIELDS);
+        self.append_fields(stream, true);
+    }
+}
+
+impl Decodable for Header {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != HEADER_FIELDS {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let pow_salt: Vec<u8> = rlp.val_at(8)?;
+        if !POW_SALT_LEN.contains(&pow_salt.len()) {
+            return Err(DecoderError::Custom("invalid proof-of-work salt length"));
+        }
+        let extra_data: Vec<u8> = rlp.val_at(11)?;
+        if extra_data.len() > MAX_EXTRA_DATA_LEN {
+            return Err(DecoderError::Custom("extra data too large"));
+        }
+        Ok(Self {
+            parent_hash: hash_at(rlp, 0)?,
+            height: rlp.val_at(1)?,
+            timestamp: rlp.val_at(2)?,
+            proposer: address_from_rlp(&rlp.val_at::<Vec<u8>>(3)?)?,
+            state_root: hash_at(rlp, 4)?,
+            transactions_root: hash_at(rlp, 5)?,
+            difficulty: rlp.val_at(6)?,
+            cost_params: rlp.val_at(7)?,
+            pow_salt,
+            puzzle_input: rlp.val_at(9)?,
+            pow_hash: hash_at(rlp, 10)?,
+            extra_data,
+        })
+    }
+}
+
+/// The transactions of a block, in execution order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Body {
+    pub transactions: Vec<Transaction>,
+}
+
+impl Body {
+    /// Merkle root of the transaction hashes, tagged with its domain and
+    /// committed to by the header.
+    pub fn transactions_root(&self) -> Hash {
+        let hashes: Vec<Hash> = self.transactions.iter().map(Transaction::hash).collect();
+        hash_of(Domain::TransactionsRoot, &merkle_root::<Sha3_256, _>(&hashes))
+    }
+}
+
+impl Encodable for Body {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        stream.append_list(&self.transactions);
+    }
+}
+
+impl Decodable for Body {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Self { transactions: rlp.as_list()? })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+    pub header: Header,
+    pub body: Body,
+}
+
+impl Block {
+    /// Assembles a block, committing the header to the body's transactions.
+    pub fn new(mut header: Header, body: Body) -> Self {
+        header.transactions_root = body.transactions_root();
+        Self { header, body }
+    }
+
+    pub fn hash(&self) -> Hash {
+        self.header.hash()
+    }
+
+    pub fn to_rlp(&self) -> Vec<u8> {
+        rlp::encode(self).to_vec()
+    }
+
+    pub fn from_rlp(bytes: &[u8]) -> Result<Self, BlockError> {
+        decode_canonical(bytes, MAX_BLOCK_SIZE)
+    }
+
+    /// Checks the size limits, that the header commits to the body and that
+    /// every transaction is signed by its sender.
+    pub fn validate(&self) -> Result<(), BlockError> {
+        let size = rlp::encode(self).len();
+        if size > MAX_BLOCK_SIZE {
+            return Err(BlockError::TooLarge { size, max: MAX_BLOCK_SIZE });
+        }
+        if self.header.extra_data.len() > MAX_EXTRA_DATA_LEN {
+            return Err(BlockError::ExtraDataTooLarge(self.header.extra_data.len()));
+        }
+        if self.header.transactions_root != self.body.transactions_root() {
+            return Err(BlockError::TransactionsRootMismatch);
+        }
+        for (index, transaction) in self.body.transactions.iter().enumerate() {
+            let size = rlp::encode(transaction).len();
+            if size > MAX_TRANSACTION_SIZE {
+                return Err(BlockError::TooLarge { size, max: MAX_TRANSACTION_SIZE });
+            }
+            if !transaction.verify_signature() {
+                return Err(BlockError::InvalidSignature(index));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Encodable for Block {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        stream.begin_list(2).append(&self.header).append(&self.body);
+    }
+}
+
+impl Decodable for Block {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(Self { header: rlp.val_at(0)?, body: rlp.val_at(1)? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto::{keypair_from_seed, Secp256k1};
+    use crate::utils::hashing::merkle::empty_root;
+    use proptest::prelude::*;
+
+    fn signed_transfer(nonce: u64) -> Transaction {
+        let (secret_key, _) = keypair_from_seed(b"validator-0");
+        let unsigned = Transaction {
+            chain_id: 1,
+            nonce,
+            gas_price: 1_000_000_000,
+            gas_limit: 21_000,
+            to: Some(Address::from([0x42; 20])),
+            value: 10u128.pow(18),
+            ..Transaction::default()
+        };
+        unsigned.sign::<Secp256k1>(&secret_key).unwrap()
+    }
+
+    fn sample_block() -> Block {
+        let header = Header {
+            parent_hash: [0x11; 32],
+            height: 7,
+            timestamp: 1_700_000_000,
+            proposer: Address::from([0x22; 20]),
+            state_root: [0x33; 32],
+            difficulty: 100,
+            cost_params: DifficultySchedule::default().derive(100),
+            pow_salt: b"aetherforge salt".to_vec(),
+            puzzle_input: vec![0x55; 64],
+            pow_hash: [0x44; 32],
+            extra_data: b"aetherforge".to_vec(),
+            ..Header::default()
+        };
+        Block::new(header, Body { transactions: vec![signed_transfer(0), signed_transfer(1)] })
+    }
+
+    #[test]
+    fn test_block_round_trip_and_validate() {
+        let block = sample_block();
+        assert_eq!(block.validate(), Ok(()));
+        assert_eq!(Block::from_rlp(&block.to_rlp()).unwrap(), block);
+        assert_eq!(Header::from_rlp(&block.header.to_rlp()).unwrap(), block.header);
+
+        let json = serde_json::to_string(&block).unwrap();
+        assert_eq!(serde_json::from_str::<Block>(&json).unwrap(), block);
+    }
+
+    #[test]
+    fn test_block_hash() {
+        let block = sample_block();
+        assert_eq!(block.hash(), block.header.hash());
+
+        let mut other = block.clone();
+        other.header.timestamp += 1;
+        assert_ne!(other.hash(), block.hash());
+
+        // Changing the body changes the root and therefore the hash.
+        let reordered = Block::new(
+            block.header.clone(),
+            Body { transactions: block.body.transactions.iter().rev().cloned().collect() },
+        );
+        assert_ne!(reordered.hash(), block.hash());
+        let empty_root = hash_of(Domain::TransactionsRoot, &empty_root::<Sha3_256>());
+        assert_eq!(Block::new(Header::default(), Body::default()).header.transactions_root, empty_root);
+    }
+
+    #[test]
+    fn test_validate_rejects_inconsistent_blocks() {
+        let block = sample_block();
+
+        let mut tampered = block.clone();
+        tampered.body.transactions.pop();
+        assert_eq!(tampered.validate(), Err(BlockError::TransactionsRootMismatch));
+
+        let mut tampered = block.clone();
+        tampered.body.transactions[1].value += 1;
+        let tampered = Block::new(tampered.header, tampered.body);
+        assert_eq!(tampered.validate(), Err(BlockError::InvalidSignature(1)));
+
+        let mut tampered = block;
+        tampered.header.extra_data = vec![0; MAX_EXTRA_DATA_LEN + 1];
+        assert_eq!(tampered.validate(), Err(BlockError::ExtraDataTooLarge(MAX_EXTRA_DATA_LEN + 1)));
+        assert!(Header::from_rlp(&tampered.header.to_rlp()).is_err());
+    }
+
+    #[test]
+    fn test_cost_params_committed_in_header() {
+        let block = sample_block();
+        let schedule = DifficultySchedule::default();
+        assert_eq!(block.header.validate_cost_params(&schedule), Ok(()));
+        assert_eq!(block.header.cost_commitment(), schedule.derive(100).commitment());
+
+        // Claiming cheaper parameters than the difficulty calls for is caught, and changes the hash.
+        let mut cheap = block.header.clone();
+        cheap.cost_params = schedule.derive(1);
+        assert_eq!(cheap.validate_cost_params(&schedule), Err(BlockError::CostParamsMismatch(100)));
+        assert_ne!(cheap.hash(), block.header.hash());
+    }
+
+    #[test]
+    fn test_seal_hash_covers_all_but_pow_hash() {
+        let header = sample_block().header;
+        let mut sealed = header.clone();
+        sealed.pow_hash = [0x99; 32];
+        assert_eq!(sealed.seal_hash(), header.seal_hash());
+        assert_ne!(sealed.hash(), header.hash());
+
+        let mut other = header.clone();
+        other.pow_salt.push(0);
+        assert_ne!(other.seal_hash(), header.seal_hash());
+        let mut other = header.clone();
+        other.puzzle_input[0] ^= 1;
+        assert_ne!(other.seal_hash(), header.seal_hash());
+        let mut other = header.clone();
+        other.extra_data.clear();
+        assert_ne!(other.seal_hash(), header.seal_hash());
+    }
+
+    #[test]
+    fn test_decoding_is_canonical() {
+        let block = sample_block();
+        let mut bytes = block.to_rlp();
+        bytes.push(0x80);
+        assert_eq!(Block::from_rlp(&bytes), Err(BlockError::TrailingBytes));
+
+        assert_eq!(
+            Block::from_rlp(&vec![0; MAX_BLOCK_SIZE + 1]),
+            Err(BlockError::TooLarge { size: MAX_BLOCK_SIZE + 1, max: MAX_BLOCK_SIZE })
+        );
+
+        // Re-encodes the sample header with one field replaced by raw RLP.
+        let header_bytes = block.header.to_rlp();
+        let header_with = |index: usize, item: &[u8]| {
+            let fields = Rlp::new(&header_bytes);
+            let mut stream = RlpStream::new_list(HEADER_FIELDS);
+            for (i, field) in fields.iter().enumerate() {
+                stream.append_raw(if i == index { item } else { field.as_raw() }, 1);
+            }
+            stream.out().to_vec()
+        };
+        assert_eq!(header_with(1, &rlp::encode(&7u64)), header_bytes);
+        // A height of 7 written with a leading zero byte.
+        assert!(Header::from_rlp(&header_with(1, &[0x82, 0x00, 0x07])).is_err());
+        // A single byte below 0x80 wrapped in a string header.
+        assert!(Header::from_rlp(&header_with(1, &[0x81, 0x07])).is_err());
+        // Wrongly sized hashes and addresses.
+        assert!(Header::from_rlp(&header_with(0, &rlp::encode(&[0x11u8; 31].as_slice()))).is_err());
+        assert!(Header::from_rlp(&header_with(3, &rlp::encode(&[0x22u8; 19].as_slice()))).is_err());
+        // A salt too short for Argon2.
+        assert!(Header::from_rlp(&header_with(8, &rlp::encode(&[0x66u8; 7].as_slice()))).is_err());
+        // Wrong field counts.
+        assert!(Header::from_rlp(&rlp::encode_list::<u64, u64>(&[1, 2])).is_err());
+    }
+
+    fn arb_hash() -> impl Strategy<Value = Hash> {
+        any::<[u8; 32]>()
+    }
+
+    fn arb_address() -> impl Strategy<Value = Address> {
+        any::<[u8; 20]>().prop_map(Address::from)
+    }
+
+    fn arb_bytes(max_len: usize) -> impl Strategy<Value = Vec<u8>> {
+        prop::collection::vec(any::<u8>(), 0..=max_len)
+    }
+
+    fn arb_transaction() -> impl Strategy<Value = Transaction> {
+        (
+            (any::<u64>(), any::<u64>(), any::<u128>(), any::<u64>()),
+            (prop::option::of(arb_address()), any::<u128>(), arb_bytes(256)),
+            (arb_bytes(70), arb_bytes(80)),
+        )
+            .prop_map(|((chain_id, nonce, gas_price, gas_limit), (to, value, data), (sender_key, signature))| {
+                Transaction { chain_id, nonce, gas_price, gas_limit, to, value, data, sender_key, signature }
+            })
+    }
+
+    fn arb_cost_params() -> impl Strategy<Value = CostParams> {
+        (any::<u32>(), any::<u32>(), any::<u32>(), any::<u8>()).prop_map(
+            |(argon2_mem_cost, argon2_time_cost, matrix_size, puzzle_difficulty)| CostParams {
+                argon2_mem_cost,
+                argon2_time_cost,
+                matrix_size,
+                puzzle_difficulty,
+            },
+        )
+    }
+
+    fn arb_header() -> impl Strategy<Value = Header> {
+        (
+            (arb_hash(), any::<u64>(), any::<u64>(), arb_address(), arb_hash(), arb_hash()),
+            (any::<u64>(), arb_cost_params(), prop::collection::vec(any::<u8>(), POW_SALT_LEN), arb_bytes(256)),
+            (arb_hash(), arb_bytes(MAX_EXTRA_DATA_LEN)),
+        )
+            .prop_map(
+                |(
+                    (parent_hash, height, timestamp, proposer, state_root, transactions_root),
+                    (difficulty, cost_params, pow_salt, puzzle_input),
+                    (pow_hash, extra_data),
+                )| {
+                    Header {
+                        parent_hash,
+                        height,
+                        timestamp,
+                        proposer,
+                        state_root,
+                        transactions_root,
+                        difficulty,
+                        cost_params,
+                        pow_salt,
+                        puzzle_input,
+                        pow_hash,
+                        extra_data,
+                    }
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn prop_transaction_round_trip(transaction in arb_transaction()) {
+            let bytes = transaction.to_rlp();
+            let decoded = Transaction::from_rlp(&bytes).unwrap();
+            prop_assert_eq!(decoded.to_rlp(), bytes);
+            prop_assert_eq!(decoded.hash(), transaction.hash());
+            prop_assert_eq!(&decoded, &transaction);
+
+            let json = serde_json::to_string(&transaction).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Transaction>(&json).unwrap(), transaction);
+        }
+
+        #[test]
+        fn prop_block_round_trip(
+            header in arb_header(),
+            transactions in prop::collection::vec(arb_transaction(), 0..8),
+        ) {
+            let block = Block::new(header, Body { transactions });
+            let bytes = block.to_rlp();
+            let decoded = Block::from_rlp(&bytes).unwrap();
+            prop_assert_eq!(decoded.to_rlp(), bytes);
+            prop_assert_eq!(decoded.hash(), block.hash());
+            prop_assert_eq!(&decoded, &block);
+
+            let json = serde_json::to_string(&block).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Block>(&json).unwrap(), block);
+        }
+
+        #[test]
+        fn prop_truncated_encoding_rejected(transaction in arb_transaction(), cut in 1usize..16) {
+            let bytes = transaction.to_rlp();
+            let cut = cut.min(bytes.len());
+            prop_assert!(Transaction::from_rlp(&bytes[..bytes.len() - cut]).is_err());
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+
+use super::{address_from_rlp, decode_canonical, hash_of, BlockError, Hash, MAX_TRANSACTION_SIZE};
+use crate::utils::address::Address;
+use crate::utils::crypto::{encode_tagged, verify_tagged, CryptoError, SignatureScheme};
+use crate::utils::hashing::Domain;
+
+/// Number of RLP fields of a transaction, the last being the signature.
+const TRANSACTION_FIELDS: usize = 9;
+
+/// A value transfer or contract call, signed by its sender.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    /// Chain the transaction is valid on, for replay protection.
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_price: u128,
+    pub gas_limit: u64,
+    /// Recipient, or `None` to create a contract.
+    pub to: Option<Address>,
+    pub value: u128,
+    #[serde(with = "hex")]
+    pub data: Vec<u8>,
+    /// Sender's public key, tagged with its signature scheme.
+    #[serde(with = "hex")]
+    pub sender_key: Vec<u8>,
+    /// Tagged signature over `signing_hash()`.
+    #[serde(with = "hex")]
+    pub signature: Vec<u8>,
+}
+
+impl Transaction {
+    /// Transaction hash, covering the signature.
+    pub fn hash(&self) -> Hash {
+        hash_of(Domain::Transaction, &self.to_rlp())
+    }
+
+    /// Digest the sender signs: every field but the signature, including
+    /// the sender's tagged key so the scheme can't be swapped afterwards.
+    pub fn signing_hash(&self) -> Hash {
+        let mut stream = RlpStream::new_list(TRANSACTION_FIELDS - 1);
+        self.append_unsigned(&mut stream);
+        hash_of(Domain::TransactionSignature, &stream.out())
+    }
+
+    /// Signs the transaction with a key of scheme `S`, making it the sender.
+    pub fn sign<S: SignatureScheme>(mut self, secret_key: &S::SecretKey) -> Result<Self, CryptoError> {
+        self.sender_key = encode_tagged(S::TAG, &S::public_key(secret_key));
+        self.signature = encode_tagged(S::TAG, &S::sign(secret_key, &self.signing_hash())?);
+        Ok(self)
+    }
+
+    /// Checks that the transaction is signed by `sender_key`.
+    pub fn verify_signature(&self) -> bool {
+        verify_tagged(&self.sender_key, &self.signing_hash(), &self.signature)
+    }
+
+    pub fn to_rlp(&self) -> Vec<u8> {
+        rlp::encode(self).to_vec()
+    }
+
+    pub fn from_rlp(bytes: &[u8]) -> Result<Self, BlockError> {
+        decode_canonical(bytes, MAX_TRANSACTION_SIZE)
+    }
+
+    fn append_unsigned(&self, stream: &mut RlpStream) {
+        stream
+            .append(&self.chain_id)
+            .append(&self.nonce)
+            .append(&self.gas_price)
+            .append(&self.gas_limit);
+        match &self.to {
+            Some(to) => stream.append(&to.as_bytes().as_slice()),
+            None => stream.append_empty_data(),
+        };
+        stream.append(&self.value).append(&self.data).append(&self.sender_key);
+    }
+}
+
+impl Encodable for Transaction {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        stream.begin_list(TRANSACTION_FIELDS);
+        self.append_unsigned(stream);
+        stream.append(&self.signature);
+    }
+}
+
+impl Decodable for Transaction {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        // Also enforced for transactions nested in a block.
+        if rlp.as_raw().len() > MAX_TRANSACTION_SIZE {
+            return Err(DecoderError::Custom("transaction too large"));
+        }
+        if rlp.item_count()? != TRANSACTION_FIELDS {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let to = match rlp.val_at::<Vec<u8>>(4)?.as_slice() {
+            [] => None,
+            bytes => Some(address_from_rlp(bytes)?),
+        };
+        Ok(Self {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            gas_price: rlp.val_at(2)?,
+            gas_limit: rlp.val_at(3)?,
+            to,
+            value: rlp.val_at(5)?,
+            data: rlp.val_at(6)?,
+            sender_key: rlp.val_at(7)?,
+            signature: rlp.val_at(8)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto::{keypair_from_seed, SchemeTag, Secp256k1};
+    use crate::utils::mldsa::MlDsa65;
+
+    fn transfer() -> Transaction {
+        Transaction {
+            chain_id: 1,
+            nonce: 3,
+            gas_price: 1_000_000_000,
+            gas_limit: 21_000,
+            to: Some(Address::from([0x42; 20])),
+            value: 10u128.pow(18),
+            ..Transaction::default()
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let (secret_key, _) = keypair_from_seed(b"validator-0");
+        let transaction = transfer().sign::<Secp256k1>(&secret_key).unwrap();
+        assert_eq!(transaction.sender_key[0], SchemeTag::Secp256k1 as u8);
+        assert!(transaction.verify_signature());
+
+        // The signature covers every field but itself; the hash covers everything.
+        let tampered = Transaction { to: None, ..transaction.clone() };
+        assert!(!tampered.verify_signature());
+        let other_chain = Transaction { chain_id: 2, ..transaction.clone() };
+        assert!(!other_chain.verify_signature());
+        assert!(!transfer().verify_signature());
+
+        let resigned = transaction.clone().sign::<Secp256k1>(&secret_key).unwrap();
+        assert_eq!(resigned.signing_hash(), transaction.signing_hash());
+        assert_ne!(Transaction { signature: vec![], ..transaction.clone() }.hash(), transaction.hash());
+    }
+
+    #[test]
+    fn test_post_quantum_sender() {
+        let (secret_key, _) = MlDsa65::generate_keypair();
+        let transaction = transfer().sign::<MlDsa65>(&secret_key).unwrap();
+        assert_eq!(transaction.sender_key[0], SchemeTag::MlDsa65 as u8);
+        assert!(transaction.verify_signature());
+        assert!(transaction.to_rlp().len() <= MAX_TRANSACTION_SIZE);
+        assert_eq!(Transaction::from_rlp(&transaction.to_rlp()).unwrap(), transaction);
+    }
+
+    #[test]
+    fn test_contract_creation_round_trip() {
+        let creation = Transaction { to: None, data: vec![0x60; 64], ..transfer() };
+        let decoded = Transaction::from_rlp(&creation.to_rlp()).unwrap();
+        assert_eq!(decoded.to, None);
+        assert_eq!(decoded, creation);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let oversized = Transaction { data: vec![0; MAX_TRANSACTION_SIZE], ..transfer() };
+        let bytes = oversized.to_rlp();
+        assert_eq!(
+            Transaction::from_rlp(&bytes),
+            Err(BlockError::TooLarge { size: bytes.len(), max: MAX_TRANSACTION_SIZE })
+        );
+        // Nested in a block, the transaction limit still applies.
+        let mut stream = RlpStream::new_list(1);
+        stream.append(&oversized);
+        assert!(Rlp::new(&stream.out()).as_list::<Transaction>().is_err());
+    }
+}
@@ -1,3 +1,7 @@
+pub mod schedule;
+
+pub use schedule::{CostParams, CostWeights, DifficultySchedule};
+
 /// Adjusts the mining difficulty based on block times.
 pub fn adjust_difficulty(block_times: &[u64], target_time: u64) -> i64 {
     if block_times.is_empty() {
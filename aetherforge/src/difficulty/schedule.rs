@@ -0,0 +1,381 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::adjust_difficulty;
+use crate::utils::hashing::{sha3_256_hash, Domain};
+
+/// Denominator for schedule weights, expressed in basis points.
+pub const WEIGHT_DENOMINATOR: u32 = 10_000;
+
+/// Divisor applied to the current difficulty on each retarget step.
+pub const ADJUSTMENT_QUOTIENT: u64 = 8;
+
+/// Custom error type for the difficulty schedule
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    #[error("Weights must sum to {WEIGHT_DENOMINATOR}, got {0}")]
+    InvalidWeights(u64),
+    #[error("Ceiling is below the base value for {0}")]
+    InvalidBounds(&'static str),
+}
+
+/// Share of the chain difficulty assigned to each PoW cost dimension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedCostWeights")]
+pub struct CostWeights {
+    pub memory: u32,
+    pub compute: u32,
+    pub puzzle: u32,
+}
+
+impl CostWeights {
+    /// Checks that the weights add up to `WEIGHT_DENOMINATOR`.
+    pub fn validate(&self) -> Result<(), ScheduleError> {
+        // Summed as u64 so governance-supplied weights can't overflow.
+        let total = self.memory as u64 + self.compute as u64 + self.puzzle as u64;
+        if total != WEIGHT_DENOMINATOR as u64 {
+            return Err(ScheduleError::InvalidWeights(total));
+        }
+        Ok(())
+    }
+}
+
+/// Wire form of `CostWeights`, validated on deserialization.
+#[derive(Deserialize)]
+struct UncheckedCostWeights {
+    memory: u32,
+    compute: u32,
+    puzzle: u32,
+}
+
+impl TryFrom<UncheckedCostWeights> for CostWeights {
+    type Error = ScheduleError;
+
+    fn try_from(unchecked: UncheckedCostWeights) -> Result<Self, Self::Error> {
+        let weights = Self { memory: unchecked.memory, compute: unchecked.compute, puzzle: unchecked.puzzle };
+        weights.validate()?;
+        Ok(weights)
+    }
+}
+
+impl Default for CostWeights {
+    fn default() -> Self {
+        Self { memory: 5_000, compute: 3_000, puzzle: 2_000 }
+    }
+}
+
+/// Concrete cost parameters derived from a difficulty, committed into the block header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostParams {
+    /// Argon2 memory cost in KiB.
+    pub argon2_mem_cost: u32,
+    /// Argon2 number of passes.
+    pub argon2_time_cost: u32,
+    /// Side length of the square matrix fed to `matrix_operation`.
+    pub matrix_size: u32,
+    /// Difficulty level handed to the puzzle solvers.
+    pub puzzle_difficulty: u8,
+}
+
+impl CostParams {
+    /// Canonical big-endian encoding of the parameters.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(13);
+        bytes.extend_from_slice(&self.argon2_mem_cost.to_be_bytes());
+        bytes.extend_from_slice(&self.argon2_time_cost.to_be_bytes());
+        bytes.extend_from_slice(&self.matrix_size.to_be_bytes());
+        bytes.push(self.puzzle_difficulty);
+        bytes
+    }
+
+    /// Commitment to the parameters for inclusion in the header.
+    pub fn commitment(&self) -> Vec<u8> {
+        sha3_256_hash(Domain::CostParams, &self.to_bytes())
+    }
+}
+
+impl Default for CostParams {
+    fn default() -> Self {
+        Self {
+            argon2_mem_cost: 65536,
+            argon2_time_cost: 3,
+            matrix_size: 64,
+            puzzle_difficulty: 1,
+        }
+    }
+}
+
+/// Maps the chain difficulty onto the Argon2, matrix and puzzle cost knobs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedSchedule")]
+pub struct DifficultySchedule {
+    weights: CostWeights,
+    base: CostParams,
+    ceiling: CostParams,
+}
+
+/// Wire form of `DifficultySchedule`, validated through `DifficultySchedule::new`.
+#[derive(Deserialize)]
+struct UncheckedSchedule {
+    weights: CostWeights,
+    base: CostParams,
+    ceiling: CostParams,
+}
+
+impl TryFrom<UncheckedSchedule> for DifficultySchedule {
+    type Error = ScheduleError;
+
+    fn try_from(unchecked: UncheckedSchedule) -> Result<Self, Self::Error> {
+        Self::new(unchecked.weights, unchecked.base, unchecked.ceiling)
+    }
+}
+
+impl DifficultySchedule {
+    /// Creates a schedule, rejecting weights that don't sum to the denominator
+    /// and ceilings that sit below the base parameters.
+    pub fn new(weights: CostWeights, base: CostParams, ceiling: CostParams) -> Result<Self, ScheduleError> {
+        weights.validate()?;
+        if ceiling.argon2_mem_cost < base.argon2_mem_cost {
+            return Err(ScheduleError::InvalidBounds("argon2_mem_cost"));
+        }
+        if ceiling.argon2_time_cost < base.argon2_time_cost {
+            return Err(ScheduleError::InvalidBounds("argon2_time_cost"));
+        }
+        if ceiling.matrix_size < base.matrix_size {
+            return Err(ScheduleError::InvalidBounds("matrix_size"));
+        }
+        if ceiling.puzzle_difficulty < base.puzzle_difficulty {
+            return Err(ScheduleError::InvalidBounds("puzzle_difficulty"));
+        }
+        Ok(Self { weights, base, ceiling })
+    }
+
+    pub fn weights(&self) -> CostWeights {
+        self.weights
+    }
+
+    /// Replaces the weights, e.g. after a governance vote.
+    pub fn set_weights(&mut self, weights: CostWeights) -> Result<(), ScheduleError> {
+        weights.validate()?;
+        self.weights = weights;
+        Ok(())
+    }
+
+    /// Derives the cost parameters for a chain difficulty.
+    ///
+    /// Each dimension receives `1 + (difficulty - 1) * weight` units of work.
+    /// Memory scales Argon2 memory until the ceiling, then spills into passes;
+    /// compute scales the matrix side with the cube root, since the product is O(n^3);
+    /// puzzle difficulty grows with the base-2 logarithm.
+    pub fn derive(&self, difficulty: u64) -> CostParams {
+        let difficulty = difficulty.max(1);
+        let memory_units = Self::units(difficulty, self.weights.memory);
+        let compute_units = Self::units(difficulty, self.weights.compute);
+        let puzzle_units = Self::units(difficulty, self.weights.puzzle);
+
+        let wanted_mem = (self.base.argon2_mem_cost as u128) * memory_units as u128;
+        let argon2_mem_cost = wanted_mem.min(self.ceiling.argon2_mem_cost as u128) as u32;
+        let spill = wanted_mem.div_ceil(self.ceiling.argon2_mem_cost.max(1) as u128).max(1);
+        let wanted_time = (self.base.argon2_time_cost as u128) * spill;
+        let argon2_time_cost = wanted_time.min(self.ceiling.argon2_time_cost as u128) as u32;
+
+        let wanted_size = (self.base.matrix_size as u64).saturating_mul(integer_cbrt(compute_units));
+        let matrix_size = wanted_size.min(self.ceiling.matrix_size as u64) as u32;
+
+        let wanted_level = self.base.puzzle_difficulty as u64 + puzzle_units.ilog2() as u64;
+        let puzzle_difficulty = wanted_level.min(self.ceiling.puzzle_difficulty as u64) as u8;
+
+        CostParams { argon2_mem_cost, argon2_time_cost, matrix_size, puzzle_difficulty }
+    }
+
+    /// Computes the next chain difficulty from recent block times.
+    pub fn retarget(&self, current: u64, block_times: &[u64], target_time: u64) -> u64 {
+        let step = (current / ADJUSTMENT_QUOTIENT).max(1);
+        match adjust_difficulty(block_times, target_time) {
+            1 => current.saturating_add(step),
+            -1 => current.saturating_sub(step).max(1),
+            _ => current.max(1),
+        }
+    }
+
+    fn units(difficulty: u64, weight: u32) -> u64 {
+        let scaled = (difficulty as u128 - 1) * weight as u128 / WEIGHT_DENOMINATOR as u128;
+        1 + scaled.min(u64::MAX as u128 - 1) as u64
+    }
+}
+
+impl Default for DifficultySchedule {
+    fn default() -> Self {
+        Self {
+            weights: CostWeights::default(),
+            base: CostParams::default(),
+            ceiling: CostParams {
+                argon2_mem_cost: 1 << 21, // 2 GiB
+                argon2_time_cost: 64,
+                matrix_size: 1024,
+                puzzle_difficulty: 3,
+            },
+        }
+    }
+}
+
+/// Largest `r` such that `r^3 <= n`.
+fn integer_cbrt(n: u64) -> u64 {
+    let cube = |r: u64| r.checked_mul(r).and_then(|sq| sq.checked_mul(r));
+    let mut root = (n as f64).cbrt() as u64;
+    while cube(root).is_none_or(|c| c > n) {
+        root -= 1;
+    }
+    while cube(root + 1).is_some_and(|c| c <= n) {
+        root += 1;
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_base_difficulty() {
+        // Difficulty 1 (and 0) should yield the base parameters.
+        let schedule = DifficultySchedule::default();
+        assert_eq!(schedule.derive(1), CostParams::default());
+        assert_eq!(schedule.derive(0), CostParams::default());
+    }
+
+    #[test]
+    fn test_derive_is_monotonic() {
+        let schedule = DifficultySchedule::default();
+        let mut previous = schedule.derive(1);
+        for difficulty in [2, 10, 100, 1_000, 100_000, u64::MAX] {
+            let params = schedule.derive(difficulty);
+            assert!(params.argon2_mem_cost >= previous.argon2_mem_cost);
+            assert!(params.argon2_time_cost >= previous.argon2_time_cost);
+            assert!(params.matrix_size >= previous.matrix_size);
+            assert!(params.puzzle_difficulty >= previous.puzzle_difficulty);
+            previous = params;
+        }
+
+        // Very large difficulties are capped at the ceiling.
+        assert_eq!(previous.argon2_mem_cost, 1 << 21);
+        assert_eq!(previous.argon2_time_cost, 64);
+        assert_eq!(previous.matrix_size, 1024);
+        assert_eq!(previous.puzzle_difficulty, 3);
+    }
+
+    #[test]
+    fn test_weights_rebalance_work() {
+        // All weight on memory leaves compute and puzzle at their base values.
+        let memory_only = CostWeights { memory: WEIGHT_DENOMINATOR, compute: 0, puzzle: 0 };
+        let schedule = DifficultySchedule::new(
+            memory_only,
+            CostParams::default(),
+            DifficultySchedule::default().ceiling,
+        )
+        .unwrap();
+        let params = schedule.derive(9);
+        assert_eq!(params.argon2_mem_cost, 65536 * 9);
+        assert_eq!(params.matrix_size, 64);
+        assert_eq!(params.puzzle_difficulty, 1);
+
+        // Governance moves all weight to compute: 8 units doubles the matrix side.
+        let mut schedule = schedule;
+        let compute_only = CostWeights { memory: 0, compute: WEIGHT_DENOMINATOR, puzzle: 0 };
+        schedule.set_weights(compute_only).unwrap();
+        let params = schedule.derive(8);
+        assert_eq!(params.argon2_mem_cost, 65536);
+        assert_eq!(params.matrix_size, 128);
+        assert_eq!(params.puzzle_difficulty, 1);
+    }
+
+    #[test]
+    fn test_memory_spills_into_time_cost() {
+        let weights = CostWeights { memory: WEIGHT_DENOMINATOR, compute: 0, puzzle: 0 };
+        let base = CostParams { argon2_mem_cost: 1024, argon2_time_cost: 2, ..CostParams::default() };
+        let ceiling = CostParams {
+            argon2_mem_cost: 4096,
+            argon2_time_cost: 100,
+            ..DifficultySchedule::default().ceiling
+        };
+        let schedule = DifficultySchedule::new(weights, base, ceiling).unwrap();
+
+        // 4 units fit in memory exactly.
+        let params = schedule.derive(4);
+        assert_eq!((params.argon2_mem_cost, params.argon2_time_cost), (4096, 2));
+
+        // 12 units need three times the memory ceiling, so passes triple.
+        let params = schedule.derive(12);
+        assert_eq!((params.argon2_mem_cost, params.argon2_time_cost), (4096, 6));
+    }
+
+    #[test]
+    fn test_invalid_schedule() {
+        let bad_weights = CostWeights { memory: 1, compute: 1, puzzle: 1 };
+        let mut schedule = DifficultySchedule::default();
+        assert_eq!(schedule.set_weights(bad_weights), Err(ScheduleError::InvalidWeights(3)));
+        assert_eq!(schedule.weights(), CostWeights::default());
+
+        let low_ceiling = CostParams { matrix_size: 1, ..CostParams::default() };
+        assert_eq!(
+            DifficultySchedule::new(CostWeights::default(), CostParams::default(), low_ceiling),
+            Err(ScheduleError::InvalidBounds("matrix_size"))
+        );
+    }
+
+    #[test]
+    fn test_weights_do_not_overflow() {
+        let huge = CostWeights { memory: u32::MAX, compute: u32::MAX, puzzle: 2 };
+        assert_eq!(huge.validate(), Err(ScheduleError::InvalidWeights(2 * u32::MAX as u64 + 2)));
+        let wrapping = CostWeights { memory: u32::MAX, compute: WEIGHT_DENOMINATOR + 1, puzzle: 0 };
+        assert!(wrapping.validate().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_validates() {
+        let schedule = DifficultySchedule::default();
+        let json = serde_json::to_string(&schedule).unwrap();
+        assert_eq!(serde_json::from_str::<DifficultySchedule>(&json).unwrap(), schedule);
+
+        let bad_weights = r#"{"memory": 1, "compute": 1, "puzzle": 1}"#;
+        assert!(serde_json::from_str::<CostWeights>(bad_weights).is_err());
+
+        let mut value = serde_json::to_value(&schedule).unwrap();
+        value["ceiling"]["matrix_size"] = 1.into();
+        assert!(serde_json::from_value::<DifficultySchedule>(value).is_err());
+    }
+
+    #[test]
+    fn test_retarget() {
+        let schedule = DifficultySchedule::default();
+
+        // Slow blocks lower the difficulty, fast blocks raise it.
+        assert_eq!(schedule.retarget(800, &[70, 80, 75], 60), 700);
+        assert_eq!(schedule.retarget(800, &[50, 40, 55], 60), 900);
+
+        // No history keeps the difficulty, and it never drops below 1.
+        assert_eq!(schedule.retarget(800, &[], 60), 800);
+        assert_eq!(schedule.retarget(1, &[70, 80, 75], 60), 1);
+    }
+
+    #[test]
+    fn test_commitment() {
+        let params = CostParams::default();
+        assert_eq!(params.to_bytes().len(), 13);
+        assert_eq!(params.commitment().len(), 32);
+
+        let other = CostParams { puzzle_difficulty: 2, ..params };
+        assert_ne!(params.commitment(), other.commitment());
+    }
+
+    #[test]
+    fn test_integer_cbrt() {
+        assert_eq!(integer_cbrt(0), 0);
+        assert_eq!(integer_cbrt(1), 1);
+        assert_eq!(integer_cbrt(7), 1);
+        assert_eq!(integer_cbrt(8), 2);
+        assert_eq!(integer_cbrt(26), 2);
+        assert_eq!(integer_cbrt(27), 3);
+        assert_eq!(integer_cbrt(u64::MAX), 2_642_245);
+    }
+}
@@ -0,0 +1,408 @@
+pub mod rules;
+
+pub use rules::{Ghost, HeaviestChain, Hybrid};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::pos::staking::ValidatorId;
+
+pub type BlockHash = Vec<u8>;
+
+/// Most blocks held back waiting for their parent; the oldest is dropped first.
+pub const MAX_ORPHANS: usize = 1024;
+
+/// A block connected to the tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockNode {
+    pub hash: BlockHash,
+    pub parent: Option<BlockHash>,
+    pub height: u64,
+    /// Proof-of-work done by this block alone.
+    pub work: u128,
+    /// Work from genesis up to and including this block.
+    pub cumulative_work: u128,
+}
+
+/// A validator's most recent vote for a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatestVote {
+    pub block_hash: BlockHash,
+    pub slot: u64,
+    pub stake: u64,
+}
+
+/// Picks the canonical head of a block tree.
+pub trait ForkChoice {
+    /// Head among `root` and its descendants.
+    fn head_from(&self, tree: &BlockTree, root: &[u8]) -> BlockHash;
+
+    /// Head of the whole tree.
+    fn head(&self, tree: &BlockTree) -> BlockHash {
+        self.head_from(tree, tree.genesis())
+    }
+}
+
+/// Blocks, latest validator votes and finalized checkpoints seen so far.
+///
+/// Blocks whose parent hasn't arrived yet are held back until it does, and
+/// votes and finalizations may refer to blocks not yet known, so the tree
+/// ends up the same whatever order its inputs arrive in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockTree {
+    genesis: BlockHash,
+    blocks: BTreeMap<BlockHash, BlockNode>,
+    children: BTreeMap<BlockHash, BTreeSet<BlockHash>>,
+    orphans: BTreeMap<BlockHash, BTreeMap<BlockHash, u128>>,
+    /// Every held-back block with its claimed parent, oldest first.
+    orphan_queue: VecDeque<(BlockHash, BlockHash)>,
+    orphan_hashes: BTreeSet<BlockHash>,
+    votes: BTreeMap<ValidatorId, LatestVote>,
+    finalized: BTreeSet<BlockHash>,
+}
+
+impl BlockTree {
+    pub fn new(genesis: BlockHash, genesis_work: u128) -> Self {
+        let node = BlockNode {
+            hash: genesis.clone(),
+            parent: None,
+            height: 0,
+            work: genesis_work,
+            cumulative_work: genesis_work,
+        };
+        Self {
+            genesis: genesis.clone(),
+            blocks: BTreeMap::from([(genesis, node)]),
+            children: BTreeMap::new(),
+            orphans: BTreeMap::new(),
+            orphan_queue: VecDeque::new(),
+            orphan_hashes: BTreeSet::new(),
+            votes: BTreeMap::new(),
+            finalized: BTreeSet::new(),
+        }
+    }
+
+    pub fn genesis(&self) -> &[u8] {
+        &self.genesis
+    }
+
+    pub fn get(&self, hash: &[u8]) -> Option<&BlockNode> {
+        self.blocks.get(hash)
+    }
+
+    pub fn contains(&self, hash: &[u8]) -> bool {
+        self.blocks.contains_key(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Number of blocks waiting for their parent.
+    pub fn orphan_count(&self) -> usize {
+        self.orphan_hashes.len()
+    }
+
+    /// Children of `hash`, in hash order.
+    pub fn children(&self, hash: &[u8]) -> impl Iterator<Item = &BlockNode> {
+        self.children
+            .get(hash)
+            .into_iter()
+            .flatten()
+            .filter_map(|child| self.blocks.get(child))
+    }
+
+    pub fn votes(&self) -> &BTreeMap<ValidatorId, LatestVote> {
+        &self.votes
+    }
+
+    /// Whether `ancestor` is `descendant` or one of its ancestors.
+    pub fn is_ancestor(&self, ancestor: &[u8], descendant: &[u8]) -> bool {
+        let (Some(ancestor), Some(mut node)) = (self.blocks.get(ancestor), self.blocks.get(descendant)) else {
+            return false;
+        };
+        while node.height > ancestor.height {
+            match node.parent.as_ref().and_then(|parent| self.blocks.get(parent)) {
+                Some(parent) => node = parent,
+                None => return false,
+            }
+        }
+        node.hash == ancestor.hash
+    }
+
+    /// Adds a block, returning false if it was already known. A block whose
+    /// parent is missing is kept aside and connected once the parent arrives;
+    /// at most `MAX_ORPHANS` are kept, evicting the oldest.
+    pub fn add_block(&mut self, hash: BlockHash, parent: BlockHash, work: u128) -> bool {
+        if self.blocks.contains_key(&hash) || self.orphan_hashes.contains(&hash) {
+            return false;
+        }
+        if !self.blocks.contains_key(&parent) {
+            if self.orphan_queue.len() >= MAX_ORPHANS {
+                self.evict_oldest_orphan();
+            }
+            self.orphan_hashes.insert(hash.clone());
+            self.orphan_queue.push_back((hash.clone(), parent.clone()));
+            self.orphans.entry(parent).or_default().insert(hash, work);
+            return true;
+        }
+
+        let mut pending = vec![(hash, parent, work)];
+        while let Some((hash, parent, work)) = pending.pop() {
+            let parent_node = &self.blocks[&parent];
+            let node = BlockNode {
+                hash: hash.clone(),
+                parent: Some(parent.clone()),
+                height: parent_node.height + 1,
+                work,
+                cumulative_work: parent_node.cumulative_work.saturating_add(work),
+            };
+            self.blocks.insert(hash.clone(), node);
+            self.children.entry(parent).or_default().insert(hash.clone());
+            let children = self.orphans.remove(&hash).unwrap_or_default();
+            if !children.is_empty() {
+                self.orphan_queue.retain(|(_, parent)| *parent != hash);
+            }
+            for (child, work) in children {
+                self.orphan_hashes.remove(&child);
+                pending.push((child, hash.clone(), work));
+            }
+        }
+        true
+    }
+
+    fn evict_oldest_orphan(&mut self) {
+        let Some((hash, parent)) = self.orphan_queue.pop_front() else {
+            return;
+        };
+        self.orphan_hashes.remove(&hash);
+        if let Some(siblings) = self.orphans.get_mut(&parent) {
+            siblings.remove(&hash);
+            if siblings.is_empty() {
+                self.orphans.remove(&parent);
+            }
+        }
+    }
+
+    /// Records a validator's vote, keeping only its latest one. Between two
+    /// votes for the same slot, the lower block hash is kept so that the
+    /// result doesn't depend on arrival order.
+    pub fn add_vote(&mut self, validator: &ValidatorId, block_hash: BlockHash, slot: u64, stake: u64) -> bool {
+        let newer = self.votes.get(validator).is_none_or(|latest| {
+            slot > latest.slot || (slot == latest.slot && block_hash < latest.block_hash)
+        });
+        if newer {
+            self.votes.insert(*validator, LatestVote { block_hash, slot, stake });
+        }
+        newer
+    }
+
+    /// Marks a block as finalized by the PoS checkpoint gadget.
+    pub fn finalize(&mut self, hash: BlockHash) {
+        self.finalized.insert(hash);
+    }
+
+    /// The highest finalized block connected to the tree, or genesis.
+    pub fn finalized(&self) -> &[u8] {
+        self.finalized
+            .iter()
+            .filter_map(|hash| self.blocks.get(hash))
+            .max_by(|a, b| a.height.cmp(&b.height).then_with(|| b.hash.cmp(&a.hash)))
+            .map_or(self.genesis.as_slice(), |node| node.hash.as_slice())
+    }
+
+    /// Stake of the latest votes in each block's subtree, together with the
+    /// subtree's total work.
+    pub fn subtree_weights(&self) -> BTreeMap<&[u8], (u128, u128)> {
+        let mut weights: BTreeMap<&[u8], (u128, u128)> =
+            self.blocks.values().map(|node| (node.hash.as_slice(), (0, node.work))).collect();
+        for vote in self.votes.values() {
+            if let Some(weight) = weights.get_mut(vote.block_hash.as_slice()) {
+                weight.0 += vote.stake as u128;
+            }
+        }
+
+        let mut by_height: Vec<&BlockNode> = self.blocks.values().collect();
+        by_height.sort_by_key(|node| std::cmp::Reverse(node.height));
+        for node in by_height {
+            if let Some(parent) = &node.parent {
+                let (stake, work) = weights[node.hash.as_slice()];
+                let parent_weight = weights.get_mut(parent.as_slice()).unwrap();
+                parent_weight.0 += stake;
+                parent_weight.1 = parent_weight.1.saturating_add(work);
+            }
+        }
+        weights
+    }
+
+    /// `root` and all its descendants.
+    pub fn descendants<'a>(&'a self, root: &[u8]) -> Vec<&'a BlockNode> {
+        let mut stack: Vec<&BlockNode> = self.blocks.get(root).into_iter().collect();
+        let mut descendants = Vec::new();
+        while let Some(node) = stack.pop() {
+            stack.extend(self.children(&node.hash));
+            descendants.push(node);
+        }
+        descendants
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::address::testing::ALICE;
+    use proptest::prelude::*;
+
+    fn hash(index: usize) -> BlockHash {
+        format!("block {index}").into_bytes()
+    }
+
+    #[test]
+    fn test_out_of_order_blocks() {
+        let mut tree = BlockTree::new(hash(0), 1);
+        assert!(tree.add_block(hash(2), hash(1), 5));
+        assert!(!tree.contains(&hash(2)));
+        assert_eq!(tree.orphan_count(), 1);
+
+        assert!(tree.add_block(hash(1), hash(0), 3));
+        assert_eq!(tree.orphan_count(), 0);
+        assert_eq!(tree.get(&hash(2)).unwrap().height, 2);
+        assert_eq!(tree.get(&hash(2)).unwrap().cumulative_work, 9);
+        assert!(tree.is_ancestor(&hash(0), &hash(2)));
+        assert!(!tree.is_ancestor(&hash(2), &hash(1)));
+
+        // Duplicates are ignored.
+        assert!(!tree.add_block(hash(2), hash(1), 5));
+    }
+
+    #[test]
+    fn test_orphan_claiming_two_parents_is_linked_once() {
+        let mut tree = BlockTree::new(hash(0), 1);
+        assert!(tree.add_block(hash(3), hash(1), 5));
+        assert!(!tree.add_block(hash(3), hash(2), 5));
+        assert_eq!(tree.orphan_count(), 1);
+
+        tree.add_block(hash(1), hash(0), 1);
+        tree.add_block(hash(2), hash(0), 1);
+        assert_eq!(tree.orphan_count(), 0);
+        assert_eq!(tree.get(&hash(3)).unwrap().parent, Some(hash(1)));
+        assert_eq!(tree.children(&hash(2)).count(), 0);
+        assert_eq!(tree.subtree_weights()[hash(0).as_slice()].1, 1 + 1 + 1 + 5);
+    }
+
+    #[test]
+    fn test_orphan_pool_is_bounded() {
+        let mut tree = BlockTree::new(hash(0), 1);
+        let missing = |index: usize| format!("missing {index}").into_bytes();
+        for index in 1..=MAX_ORPHANS + 1 {
+            assert!(tree.add_block(hash(index), missing(index), 1));
+        }
+        assert_eq!(tree.orphan_count(), MAX_ORPHANS);
+
+        // The oldest orphan was evicted, the newest is still connected when its parent arrives.
+        tree.add_block(missing(1), hash(0), 1);
+        assert!(!tree.contains(&hash(1)));
+        tree.add_block(missing(MAX_ORPHANS + 1), hash(0), 1);
+        assert!(tree.contains(&hash(MAX_ORPHANS + 1)));
+        assert_eq!(tree.orphan_count(), MAX_ORPHANS - 1);
+    }
+
+    #[test]
+    fn test_latest_vote_wins() {
+        let mut tree = BlockTree::new(hash(0), 1);
+        assert!(tree.add_vote(&ALICE, hash(1), 5, 100));
+        assert!(!tree.add_vote(&ALICE, hash(2), 4, 100));
+        assert!(tree.add_vote(&ALICE, hash(3), 6, 100));
+        assert_eq!(tree.votes()[&ALICE].block_hash, hash(3));
+    }
+
+    #[derive(Debug, Clone)]
+    enum Event {
+        Block { hash: BlockHash, parent: BlockHash, work: u128 },
+        Vote { validator: ValidatorId, block_hash: BlockHash, slot: u64, stake: u64 },
+        Finalize(BlockHash),
+    }
+
+    fn apply(events: &[Event]) -> BlockTree {
+        let mut tree = BlockTree::new(hash(0), 1);
+        for event in events.iter().cloned() {
+            match event {
+                Event::Block { hash, parent, work } => {
+                    tree.add_block(hash, parent, work);
+                }
+                Event::Vote { validator, block_hash, slot, stake } => {
+                    tree.add_vote(&validator, block_hash, slot, stake);
+                }
+                Event::Finalize(hash) => tree.finalize(hash),
+            }
+        }
+        tree
+    }
+
+    /// A random block tree with votes and a finalized block, as two
+    /// independently shuffled arrival orders.
+    fn scenario() -> impl Strategy<Value = (Vec<Event>, Vec<Event>)> {
+        (2..40usize)
+            .prop_flat_map(|blocks| {
+                let parents: Vec<_> = (1..blocks).map(|index| 0..index).collect();
+                let works = prop::collection::vec(1..100u128, blocks - 1);
+                let votes = prop::collection::vec((0..5usize, 0..blocks, 0..10u64), 0..20);
+                (parents, works, votes, 0..blocks)
+            })
+            .prop_flat_map(|(parents, works, votes, finalized)| {
+                let mut events: Vec<Event> = parents
+                    .iter()
+                    .zip(works)
+                    .enumerate()
+                    .map(|(index, (parent, work))| Event::Block { hash: hash(index + 1), parent: hash(*parent), work })
+                    .collect();
+                events.extend(votes.into_iter().map(|(validator, block, slot)| Event::Vote {
+                    validator: ValidatorId::repeat_byte(validator as u8),
+                    block_hash: hash(block),
+                    slot,
+                    stake: (validator as u64 + 1) * 10,
+                }));
+                events.push(Event::Finalize(hash(finalized)));
+                (Just(events.clone()).prop_shuffle(), Just(events).prop_shuffle())
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_heads_independent_of_arrival_order((first, second) in scenario()) {
+            let a = apply(&first);
+            let b = apply(&second);
+            prop_assert_eq!(&a, &b);
+            prop_assert_eq!(a.orphan_count(), 0);
+
+            prop_assert_eq!(HeaviestChain.head(&a), HeaviestChain.head(&b));
+            prop_assert_eq!(Ghost.head(&a), Ghost.head(&b));
+            prop_assert_eq!(Hybrid::new(Ghost).head(&a), Hybrid::new(Ghost).head(&b));
+            prop_assert_eq!(Hybrid::new(HeaviestChain).head(&a), Hybrid::new(HeaviestChain).head(&b));
+        }
+
+        #[test]
+        fn prop_heads_are_consistent((events, _) in scenario()) {
+            let tree = apply(&events);
+
+            // The heaviest chain ends at a block with maximal cumulative work.
+            let heaviest = HeaviestChain.head(&tree);
+            let max_work = tree.descendants(tree.genesis()).iter().map(|node| node.cumulative_work).max();
+            prop_assert_eq!(Some(tree.get(&heaviest).unwrap().cumulative_work), max_work);
+
+            // GHOST always walks down to a leaf.
+            let ghost = Ghost.head(&tree);
+            prop_assert_eq!(tree.children(&ghost).count(), 0);
+
+            // The hybrid head never leaves the finalized block's subtree.
+            for head in [Hybrid::new(Ghost).head(&tree), Hybrid::new(HeaviestChain).head(&tree)] {
+                prop_assert!(tree.is_ancestor(tree.finalized(), &head));
+                prop_assert_eq!(tree.children(&head).count(), 0);
+            }
+        }
+    }
+}
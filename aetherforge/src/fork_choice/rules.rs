@@ -0,0 +1,133 @@
+use std::cmp::Ordering;
+
+use super::{BlockHash, BlockNode, BlockTree, ForkChoice};
+
+/// Follows the chain tip with the most cumulative proof-of-work.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeaviestChain;
+
+impl ForkChoice for HeaviestChain {
+    fn head_from(&self, tree: &BlockTree, root: &[u8]) -> BlockHash {
+        tree.descendants(root)
+            .into_iter()
+            .max_by(|a, b| a.cumulative_work.cmp(&b.cumulative_work).then_with(|| lower_hash_first(a, b)))
+            .map_or_else(|| root.to_vec(), |node| node.hash.clone())
+    }
+}
+
+/// Greedy heaviest observed subtree: from the root, repeatedly steps into the
+/// child whose subtree carries the most stake from validators' latest votes.
+/// Ties, including the case with no votes at all, go to the subtree with the
+/// most work.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Ghost;
+
+impl ForkChoice for Ghost {
+    fn head_from(&self, tree: &BlockTree, root: &[u8]) -> BlockHash {
+        if !tree.contains(root) {
+            return root.to_vec();
+        }
+        let weights = tree.subtree_weights();
+        let mut head = root;
+        while let Some(child) = tree.children(head).max_by(|a, b| {
+            let (a_weight, b_weight) = (weights[a.hash.as_slice()], weights[b.hash.as_slice()]);
+            a_weight.cmp(&b_weight).then_with(|| lower_hash_first(a, b))
+        }) {
+            head = &child.hash;
+        }
+        head.to_vec()
+    }
+}
+
+/// Runs an inner rule from the latest PoS-finalized checkpoint, so the head
+/// can never be reorganised onto a chain that doesn't contain it, however
+/// much work that chain has.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Hybrid<F> {
+    inner: F,
+}
+
+impl<F: ForkChoice> Hybrid<F> {
+    pub fn new(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+impl<F: ForkChoice> ForkChoice for Hybrid<F> {
+    fn head_from(&self, tree: &BlockTree, root: &[u8]) -> BlockHash {
+        let finalized = tree.finalized();
+        if tree.is_ancestor(root, finalized) {
+            self.inner.head_from(tree, finalized)
+        } else {
+            self.inner.head_from(tree, root)
+        }
+    }
+}
+
+fn lower_hash_first(a: &BlockNode, b: &BlockNode) -> Ordering {
+    b.hash.cmp(&a.hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::address::testing::{ALICE, BOB, CHARLIE};
+
+    fn hash(name: &str) -> BlockHash {
+        name.as_bytes().to_vec()
+    }
+
+    /// genesis -> a1 -> {a2, a3} (light blocks) and genesis -> b1 (one heavy block).
+    fn forked_tree() -> BlockTree {
+        let mut tree = BlockTree::new(hash("genesis"), 1);
+        tree.add_block(hash("a1"), hash("genesis"), 10);
+        tree.add_block(hash("a2"), hash("a1"), 10);
+        tree.add_block(hash("a3"), hash("a1"), 5);
+        tree.add_block(hash("b1"), hash("genesis"), 30);
+        tree
+    }
+
+    #[test]
+    fn test_heaviest_chain() {
+        let tree = forked_tree();
+        assert_eq!(HeaviestChain.head(&tree), hash("b1"));
+        assert_eq!(HeaviestChain.head_from(&tree, &hash("a1")), hash("a2"));
+    }
+
+    #[test]
+    fn test_ghost() {
+        let mut tree = forked_tree();
+
+        // Without votes, b1 (30) outweighs the whole a-subtree (25) until a4 arrives.
+        assert_eq!(Ghost.head(&tree), hash("b1"));
+        tree.add_block(hash("a4"), hash("a3"), 20);
+        assert_eq!(Ghost.head(&tree), hash("a4"));
+
+        // Votes outweigh work.
+        tree.add_vote(&ALICE, hash("b1"), 1, 100);
+        tree.add_vote(&BOB, hash("a2"), 1, 60);
+        assert_eq!(Ghost.head(&tree), hash("b1"));
+        tree.add_vote(&CHARLIE, hash("a4"), 1, 50);
+        assert_eq!(Ghost.head(&tree), hash("a2"));
+    }
+
+    #[test]
+    fn test_hybrid_never_reorgs_past_finality() {
+        let mut tree = forked_tree();
+        tree.finalize(hash("a1"));
+        assert_eq!(HeaviestChain.head(&tree), hash("b1"));
+        assert_eq!(Hybrid::new(HeaviestChain).head(&tree), hash("a2"));
+
+        // Even a much heavier competing chain doesn't move the head off a1.
+        tree.add_block(hash("b2"), hash("b1"), 1_000);
+        tree.add_vote(&ALICE, hash("b2"), 2, 1_000);
+        assert_eq!(Hybrid::new(HeaviestChain).head(&tree), hash("a2"));
+        assert_eq!(Hybrid::new(Ghost).head(&tree), hash("a2"));
+
+        // Finalization of an unknown block is ignored until it arrives.
+        tree.finalize(hash("a5"));
+        assert_eq!(tree.finalized(), hash("a1").as_slice());
+        tree.add_block(hash("a5"), hash("a3"), 1);
+        assert_eq!(Hybrid::new(HeaviestChain).head(&tree), hash("a5"));
+    }
+}
@@ -1,3 +1,7 @@
 pub mod pow;
 pub mod pos;
-pub mod difficulty;
\ No newline at end of file
+pub mod difficulty;
+pub mod utils;
+pub mod fork_choice;
+pub mod trie;
+pub mod block;
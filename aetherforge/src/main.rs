@@ -1,6 +1,10 @@
 mod pow;
 mod pos;
 mod difficulty;
+mod utils;
+mod fork_choice;
+mod trie;
+mod block;
 
 fn main() {
     println!("AetherForge: Hybrid Consensus Mining Algorithm");
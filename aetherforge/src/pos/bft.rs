@@ -0,0 +1,941 @@
+use k256::SecretKey;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use super::epoch::ValidatorSnapshot;
+use super::staking::ValidatorId;
+use crate::utils::crypto::{encode_tagged, verify_tagged, Secp256k1, SignatureScheme};
+use crate::utils::hashing::{ConsensusHasher, Domain};
+
+/// Hash of a PoW-produced block being finalized.
+pub type BlockHash = Vec<u8>;
+
+/// Step within a round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Step {
+    Propose,
+    Prevote,
+    Precommit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum VoteType {
+    Prevote,
+    Precommit,
+}
+
+/// A proposer's candidate block for a round. `valid_round` is the round in
+/// which the block last gathered a prevote quorum, if any.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proposal {
+    pub height: u64,
+    pub round: u32,
+    pub block_hash: BlockHash,
+    pub valid_round: Option<u32>,
+    pub proposer: ValidatorId,
+}
+
+/// A prevote or precommit; `block_hash` is `None` for a nil vote.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vote {
+    pub vote_type: VoteType,
+    pub height: u64,
+    pub round: u32,
+    pub block_hash: Option<BlockHash>,
+    pub validator: ValidatorId,
+}
+
+/// A vote signed with the validator's consensus key, as it travels between nodes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedVote {
+    pub vote: Vote,
+    /// Public key of the signing validator, tagged with its signature scheme.
+    pub validator_pubkey: Vec<u8>,
+    /// Tagged signature over `vote_digest(&vote)`.
+    pub signature: Vec<u8>,
+}
+
+/// Digest a validator signs to cast `vote`.
+pub fn vote_digest(vote: &Vote) -> Vec<u8> {
+    let mut hasher = ConsensusHasher::new(Domain::BftVote);
+    hasher
+        .field(&[vote.vote_type as u8])
+        .field_u64(vote.height)
+        .field_u64(vote.round as u64)
+        .field(vote.validator.as_bytes());
+    match &vote.block_hash {
+        Some(block_hash) => hasher.field(&[1]).field(block_hash),
+        None => hasher.field(&[0]),
+    };
+    hasher.finish()
+}
+
+/// Signs `vote` with a secp256k1 key.
+pub fn sign_vote(secret_key: &SecretKey, vote: Vote) -> SignedVote {
+    sign_vote_with::<Secp256k1>(secret_key, vote)
+}
+
+/// Signs `vote` with a key of scheme `S`.
+pub fn sign_vote_with<S: SignatureScheme>(secret_key: &S::SecretKey, vote: Vote) -> SignedVote {
+    let signature = S::sign(secret_key, &vote_digest(&vote)).expect("digest is 32 bytes");
+    SignedVote {
+        vote,
+        validator_pubkey: encode_tagged(S::TAG, &S::public_key(secret_key)),
+        signature: encode_tagged(S::TAG, &signature),
+    }
+}
+
+/// Checks that a vote is signed by its `validator_pubkey`.
+pub fn verify_signed_vote(signed: &SignedVote) -> bool {
+    verify_tagged(&signed.validator_pubkey, &vote_digest(&signed.vote), &signed.signature)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Message {
+    Proposal(Proposal),
+    Vote(Vote),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timeout {
+    pub height: u64,
+    pub round: u32,
+    pub step: Step,
+}
+
+/// Output of the state machine for the caller to carry out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Send the message to every other validator.
+    Broadcast(Message),
+    /// Call `handle_timeout` with the timeout once the duration has elapsed.
+    ScheduleTimeout(Timeout, Duration),
+    /// The block is final at this height.
+    Commit { height: u64, round: u32, block_hash: BlockHash },
+}
+
+/// Base timeouts per step, each growing by `delta` per round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    pub propose: Duration,
+    pub prevote: Duration,
+    pub precommit: Duration,
+    pub delta: Duration,
+}
+
+impl TimeoutConfig {
+    pub fn duration(&self, step: Step, round: u32) -> Duration {
+        let base = match step {
+            Step::Propose => self.propose,
+            Step::Prevote => self.prevote,
+            Step::Precommit => self.precommit,
+        };
+        base + self.delta * round
+    }
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            propose: Duration::from_millis(3_000),
+            prevote: Duration::from_millis(1_000),
+            precommit: Duration::from_millis(1_000),
+            delta: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Deterministic Tendermint-style BFT state machine for one validator.
+///
+/// The machine does no I/O: messages and timeouts are fed in, and the
+/// resulting `Action`s are returned for the caller to perform. Messages must
+/// be authenticated, and proposed blocks checked for valid PoW, before they
+/// are handed in. Quorums are measured by stake: more than two thirds to
+/// lock or commit, more than one third to skip ahead to a later round, all
+/// taken from the epoch's validator snapshot.
+#[derive(Debug, Clone)]
+pub struct BftMachine {
+    id: ValidatorId,
+    validators: ValidatorSnapshot,
+    timeouts: TimeoutConfig,
+
+    height: u64,
+    round: u32,
+    step: Step,
+    candidate: Option<BlockHash>,
+    locked: Option<(BlockHash, u32)>,
+    valid: Option<(BlockHash, u32)>,
+    decision: Option<BlockHash>,
+
+    proposals: BTreeMap<u32, Proposal>,
+    votes: BTreeMap<(u32, VoteType), BTreeMap<ValidatorId, Option<BlockHash>>>,
+    equivocations: Vec<(Vote, Vote)>,
+    signed_votes: BTreeMap<(u32, VoteType, ValidatorId), SignedVote>,
+    signed_equivocations: Vec<(SignedVote, SignedVote)>,
+    prevote_timeout_rounds: BTreeSet<u32>,
+    precommit_timeout_rounds: BTreeSet<u32>,
+    valid_rounds: BTreeSet<u32>,
+    actions: Vec<Action>,
+}
+
+impl BftMachine {
+    pub fn new(id: ValidatorId, validators: ValidatorSnapshot, timeouts: TimeoutConfig) -> Self {
+        Self {
+            id,
+            validators,
+            timeouts,
+            height: 0,
+            round: 0,
+            step: Step::Propose,
+            candidate: None,
+            locked: None,
+            valid: None,
+            decision: None,
+            proposals: BTreeMap::new(),
+            votes: BTreeMap::new(),
+            equivocations: Vec::new(),
+            signed_votes: BTreeMap::new(),
+            signed_equivocations: Vec::new(),
+            prevote_timeout_rounds: BTreeSet::new(),
+            precommit_timeout_rounds: BTreeSet::new(),
+            valid_rounds: BTreeSet::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    pub fn step(&self) -> Step {
+        self.step
+    }
+
+    pub fn decision(&self) -> Option<&BlockHash> {
+        self.decision.as_ref()
+    }
+
+    pub fn locked(&self) -> Option<&(BlockHash, u32)> {
+        self.locked.as_ref()
+    }
+
+    /// Pairs of conflicting votes seen from the same validator, for slashing.
+    pub fn equivocations(&self) -> &[(Vote, Vote)] {
+        &self.equivocations
+    }
+
+    /// Signed copies of conflicting votes received through `handle_signed_vote`,
+    /// ready to be submitted as `RoundEvidence`.
+    pub fn signed_equivocations(&self) -> &[(SignedVote, SignedVote)] {
+        &self.signed_equivocations
+    }
+
+    pub fn validators(&self) -> &ValidatorSnapshot {
+        &self.validators
+    }
+
+    /// Proposer for a round, chosen by stake-weighted selection from the snapshot.
+    pub fn proposer(&self, height: u64, round: u32) -> Option<ValidatorId> {
+        let mut seed = height.to_be_bytes().to_vec();
+        seed.extend_from_slice(&round.to_be_bytes());
+        self.validators.select(&seed)
+    }
+
+    /// Starts consensus on `height`, proposing `candidate` when this validator
+    /// is the proposer and holds no earlier valid block.
+    pub fn start_height(&mut self, height: u64, candidate: Option<BlockHash>) -> Vec<Action> {
+        self.height = height;
+        self.candidate = candidate;
+        self.locked = None;
+        self.valid = None;
+        self.decision = None;
+        self.proposals.clear();
+        self.votes.clear();
+        self.signed_votes.clear();
+        self.prevote_timeout_rounds.clear();
+        self.precommit_timeout_rounds.clear();
+        self.valid_rounds.clear();
+        self.start_round(0);
+        self.process();
+        std::mem::take(&mut self.actions)
+    }
+
+    /// Feeds a message from another validator into the machine.
+    pub fn handle_message(&mut self, message: Message) -> Vec<Action> {
+        if self.decision.is_none() {
+            match message {
+                Message::Proposal(proposal) => self.record_proposal(proposal),
+                Message::Vote(vote) => self.record_vote(vote),
+            }
+            self.process();
+        }
+        std::mem::take(&mut self.actions)
+    }
+
+    /// Feeds a signed vote into the machine, keeping the signature so that an
+    /// equivocation can be turned into slashing evidence. Votes not signed
+    /// with the consensus key the validator registered in the snapshot are
+    /// dropped.
+    pub fn handle_signed_vote(&mut self, signed: SignedVote) -> Vec<Action> {
+        let registered = self.validators.consensus_key(&signed.vote.validator);
+        if registered != Some(signed.validator_pubkey.as_slice()) || !verify_signed_vote(&signed) {
+            return Vec::new();
+        }
+        let vote = &signed.vote;
+        if vote.height == self.height && self.validators.contains(&vote.validator) {
+            let key = (vote.round, vote.vote_type, vote.validator);
+            match self.signed_votes.get(&key) {
+                None => {
+                    self.signed_votes.insert(key, signed.clone());
+                }
+                Some(first) if first.vote.block_hash != vote.block_hash => {
+                    self.signed_equivocations.push((first.clone(), signed.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        self.handle_message(Message::Vote(signed.vote))
+    }
+
+    /// Fires a timeout previously requested through `Action::ScheduleTimeout`.
+    pub fn handle_timeout(&mut self, timeout: Timeout) -> Vec<Action> {
+        let current = timeout.height == self.height && timeout.round == self.round;
+        if current && self.decision.is_none() {
+            match timeout.step {
+                Step::Propose if self.step == Step::Propose => {
+                    self.cast_vote(VoteType::Prevote, None);
+                    self.step = Step::Prevote;
+                }
+                Step::Prevote if self.step == Step::Prevote => {
+                    self.cast_vote(VoteType::Precommit, None);
+                    self.step = Step::Precommit;
+                }
+                Step::Precommit => self.start_round(self.round + 1),
+                _ => {}
+            }
+            self.process();
+        }
+        std::mem::take(&mut self.actions)
+    }
+
+    fn start_round(&mut self, round: u32) {
+        self.round = round;
+        self.step = Step::Propose;
+        let value = self
+            .valid
+            .as_ref()
+            .map(|(value, valid_round)| (value.clone(), Some(*valid_round)))
+            .or_else(|| self.candidate.clone().map(|value| (value, None)));
+
+        match value {
+            Some((block_hash, valid_round)) if self.proposer(self.height, round) == Some(self.id) => {
+                let proposal = Proposal {
+                    height: self.height,
+                    round,
+                    block_hash,
+                    valid_round,
+                    proposer: self.id,
+                };
+                self.proposals.insert(round, proposal.clone());
+                self.actions.push(Action::Broadcast(Message::Proposal(proposal)));
+            }
+            _ => self.schedule(Step::Propose),
+        }
+    }
+
+    /// Applies the Tendermint upon-rules until none fires.
+    fn process(&mut self) {
+        while self.decision.is_none() && self.apply_rule() {}
+    }
+
+    fn apply_rule(&mut self) -> bool {
+        let round = self.round;
+        let proposal = self.proposals.get(&round).cloned();
+
+        // Prevote a fresh proposal, or one re-proposed with a prevote quorum from an earlier round.
+        // A re-proposal still waiting for that quorum only holds back this rule, not the ones below.
+        if let (Step::Propose, Some(proposal)) = (self.step, &proposal) {
+            let acceptable = match proposal.valid_round {
+                None => Some(self.locked.as_ref().is_none_or(|(value, _)| *value == proposal.block_hash)),
+                Some(valid_round) if valid_round < round => self
+                    .has_quorum(valid_round, VoteType::Prevote, Some(&Some(proposal.block_hash.clone())))
+                    .then(|| {
+                        self.locked.as_ref().is_none_or(|(value, locked_round)| {
+                            *locked_round <= valid_round || *value == proposal.block_hash
+                        })
+                    }),
+                Some(_) => Some(false),
+            };
+            if let Some(acceptable) = acceptable {
+                let vote = acceptable.then(|| proposal.block_hash.clone());
+                self.cast_vote(VoteType::Prevote, vote);
+                self.step = Step::Prevote;
+                return true;
+            }
+        }
+
+        if self.step == Step::Prevote
+            && self.has_quorum(round, VoteType::Prevote, None)
+            && self.prevote_timeout_rounds.insert(round)
+        {
+            self.schedule(Step::Prevote);
+            return true;
+        }
+
+        // Lock on a proposal once it has a prevote quorum in this round.
+        if let Some(proposal) = &proposal {
+            let value = Some(proposal.block_hash.clone());
+            if self.step >= Step::Prevote
+                && self.has_quorum(round, VoteType::Prevote, Some(&value))
+                && self.valid_rounds.insert(round)
+            {
+                if self.step == Step::Prevote {
+                    self.locked = Some((proposal.block_hash.clone(), round));
+                    self.cast_vote(VoteType::Precommit, value);
+                    self.step = Step::Precommit;
+                }
+                self.valid = Some((proposal.block_hash.clone(), round));
+                return true;
+            }
+        }
+
+        if self.step == Step::Prevote && self.has_quorum(round, VoteType::Prevote, Some(&None)) {
+            self.cast_vote(VoteType::Precommit, None);
+            self.step = Step::Precommit;
+            return true;
+        }
+
+        if self.has_quorum(round, VoteType::Precommit, None) && self.precommit_timeout_rounds.insert(round) {
+            self.schedule(Step::Precommit);
+            return true;
+        }
+
+        // Commit any proposal that gathered a precommit quorum, in any round.
+        let committed = self.proposals.iter().find(|(proposal_round, proposal)| {
+            let value = Some(proposal.block_hash.clone());
+            self.has_quorum(**proposal_round, VoteType::Precommit, Some(&value))
+        });
+        if let Some((proposal_round, proposal)) = committed {
+            let action = Action::Commit {
+                height: self.height,
+                round: *proposal_round,
+                block_hash: proposal.block_hash.clone(),
+            };
+            self.decision = Some(proposal.block_hash.clone());
+            self.actions.push(action);
+            return true;
+        }
+
+        // Skip ahead when more than a third of the stake is already in a later round.
+        if let Some(later_round) = self.later_round_with_weight() {
+            self.start_round(later_round);
+            return true;
+        }
+
+        false
+    }
+
+    fn record_proposal(&mut self, proposal: Proposal) {
+        if proposal.height != self.height
+            || self.proposer(proposal.height, proposal.round) != Some(proposal.proposer)
+        {
+            return;
+        }
+        self.proposals.entry(proposal.round).or_insert(proposal);
+    }
+
+    fn record_vote(&mut self, vote: Vote) {
+        if vote.height != self.height || !self.validators.contains(&vote.validator) {
+            return;
+        }
+        let votes = self.votes.entry((vote.round, vote.vote_type)).or_default();
+        match votes.get(&vote.validator) {
+            None => {
+                votes.insert(vote.validator, vote.block_hash);
+            }
+            Some(existing) if *existing != vote.block_hash => {
+                let first = Vote { block_hash: existing.clone(), ..vote.clone() };
+                self.equivocations.push((first, vote));
+            }
+            Some(_) => {}
+        }
+    }
+
+    fn cast_vote(&mut self, vote_type: VoteType, block_hash: Option<BlockHash>) {
+        let vote = Vote {
+            vote_type,
+            height: self.height,
+            round: self.round,
+            block_hash,
+            validator: self.id,
+        };
+        self.record_vote(vote.clone());
+        self.actions.push(Action::Broadcast(Message::Vote(vote)));
+    }
+
+    fn schedule(&mut self, step: Step) {
+        let timeout = Timeout { height: self.height, round: self.round, step };
+        self.actions.push(Action::ScheduleTimeout(timeout, self.timeouts.duration(step, self.round)));
+    }
+
+    fn stake_of<'a>(&self, voters: impl Iterator<Item = &'a ValidatorId>) -> u64 {
+        voters.map(|v| self.validators.stake_of(v)).sum()
+    }
+
+    /// Whether more than 2/3 of stake voted in `round`, for `value` if given or for anything otherwise.
+    fn has_quorum(&self, round: u32, vote_type: VoteType, value: Option<&Option<BlockHash>>) -> bool {
+        let Some(votes) = self.votes.get(&(round, vote_type)) else {
+            return false;
+        };
+        let voters = votes.iter().filter(|(_, vote)| value.is_none_or(|value| *vote == value));
+        let stake = self.stake_of(voters.map(|(validator, _)| validator));
+        stake as u128 * 3 > self.validators.total_stake() as u128 * 2
+    }
+
+    fn later_round_with_weight(&self) -> Option<u32> {
+        let mut senders: BTreeMap<u32, BTreeSet<&ValidatorId>> = BTreeMap::new();
+        for ((round, _), votes) in self.votes.range((self.round + 1, VoteType::Prevote)..) {
+            senders.entry(*round).or_default().extend(votes.keys());
+        }
+        for (round, proposal) in self.proposals.range(self.round + 1..) {
+            senders.entry(*round).or_default().insert(&proposal.proposer);
+        }
+        senders
+            .into_iter()
+            .find(|(_, senders)| self.stake_of(senders.iter().copied()) as u128 * 3 > self.validators.total_stake() as u128)
+            .map(|(round, _)| round)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::address::testing::{ALICE, BOB, CHARLIE, DAVE};
+    use crate::utils::address::Address;
+    use crate::utils::crypto::keypair_from_seed;
+    use std::collections::VecDeque;
+
+    const WHALE: Address = Address::repeat_byte(0x77);
+
+    /// How a simulated Byzantine validator misbehaves.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Byzantine {
+        /// Never sends anything.
+        Silent,
+        /// Sends conflicting proposals and votes to the two halves of the network.
+        Equivocating,
+        /// Proposes in round 1 with a valid round that never had a prevote
+        /// quorum, and precommits that proposal.
+        BogusReproposal,
+    }
+
+    /// In-process network delivering messages in a seeded pseudo-random order.
+    struct Harness {
+        nodes: BTreeMap<ValidatorId, BftMachine>,
+        byzantine: BTreeMap<ValidatorId, Byzantine>,
+        queue: VecDeque<(ValidatorId, Message)>,
+        timers: Vec<(Duration, ValidatorId, Timeout)>,
+        now: Duration,
+        commits: BTreeMap<ValidatorId, BlockHash>,
+        rng: u64,
+    }
+
+    impl Harness {
+        fn new(stakes: &[(ValidatorId, u64)], byzantine: &[(ValidatorId, Byzantine)], seed: u64) -> Self {
+            let validators = snapshot(stakes);
+            let byzantine: BTreeMap<ValidatorId, Byzantine> =
+                byzantine.iter().copied().collect();
+            let nodes = validators
+                .validators()
+                .keys()
+                .filter(|v| !byzantine.contains_key(*v))
+                .map(|v| (*v, BftMachine::new(*v, validators.clone(), TimeoutConfig::default())))
+                .collect();
+            Self {
+                nodes,
+                byzantine,
+                queue: VecDeque::new(),
+                timers: Vec::new(),
+                now: Duration::ZERO,
+                commits: BTreeMap::new(),
+                rng: seed | 1,
+            }
+        }
+
+        fn next_random(&mut self) -> u64 {
+            self.rng ^= self.rng << 13;
+            self.rng ^= self.rng >> 7;
+            self.rng ^= self.rng << 17;
+            self.rng
+        }
+
+        fn apply(&mut self, from: ValidatorId, actions: Vec<Action>) {
+            for action in actions {
+                match action {
+                    Action::Broadcast(message) => {
+                        for to in self.nodes.keys().filter(|to| **to != from) {
+                            self.queue.push_back((*to, message.clone()));
+                        }
+                    }
+                    Action::ScheduleTimeout(timeout, after) => {
+                        self.timers.push((self.now + after, from, timeout));
+                    }
+                    Action::Commit { block_hash, .. } => {
+                        self.commits.insert(from, block_hash);
+                    }
+                }
+            }
+        }
+
+        /// Queues conflicting proposals and votes from equivocating validators for the first rounds.
+        fn inject_equivocations(&mut self, height: u64, rounds: u32) {
+            let honest: Vec<ValidatorId> = self.nodes.keys().copied().collect();
+            let (left, right) = honest.split_at(honest.len() / 2);
+            let reference = self.nodes.values().next().unwrap().clone();
+            let equivocators: Vec<ValidatorId> = self
+                .byzantine
+                .iter()
+                .filter(|(_, b)| **b == Byzantine::Equivocating)
+                .map(|(v, _)| *v)
+                .collect();
+
+            for validator in equivocators {
+                for round in 0..rounds {
+                    for (group, block_hash) in [(left, b"evil block a".to_vec()), (right, b"evil block b".to_vec())] {
+                        let mut messages = Vec::new();
+                        if reference.proposer(height, round) == Some(validator) {
+                            messages.push(Message::Proposal(Proposal {
+                                height,
+                                round,
+                                block_hash: block_hash.clone(),
+                                valid_round: None,
+                                proposer: validator,
+                            }));
+                        }
+                        for vote_type in [VoteType::Prevote, VoteType::Precommit] {
+                            messages.push(Message::Vote(Vote {
+                                vote_type,
+                                height,
+                                round,
+                                block_hash: Some(block_hash.clone()),
+                                validator,
+                            }));
+                        }
+                        for to in group {
+                            for message in &messages {
+                                self.queue.push_back((*to, message.clone()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Queues round-1 proposals with a bogus valid round, and matching precommits, to every honest node.
+        fn inject_bogus_reproposals(&mut self, height: u64) {
+            let reproposers: Vec<ValidatorId> = self
+                .byzantine
+                .iter()
+                .filter(|(_, b)| **b == Byzantine::BogusReproposal)
+                .map(|(v, _)| *v)
+                .collect();
+            for validator in reproposers {
+                let block_hash = format!("pow block from {validator}").into_bytes();
+                let proposal = Message::Proposal(Proposal {
+                    height,
+                    round: 1,
+                    block_hash: block_hash.clone(),
+                    valid_round: Some(0),
+                    proposer: validator,
+                });
+                let precommit = Message::Vote(Vote {
+                    vote_type: VoteType::Precommit,
+                    height,
+                    round: 1,
+                    block_hash: Some(block_hash),
+                    validator,
+                });
+                for to in self.nodes.keys() {
+                    self.queue.push_back((*to, proposal.clone()));
+                    self.queue.push_back((*to, precommit.clone()));
+                }
+            }
+        }
+
+        /// Runs one height until every honest node commits or the step budget runs out.
+        fn run(&mut self, height: u64, max_steps: usize) {
+            self.inject_equivocations(height, 3);
+            self.inject_bogus_reproposals(height);
+            let ids: Vec<ValidatorId> = self.nodes.keys().copied().collect();
+            for id in &ids {
+                let candidate = format!("pow block from {id}").into_bytes();
+                let actions = self.nodes.get_mut(id).unwrap().start_height(height, Some(candidate));
+                self.apply(*id, actions);
+            }
+
+            for _ in 0..max_steps {
+                if self.commits.len() == self.nodes.len() {
+                    return;
+                }
+                if !self.queue.is_empty() {
+                    let index = (self.next_random() % self.queue.len() as u64) as usize;
+                    let (to, message) = self.queue.remove(index).unwrap();
+                    let actions = self.nodes.get_mut(&to).unwrap().handle_message(message);
+                    self.apply(to, actions);
+                } else if !self.timers.is_empty() {
+                    self.timers.sort_by_key(|(at, _, _)| *at);
+                    let (at, to, timeout) = self.timers.remove(0);
+                    self.now = self.now.max(at);
+                    let actions = self.nodes.get_mut(&to).unwrap().handle_timeout(timeout);
+                    self.apply(to, actions);
+                } else {
+                    return;
+                }
+            }
+        }
+
+        fn assert_agreement(&self) {
+            let decisions: BTreeSet<&BlockHash> = self.commits.values().collect();
+            assert!(decisions.len() <= 1, "honest validators committed different blocks");
+        }
+    }
+
+    fn snapshot(stakes: &[(ValidatorId, u64)]) -> ValidatorSnapshot {
+        let validators = stakes.iter().copied().collect();
+        ValidatorSnapshot::new(0, validators, b"epoch randomness".to_vec())
+    }
+
+    fn equal_stakes() -> Vec<(ValidatorId, u64)> {
+        vec![(ALICE, 100), (BOB, 100), (CHARLIE, 100), (DAVE, 100)]
+    }
+
+    #[test]
+    fn test_all_honest_commit_same_block() {
+        for seed in 1..20 {
+            let mut harness = Harness::new(&equal_stakes(), &[], seed);
+            harness.run(1, 10_000);
+            assert_eq!(harness.commits.len(), 4);
+            harness.assert_agreement();
+        }
+    }
+
+    #[test]
+    fn test_silent_validator_tolerated() {
+        // The silent validator may be the proposer, forcing a round change.
+        for seed in 1..20 {
+            let mut harness = Harness::new(&equal_stakes(), &[(DAVE, Byzantine::Silent)], seed);
+            harness.run(1, 10_000);
+            assert_eq!(harness.commits.len(), 3);
+            harness.assert_agreement();
+        }
+    }
+
+    #[test]
+    fn test_equivocating_validator_cannot_split_honest_nodes() {
+        for seed in 1..20 {
+            for byzantine in [ALICE, BOB, CHARLIE, DAVE] {
+                let mut harness = Harness::new(&equal_stakes(), &[(byzantine, Byzantine::Equivocating)], seed);
+                harness.run(1, 10_000);
+                harness.assert_agreement();
+                assert!(!harness.commits.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_commit_without_supermajority() {
+        // Two silent validators out of four leave only half the stake online.
+        let mut harness =
+            Harness::new(&equal_stakes(), &[(CHARLIE, Byzantine::Silent), (DAVE, Byzantine::Silent)], 7);
+        harness.run(1, 2_000);
+        assert!(harness.commits.is_empty());
+        assert!(harness.nodes.values().all(|node| node.decision().is_none()));
+    }
+
+    #[test]
+    fn test_stake_weighted_quorum() {
+        // A single validator holding over two thirds of the stake carries the quorum alone.
+        let stakes = [(WHALE, 700), (BOB, 100), (CHARLIE, 100), (DAVE, 100)];
+        let byzantine = [(BOB, Byzantine::Silent), (CHARLIE, Byzantine::Silent), (DAVE, Byzantine::Silent)];
+        let mut harness = Harness::new(&stakes, &byzantine, 3);
+        harness.run(1, 10_000);
+        assert_eq!(harness.commits.len(), 1);
+    }
+
+    #[test]
+    fn test_commit_despite_bogus_valid_round() {
+        // The whale's precommit alone is a quorum, so its round-1 block must be committed as soon
+        // as it arrives, even though the proposal cites a valid round without a prevote quorum.
+        let stakes = [(WHALE, 700), (BOB, 100), (CHARLIE, 100), (DAVE, 100)];
+        let probe = BftMachine::new(BOB, snapshot(&stakes), TimeoutConfig::default());
+        let height = (1..).find(|height| probe.proposer(*height, 1) == Some(WHALE)).unwrap();
+        for seed in 1..20 {
+            let mut harness = Harness::new(&stakes, &[(WHALE, Byzantine::BogusReproposal)], seed);
+            harness.run(height, 10_000);
+            assert_eq!(harness.commits.len(), 3);
+            let whale_block = format!("pow block from {WHALE}").into_bytes();
+            assert!(harness.commits.values().all(|block_hash| *block_hash == whale_block));
+            assert_eq!(harness.now, Duration::ZERO, "commit waited for a timeout");
+        }
+    }
+
+    #[test]
+    fn test_locking_rules() {
+        let validators = snapshot(&equal_stakes());
+        let probe = BftMachine::new(ALICE, validators.clone(), TimeoutConfig::default());
+        let proposer = probe.proposer(1, 0).unwrap();
+        let me = *validators.validators().keys().find(|v| **v != proposer).unwrap();
+        let others: Vec<ValidatorId> = validators.validators().keys().filter(|v| **v != me).copied().collect();
+        let mut machine = BftMachine::new(me, validators, TimeoutConfig::default());
+        machine.start_height(1, None);
+
+        let block = b"block x".to_vec();
+        let vote = |vote_type, round, block_hash: Option<BlockHash>, validator: &ValidatorId| {
+            Message::Vote(Vote { vote_type, height: 1, round, block_hash, validator: *validator })
+        };
+
+        // A fresh proposal is prevoted.
+        let actions = machine.handle_message(Message::Proposal(Proposal {
+            height: 1,
+            round: 0,
+            block_hash: block.clone(),
+            valid_round: None,
+            proposer,
+        }));
+        assert!(actions.contains(&Action::Broadcast(vote(VoteType::Prevote, 0, Some(block.clone()), &me))));
+
+        // A prevote quorum for the block locks it and triggers a precommit.
+        for validator in &others[..2] {
+            machine.handle_message(vote(VoteType::Prevote, 0, Some(block.clone()), validator));
+        }
+        assert_eq!(machine.locked(), Some(&(block.clone(), 0)));
+        assert_eq!(machine.step(), Step::Precommit);
+
+        // Move to round 1 through the precommit timeout after nil precommits.
+        for validator in &others[..2] {
+            machine.handle_message(vote(VoteType::Precommit, 0, None, validator));
+        }
+        let actions = machine.handle_timeout(Timeout { height: 1, round: 0, step: Step::Precommit });
+        assert_eq!(machine.round(), 1);
+
+        let proposer = machine.proposer(1, 1).unwrap();
+        if proposer == me {
+            // The proposer re-proposes its valid block together with the round it was prevoted in.
+            let reproposal = actions.iter().find_map(|action| match action {
+                Action::Broadcast(Message::Proposal(proposal)) => Some(proposal.clone()),
+                _ => None,
+            });
+            assert_eq!(reproposal.map(|p| (p.block_hash, p.valid_round)), Some((block, Some(0))));
+        } else {
+            // A fresh proposal for another block is prevoted nil while locked.
+            let actions = machine.handle_message(Message::Proposal(Proposal {
+                height: 1,
+                round: 1,
+                block_hash: b"block y".to_vec(),
+                valid_round: None,
+                proposer,
+            }));
+            assert!(actions.contains(&Action::Broadcast(vote(VoteType::Prevote, 1, None, &me))));
+        }
+    }
+
+    #[test]
+    fn test_equivocation_recorded() {
+        let mut machine = BftMachine::new(ALICE, snapshot(&equal_stakes()), TimeoutConfig::default());
+        machine.start_height(1, None);
+        for block_hash in [b"a".to_vec(), b"b".to_vec()] {
+            machine.handle_message(Message::Vote(Vote {
+                vote_type: VoteType::Prevote,
+                height: 1,
+                round: 0,
+                block_hash: Some(block_hash),
+                validator: BOB,
+            }));
+        }
+        assert_eq!(machine.equivocations().len(), 1);
+        let (first, second) = &machine.equivocations()[0];
+        assert_eq!(first.block_hash, Some(b"a".to_vec()));
+        assert_eq!(second.block_hash, Some(b"b".to_vec()));
+    }
+
+    /// Snapshot of equal stakes in which bob registered the key derived from seed "bob".
+    fn snapshot_with_bob_key() -> ValidatorSnapshot {
+        let (secret_key, _) = keypair_from_seed(b"bob");
+        let bob_key = encode_tagged(Secp256k1::TAG, &Secp256k1::public_key(&secret_key));
+        snapshot(&equal_stakes()).with_consensus_keys(BTreeMap::from([(BOB, bob_key)]))
+    }
+
+    #[test]
+    fn test_signed_equivocation_recorded() {
+        let (secret_key, _) = keypair_from_seed(b"bob");
+        let mut machine = BftMachine::new(ALICE, snapshot_with_bob_key(), TimeoutConfig::default());
+        machine.start_height(1, None);
+        let prevote = |block_hash: &[u8]| Vote {
+            vote_type: VoteType::Prevote,
+            height: 1,
+            round: 0,
+            block_hash: Some(block_hash.to_vec()),
+            validator: BOB,
+        };
+
+        // Forged votes are dropped before they reach the machine.
+        let mut forged = sign_vote(&secret_key, prevote(b"a"));
+        forged.vote.block_hash = Some(b"c".to_vec());
+        assert!(machine.handle_signed_vote(forged).is_empty());
+
+        machine.handle_signed_vote(sign_vote(&secret_key, prevote(b"a")));
+        machine.handle_signed_vote(sign_vote(&secret_key, prevote(b"a")));
+        assert!(machine.signed_equivocations().is_empty());
+        machine.handle_signed_vote(sign_vote(&secret_key, prevote(b"b")));
+
+        assert_eq!(machine.equivocations().len(), 1);
+        let (first, second) = &machine.signed_equivocations()[0];
+        assert_eq!(first.vote, prevote(b"a"));
+        assert_eq!(second.vote, prevote(b"b"));
+        assert!(verify_signed_vote(first) && verify_signed_vote(second));
+    }
+
+    #[test]
+    fn test_vote_signed_with_unregistered_key_rejected() {
+        let (secret_key, _) = keypair_from_seed(b"bob");
+        let (impostor_key, _) = keypair_from_seed(b"mallory");
+        let mut machine = BftMachine::new(ALICE, snapshot_with_bob_key(), TimeoutConfig::default());
+        machine.start_height(1, None);
+        let prevote = |block_hash: &[u8]| Vote {
+            vote_type: VoteType::Prevote,
+            height: 1,
+            round: 0,
+            block_hash: Some(block_hash.to_vec()),
+            validator: BOB,
+        };
+
+        // A validly signed vote under a key bob never registered is dropped...
+        let forged = sign_vote(&impostor_key, prevote(b"a"));
+        assert!(verify_signed_vote(&forged));
+        assert!(machine.handle_signed_vote(forged).is_empty());
+
+        // ...so bob's real vote afterwards is counted, not taken for an equivocation.
+        machine.handle_signed_vote(sign_vote(&secret_key, prevote(b"b")));
+        assert!(machine.equivocations().is_empty());
+        assert!(machine.signed_equivocations().is_empty());
+
+        // Validators without a registered key can't cast signed votes at all.
+        let unkeyed = Vote { validator: CHARLIE, ..prevote(b"a") };
+        let (charlie_key, _) = keypair_from_seed(b"charlie");
+        assert!(machine.handle_signed_vote(sign_vote(&charlie_key, unkeyed)).is_empty());
+    }
+
+    #[test]
+    fn test_timeouts_grow_with_round() {
+        let config = TimeoutConfig::default();
+        assert_eq!(config.duration(Step::Propose, 0), Duration::from_millis(3_000));
+        assert_eq!(config.duration(Step::Propose, 2), Duration::from_millis(4_000));
+        assert_eq!(config.duration(Step::Prevote, 1), Duration::from_millis(1_500));
+    }
+}
@@ -1,24 +1,117 @@
-use sha3::{Digest, Sha3_256};
+use k256::SecretKey;
+use serde::{Deserialize, Serialize};
+use super::staking::ValidatorId;
+use crate::utils::crypto::{encode_tagged, verify_tagged, Secp256k1, SignatureScheme};
+use crate::utils::hashing::{sha3_256_hash, ConsensusHasher, Domain};
+use crate::utils::musig2::KeyAggContext;
+use crate::utils::schnorr::schnorr_verify;
+
+/// A validator's signed attestation that `block_hash` is final at `height`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finalization {
+    pub height: u64,
+    pub block_hash: Vec<u8>,
+    /// Public key of the signing validator, tagged with its signature scheme.
+    pub validator_pubkey: Vec<u8>,
+    /// Tagged signature over `finalization_digest(height, block_hash)`.
+    pub signature: Vec<u8>,
+}
 
 /// Finalizes a block using PoS consensus.
 pub fn finalize_block(block_data: &[u8]) -> Vec<u8> {
-    let mut hasher = Sha3_256::new();
-    hasher.update(block_data);
-    hasher.finalize().to_vec()
+    sha3_256_hash(Domain::FinalizedBlock, block_data)
+}
+
+/// Digest a validator signs to finalize `block_hash` at `height`.
+pub fn finalization_digest(height: u64, block_hash: &[u8]) -> Vec<u8> {
+    ConsensusHasher::new(Domain::Finalization).field_u64(height).field(block_hash).finish()
+}
+
+/// Signs a finalization of `block_hash` at `height` with a secp256k1 key.
+pub fn sign_finalization(secret_key: &SecretKey, height: u64, block_hash: &[u8]) -> Finalization {
+    sign_finalization_with::<Secp256k1>(secret_key, height, block_hash)
 }
 
-/// Selects the next validator based on their stake.
-pub fn select_validator(validators: &[(&str, u64)]) -> &str {
-    validators
+/// Signs a finalization of `block_hash` at `height` with a key of scheme `S`.
+pub fn sign_finalization_with<S: SignatureScheme>(
+    secret_key: &S::SecretKey,
+    height: u64,
+    block_hash: &[u8],
+) -> Finalization {
+    let signature = S::sign(secret_key, &finalization_digest(height, block_hash)).expect("digest is 32 bytes");
+    Finalization {
+        height,
+        block_hash: block_hash.to_vec(),
+        validator_pubkey: encode_tagged(S::TAG, &S::public_key(secret_key)),
+        signature: encode_tagged(S::TAG, &signature),
+    }
+}
+
+/// Checks that a finalization is signed by its `validator_pubkey`.
+pub fn verify_finalization(finalization: &Finalization) -> bool {
+    let digest = finalization_digest(finalization.height, &finalization.block_hash);
+    verify_tagged(&finalization.validator_pubkey, &digest, &finalization.signature)
+}
+
+/// A committee's MuSig2 finalization of `block_hash` at `height`: one
+/// signature regardless of the committee size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateFinalization {
+    pub height: u64,
+    pub block_hash: Vec<u8>,
+    /// SEC1 compressed public keys of the signers, in aggregation order.
+    pub signers: Vec<Vec<u8>>,
+    /// BIP-340 signature over `finalization_digest(height, block_hash)`
+    /// under the signers' aggregate key.
+    pub signature: Vec<u8>,
+}
+
+/// Checks that an aggregate finalization is signed by all of its `signers`.
+pub fn verify_aggregate_finalization(finalization: &AggregateFinalization) -> bool {
+    let Ok(key_agg) = KeyAggContext::new(&finalization.signers) else {
+        return false;
+    };
+    let digest = finalization_digest(finalization.height, &finalization.block_hash);
+    schnorr_verify(&key_agg.aggregate_public_key(), &digest, &finalization.signature)
+}
+
+/// Selects the next validator with probability proportional to their stake.
+///
+/// `seed` is the chain randomness for the slot. Every node must pass the
+/// validators in the same order for the choice to agree. Returns `None` if
+/// there is no stake to select from.
+pub fn select_validator(validators: &[(ValidatorId, u64)], seed: &[u8]) -> Option<ValidatorId> {
+    let total_stake = validators
         .iter()
-        .max_by_key(|(_, stake)| *stake)
-        .map(|(validator, _)| *validator)
-        .unwrap_or("default_validator")
+        .try_fold(0u64, |total, (_, stake)| total.checked_add(*stake))?;
+    if total_stake == 0 {
+        return None;
+    }
+
+    // Scale a uniform 64-bit draw onto [0, total_stake). Each target gets either
+    // floor or ceil of 2^64 / total_stake draws, a negligible bias of at most
+    // total_stake / 2^64 relative to exact stake weighting.
+    let digest = sha3_256_hash(Domain::ValidatorSelection, seed);
+    let draw = u64::from_be_bytes(digest[..8].try_into().unwrap());
+    let target = ((draw as u128 * total_stake as u128) >> 64) as u64;
+
+    let mut cumulative = 0u64;
+    validators.iter().find_map(|(validator, stake)| {
+        cumulative += stake;
+        (target < cumulative).then_some(*validator)
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::address::testing::{ALICE, BOB, CHARLIE, DAVE};
+    use crate::utils::crypto::SchemeTag;
+
+    /// Replaces the scheme tag of an encoded key or signature.
+    fn retag(tagged: &[u8], scheme: SchemeTag) -> Vec<u8> {
+        encode_tagged(scheme, &tagged[1..])
+    }
 
     #[test]
     fn test_finalize_block() {
@@ -38,18 +131,152 @@ mod tests {
         assert_ne!(hash, hash3);
     }
 
+    #[test]
+    fn test_sign_and_verify_finalization() {
+        let (secret_key, public_key) = crate::utils::crypto::generate_keypair();
+        let block_hash = finalize_block(b"test block data");
+        let finalization = sign_finalization(&secret_key, 7, &block_hash);
+
+        assert_eq!(finalization.height, 7);
+        assert_eq!(finalization.block_hash, block_hash);
+        assert_eq!(finalization.validator_pubkey.len(), 34);
+        assert_eq!(finalization.signature.len(), 65);
+        assert!(verify_finalization(&finalization));
+
+        // The signing key matches the uncompressed key from generate_keypair.
+        let compressed = crate::utils::crypto::compress_public_key(&public_key).unwrap();
+        assert_eq!(finalization.validator_pubkey, encode_tagged(SchemeTag::Secp256k1, &compressed));
+
+        // Any change to the signed fields invalidates the signature.
+        let wrong_height = Finalization { height: 8, ..finalization.clone() };
+        assert!(!verify_finalization(&wrong_height));
+        let wrong_hash = Finalization { block_hash: finalize_block(b"other"), ..finalization.clone() };
+        assert!(!verify_finalization(&wrong_hash));
+        let (other_secret_key, _) = crate::utils::crypto::generate_keypair();
+        let other_public_key = encode_tagged(SchemeTag::Secp256k1, &Secp256k1::public_key(&other_secret_key));
+        let wrong_key = Finalization { validator_pubkey: other_public_key, ..finalization.clone() };
+        assert!(!verify_finalization(&wrong_key));
+        let wrong_scheme = Finalization {
+            validator_pubkey: retag(&finalization.validator_pubkey, SchemeTag::MlDsa65),
+            signature: retag(&finalization.signature, SchemeTag::MlDsa65),
+            ..finalization.clone()
+        };
+        assert!(!verify_finalization(&wrong_scheme));
+        let untagged = Finalization {
+            validator_pubkey: compressed,
+            signature: finalization.signature[1..].to_vec(),
+            ..finalization.clone()
+        };
+        assert!(!verify_finalization(&untagged));
+        let garbage = Finalization { signature: vec![0; 10], ..finalization };
+        assert!(!verify_finalization(&garbage));
+    }
+
+    #[test]
+    fn test_post_quantum_finalization() {
+        use crate::utils::mldsa::MlDsa65;
+
+        let (secret_key, public_key) = MlDsa65::generate_keypair();
+        let block_hash = finalize_block(b"test block data");
+        let finalization = sign_finalization_with::<MlDsa65>(&secret_key, 7, &block_hash);
+        assert_eq!(finalization.validator_pubkey, encode_tagged(SchemeTag::MlDsa65, &public_key));
+        assert!(verify_finalization(&finalization));
+
+        let wrong_height = Finalization { height: 8, ..finalization.clone() };
+        assert!(!verify_finalization(&wrong_height));
+
+        // Key and signature must carry the same tag, and it must name the right scheme.
+        let mixed_signature = retag(&finalization.signature, SchemeTag::Secp256k1);
+        let mixed = Finalization { signature: mixed_signature, ..finalization.clone() };
+        assert!(!verify_finalization(&mixed));
+        let wrong_scheme = Finalization {
+            validator_pubkey: retag(&finalization.validator_pubkey, SchemeTag::Secp256k1),
+            signature: retag(&finalization.signature, SchemeTag::Secp256k1),
+            ..finalization
+        };
+        assert!(!verify_finalization(&wrong_scheme));
+    }
+
+    #[test]
+    fn test_aggregate_finalization() {
+        use crate::utils::crypto::{keypair_from_seed, public_key_of};
+        use crate::utils::musig2::{aggregate_nonces, generate_nonce, Session};
+
+        let secret_keys: Vec<_> = (0..3).map(|i| keypair_from_seed(format!("validator-{i}").as_bytes()).0).collect();
+        let signers: Vec<Vec<u8>> = secret_keys.iter().map(|key| public_key_of(key, true)).collect();
+        let block_hash = finalize_block(b"test block data");
+        let digest = finalization_digest(7, &block_hash);
+
+        let key_agg = KeyAggContext::new(&signers).unwrap();
+        let (secret_nonces, public_nonces): (Vec<_>, Vec<_>) =
+            secret_keys.iter().map(|key| generate_nonce(key, &key_agg, &digest)).unzip();
+        let session = Session::new(&key_agg, &aggregate_nonces(&public_nonces).unwrap(), &digest).unwrap();
+        let partials: Vec<_> = secret_nonces
+            .into_iter()
+            .zip(&secret_keys)
+            .map(|(nonce, key)| session.partial_sign(nonce, key).unwrap())
+            .collect();
+
+        let finalization = AggregateFinalization {
+            height: 7,
+            block_hash: block_hash.clone(),
+            signers: signers.clone(),
+            signature: session.aggregate_partials(&partials).unwrap().to_vec(),
+        };
+        assert!(verify_aggregate_finalization(&finalization));
+
+        let wrong_height = AggregateFinalization { height: 8, ..finalization.clone() };
+        assert!(!verify_aggregate_finalization(&wrong_height));
+        let missing_signer = AggregateFinalization { signers: signers[1..].to_vec(), ..finalization.clone() };
+        assert!(!verify_aggregate_finalization(&missing_signer));
+        let reordered: Vec<_> = signers.iter().rev().cloned().collect();
+        let reordered = AggregateFinalization { signers: reordered, ..finalization.clone() };
+        assert!(!verify_aggregate_finalization(&reordered));
+        let no_signers = AggregateFinalization { signers: vec![], ..finalization };
+        assert!(!verify_aggregate_finalization(&no_signers));
+    }
+
     #[test]
     fn test_select_validator() {
-        let validators = vec![("alice", 100), ("bob", 200), ("charlie", 150)];
-        let selected_validator = select_validator(&validators);
-        assert_eq!(selected_validator, "bob");
+        let validators = vec![(ALICE, 100), (BOB, 200), (CHARLIE, 150)];
+        let selected_validator = select_validator(&validators, b"seed").unwrap();
+        assert!([ALICE, BOB, CHARLIE].contains(&selected_validator));
+
+        // The same seed always selects the same validator.
+        assert_eq!(select_validator(&validators, b"seed"), Some(selected_validator));
+
+        let validators2 = vec![(ALICE, 100)];
+        let selected_validator2 = select_validator(&validators2, b"seed");
+        assert_eq!(selected_validator2, Some(ALICE));
 
-        let validators2 = vec![("alice", 100)];
-        let selected_validator2 = select_validator(&validators2);
-        assert_eq!(selected_validator2, "alice");
+        let validators3: Vec<(ValidatorId, u64)> = vec![];
+        let selected_validator3 = select_validator(&validators3, b"seed");
+        assert_eq!(selected_validator3, None);
 
-        let validators3: Vec<(&str, u64)> = vec![];
-        let selected_validator3 = select_validator(&validators3);
-        assert_eq!(selected_validator3, "default_validator");
+        // Zero-stake validators are never selected.
+        let validators4 = vec![(ALICE, 0), (BOB, 10), (CHARLIE, 0)];
+        for i in 0u32..100 {
+            assert_eq!(select_validator(&validators4, &i.to_be_bytes()), Some(BOB));
+        }
+        assert_eq!(select_validator(&[(ALICE, 0)], b"seed"), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_select_validator_frequencies() {
+        // Selection frequencies should converge to stake ratios.
+        let validators = vec![(ALICE, 100), (BOB, 200), (CHARLIE, 300), (DAVE, 400)];
+        let rounds = 100_000u32;
+        let mut counts = [0u32; 4];
+        for round in 0..rounds {
+            let selected = select_validator(&validators, &round.to_be_bytes()).unwrap();
+            let index = validators.iter().position(|(v, _)| *v == selected).unwrap();
+            counts[index] += 1;
+        }
+
+        for ((_, stake), count) in validators.iter().zip(counts) {
+            let expected = *stake as f64 / 1000.0;
+            let observed = count as f64 / rounds as f64;
+            assert!((observed - expected).abs() < 0.01, "expected {expected}, observed {observed}");
+        }
+    }
+}
@@ -0,0 +1,535 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use thiserror::Error;
+
+use super::consensus::select_validator;
+use super::staking::{normalize_key, validate_stake, StakeRegistry, ValidatorId, MIN_STAKE};
+use crate::utils::hashing::{ConsensusHasher, Domain};
+
+/// Custom error type for epoch transitions
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum EpochError {
+    #[error("Epoch length must be non-zero")]
+    ZeroEpochLength,
+    #[error("Epoch {requested} does not follow epoch {current}")]
+    NonSequentialEpoch { current: u64, requested: u64 },
+}
+
+/// Epoch and churn parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedEpochConfig")]
+pub struct EpochConfig {
+    /// Number of slots per epoch.
+    pub epoch_length: u64,
+    /// Minimum number of validators that may enter, and exit, per epoch.
+    pub min_churn: usize,
+    /// The churn limit grows to one in `churn_quotient` active validators,
+    /// and the stake churn limit to one `churn_quotient`-th of active stake.
+    pub churn_quotient: usize,
+    /// Number of validators sampled into each slot's committee.
+    pub committee_size: usize,
+}
+
+impl EpochConfig {
+    /// Checks that epochs are non-empty.
+    pub fn validate(&self) -> Result<(), EpochError> {
+        if self.epoch_length == 0 {
+            return Err(EpochError::ZeroEpochLength);
+        }
+        Ok(())
+    }
+
+    pub fn epoch_of(&self, slot: u64) -> u64 {
+        slot / self.epoch_length.max(1)
+    }
+
+    pub fn first_slot(&self, epoch: u64) -> u64 {
+        epoch.saturating_mul(self.epoch_length.max(1))
+    }
+
+    /// Maximum number of entries, and separately exits, processed per epoch.
+    pub fn churn_limit(&self, active_validators: usize) -> usize {
+        self.min_churn.max(active_validators / self.churn_quotient.max(1))
+    }
+
+    /// Maximum stake, summed over entering validators and top-ups of active
+    /// ones, that gains voting power per epoch. Never below `min_churn`
+    /// validators' worth of `MIN_STAKE`.
+    pub fn stake_churn_limit(&self, active_stake: u64) -> u64 {
+        let floor = (self.min_churn as u64).saturating_mul(MIN_STAKE);
+        floor.max(active_stake / self.churn_quotient.max(1) as u64)
+    }
+}
+
+/// Wire form of `EpochConfig`, validated on deserialization.
+#[derive(Deserialize)]
+#[serde(default)]
+struct UncheckedEpochConfig {
+    epoch_length: u64,
+    min_churn: usize,
+    churn_quotient: usize,
+    committee_size: usize,
+}
+
+impl Default for UncheckedEpochConfig {
+    fn default() -> Self {
+        let config = EpochConfig::default();
+        Self {
+            epoch_length: config.epoch_length,
+            min_churn: config.min_churn,
+            churn_quotient: config.churn_quotient,
+            committee_size: config.committee_size,
+        }
+    }
+}
+
+impl TryFrom<UncheckedEpochConfig> for EpochConfig {
+    type Error = EpochError;
+
+    fn try_from(unchecked: UncheckedEpochConfig) -> Result<Self, Self::Error> {
+        let config = Self {
+            epoch_length: unchecked.epoch_length,
+            min_churn: unchecked.min_churn,
+            churn_quotient: unchecked.churn_quotient,
+            committee_size: unchecked.committee_size,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl Default for EpochConfig {
+    fn default() -> Self {
+        Self {
+            epoch_length: 32,
+            min_churn: 4,
+            churn_quotient: 16,
+            committee_size: 16,
+        }
+    }
+}
+
+/// Validator set and randomness fixed for the duration of an epoch.
+///
+/// Every consensus lookup within the epoch (proposers, committees, voting
+/// power) is answered from the snapshot, so the chain agrees on them even
+/// while stake moves in the registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorSnapshot {
+    epoch: u64,
+    validators: BTreeMap<ValidatorId, u64>,
+    total_stake: u64,
+    randomness: Vec<u8>,
+    /// Tagged consensus keys the validators registered, as of the snapshot.
+    consensus_keys: BTreeMap<ValidatorId, Vec<u8>>,
+}
+
+impl ValidatorSnapshot {
+    /// Creates a snapshot, dropping validators without stake.
+    pub fn new(epoch: u64, validators: BTreeMap<ValidatorId, u64>, randomness: Vec<u8>) -> Self {
+        let validators: BTreeMap<ValidatorId, u64> = validators.into_iter().filter(|(_, stake)| *stake > 0).collect();
+        let total_stake = validators.values().fold(0u64, |total, stake| total.saturating_add(*stake));
+        Self { epoch, validators, total_stake, randomness, consensus_keys: BTreeMap::new() }
+    }
+
+    /// Attaches the validators' consensus keys, dropping keys of validators outside the snapshot.
+    pub fn with_consensus_keys(mut self, consensus_keys: BTreeMap<ValidatorId, Vec<u8>>) -> Self {
+        self.consensus_keys = consensus_keys
+            .into_iter()
+            .filter(|(validator, _)| self.validators.contains_key(validator))
+            .collect();
+        self
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub fn randomness(&self) -> &[u8] {
+        &self.randomness
+    }
+
+    pub fn validators(&self) -> &BTreeMap<ValidatorId, u64> {
+        &self.validators
+    }
+
+    pub fn total_stake(&self) -> u64 {
+        self.total_stake
+    }
+
+    pub fn stake_of(&self, validator: &ValidatorId) -> u64 {
+        self.validators.get(validator).copied().unwrap_or(0)
+    }
+
+    pub fn contains(&self, validator: &ValidatorId) -> bool {
+        self.validators.contains_key(validator)
+    }
+
+    /// Tagged consensus key of a validator, if it registered one.
+    pub fn consensus_key(&self, validator: &ValidatorId) -> Option<&[u8]> {
+        self.consensus_keys.get(validator).map(Vec::as_slice)
+    }
+
+    /// Validator in the snapshot that registered `consensus_key`, tagged or as a bare SEC1 key.
+    pub fn validator_by_key(&self, consensus_key: &[u8]) -> Option<ValidatorId> {
+        let key = normalize_key(consensus_key).ok()?;
+        self.consensus_keys.iter().find(|(_, registered)| **registered == key).map(|(validator, _)| *validator)
+    }
+
+    /// Stake-weighted choice of a validator, seeded by the epoch randomness and `seed`.
+    pub fn select(&self, seed: &[u8]) -> Option<ValidatorId> {
+        select_validator(&self.weighted(), &self.seed(b"select", seed))
+    }
+
+    /// Proposer for `slot`.
+    pub fn proposer(&self, slot: u64) -> Option<ValidatorId> {
+        select_validator(&self.weighted(), &self.seed(b"proposer", &slot.to_be_bytes()))
+    }
+
+    /// Samples a stake-weighted committee of up to `size` distinct validators for `slot`.
+    pub fn committee(&self, slot: u64, size: usize) -> Vec<ValidatorId> {
+        let mut remaining = self.weighted();
+        let mut committee = Vec::with_capacity(size.min(remaining.len()));
+        for index in 0..size.min(remaining.len()) as u64 {
+            let mut seed = slot.to_be_bytes().to_vec();
+            seed.extend_from_slice(&index.to_be_bytes());
+            let Some(member) = select_validator(&remaining, &self.seed(b"committee", &seed)) else {
+                break;
+            };
+            remaining.retain(|(validator, _)| *validator != member);
+            committee.push(member);
+        }
+        committee
+    }
+
+    fn weighted(&self) -> Vec<(ValidatorId, u64)> {
+        self.validators.iter().map(|(v, s)| (*v, *s)).collect()
+    }
+
+    fn seed(&self, tag: &[u8], input: &[u8]) -> Vec<u8> {
+        ConsensusHasher::digest(Domain::EpochSeed, &[&self.randomness, tag, input])
+    }
+}
+
+/// Tracks the active validator set across epochs.
+///
+/// Validators that become eligible in the stake registry wait in the entry
+/// queue, and active validators that lose eligibility wait in the exit
+/// queue; at most `churn_limit` of each are processed per epoch. A
+/// validator waiting to exit keeps no voting power in the snapshot.
+///
+/// Voting power grows by at most `stake_churn_limit` per epoch, shared first
+/// by the validators entering in that epoch, in queue order, and then by
+/// top-ups of active validators. Stake above that keeps phasing in over the
+/// following epochs; stake that leaves loses its voting power at once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochManager {
+    config: EpochConfig,
+    snapshot: ValidatorSnapshot,
+    active: BTreeSet<ValidatorId>,
+    entry_queue: VecDeque<ValidatorId>,
+    exit_queue: VecDeque<ValidatorId>,
+}
+
+impl EpochManager {
+    /// Starts at epoch 0 with every eligible validator active.
+    pub fn new(config: EpochConfig, registry: &StakeRegistry, randomness: Vec<u8>) -> Result<Self, EpochError> {
+        config.validate()?;
+        let active: BTreeSet<ValidatorId> =
+            registry.active_validators().into_iter().map(|(validator, _)| validator).collect();
+        let stakes = active.iter().map(|validator| (*validator, registry.bonded(validator))).collect();
+        let snapshot = Self::take_snapshot(0, stakes, registry, randomness);
+        Ok(Self {
+            config,
+            snapshot,
+            active,
+            entry_queue: VecDeque::new(),
+            exit_queue: VecDeque::new(),
+        })
+    }
+
+    pub fn config(&self) -> &EpochConfig {
+        &self.config
+    }
+
+    pub fn snapshot(&self) -> &ValidatorSnapshot {
+        &self.snapshot
+    }
+
+    pub fn current_epoch(&self) -> u64 {
+        self.snapshot.epoch
+    }
+
+    /// The snapshot governing `slot`, if the slot is in the current epoch.
+    pub fn snapshot_for_slot(&self, slot: u64) -> Option<&ValidatorSnapshot> {
+        (self.config.epoch_of(slot) == self.snapshot.epoch).then_some(&self.snapshot)
+    }
+
+    pub fn active(&self) -> &BTreeSet<ValidatorId> {
+        &self.active
+    }
+
+    pub fn entry_queue(&self) -> &VecDeque<ValidatorId> {
+        &self.entry_queue
+    }
+
+    pub fn exit_queue(&self) -> &VecDeque<ValidatorId> {
+        &self.exit_queue
+    }
+
+    /// Committee for `slot` sampled from its epoch's snapshot.
+    pub fn committee(&self, slot: u64) -> Option<Vec<ValidatorId>> {
+        self.snapshot_for_slot(slot)
+            .map(|snapshot| snapshot.committee(slot, self.config.committee_size))
+    }
+
+    /// Moves to `epoch` at an epoch boundary: queues validators whose
+    /// eligibility changed, processes the queues up to the churn limit and
+    /// snapshots the new set with `randomness`.
+    pub fn transition(
+        &mut self,
+        epoch: u64,
+        registry: &StakeRegistry,
+        randomness: Vec<u8>,
+    ) -> Result<&ValidatorSnapshot, EpochError> {
+        if epoch != self.snapshot.epoch + 1 {
+            return Err(EpochError::NonSequentialEpoch { current: self.snapshot.epoch, requested: epoch });
+        }
+
+        let eligible: BTreeSet<ValidatorId> =
+            registry.active_validators().into_iter().map(|(validator, _)| validator).collect();
+        self.entry_queue.retain(|validator| eligible.contains(validator));
+        self.exit_queue.retain(|validator| !eligible.contains(validator));
+        for validator in &eligible {
+            if !self.active.contains(validator) && !self.entry_queue.contains(validator) {
+                self.entry_queue.push_back(*validator);
+            }
+        }
+        for validator in &self.active {
+            if !eligible.contains(validator) && !self.exit_queue.contains(validator) {
+                self.exit_queue.push_back(*validator);
+            }
+        }
+
+        let churn = self.config.churn_limit(self.active.len());
+        for validator in self.exit_queue.drain(..churn.min(self.exit_queue.len())) {
+            self.active.remove(&validator);
+        }
+        let entered: Vec<ValidatorId> = self.entry_queue.drain(..churn.min(self.entry_queue.len())).collect();
+        self.active.extend(entered.iter().copied());
+
+        // Entering validators draw on the stake budget before top-ups do.
+        let previous = &self.snapshot;
+        let mut budget = self.config.stake_churn_limit(previous.total_stake());
+        let mut stakes = BTreeMap::new();
+        let topped_up = self.active.iter().filter(|validator| !entered.contains(validator));
+        for validator in entered.iter().chain(topped_up) {
+            if !validate_stake(registry, validator) {
+                continue;
+            }
+            let bonded = registry.bonded(validator);
+            let recognized = previous.stake_of(validator);
+            let growth = bonded.saturating_sub(recognized).min(budget);
+            budget -= growth;
+            stakes.insert(*validator, bonded.min(recognized) + growth);
+        }
+
+        self.snapshot = Self::take_snapshot(epoch, stakes, registry, randomness);
+        Ok(&self.snapshot)
+    }
+
+    fn take_snapshot(
+        epoch: u64,
+        stakes: BTreeMap<ValidatorId, u64>,
+        registry: &StakeRegistry,
+        randomness: Vec<u8>,
+    ) -> ValidatorSnapshot {
+        let consensus_keys = stakes
+            .keys()
+            .filter_map(|validator| {
+                let key = registry.stake(validator)?.consensus_key.clone()?;
+                Some((*validator, key))
+            })
+            .collect();
+        ValidatorSnapshot::new(epoch, stakes, randomness).with_consensus_keys(consensus_keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::address::testing::{ALICE, BOB, CHARLIE, DAVE};
+
+    fn registry_with(stakes: &[(ValidatorId, u64)]) -> StakeRegistry {
+        let mut registry = StakeRegistry::new(3);
+        for (validator, amount) in stakes {
+            registry.bond(validator, *amount).unwrap();
+        }
+        registry
+    }
+
+    fn config(min_churn: usize) -> EpochConfig {
+        EpochConfig { epoch_length: 10, min_churn, churn_quotient: 16, committee_size: 2 }
+    }
+
+    #[test]
+    fn test_epoch_slots() {
+        let config = config(1);
+        assert_eq!(config.epoch_of(0), 0);
+        assert_eq!(config.epoch_of(9), 0);
+        assert_eq!(config.epoch_of(10), 1);
+        assert_eq!(config.first_slot(3), 30);
+        assert_eq!(config.churn_limit(100), 6);
+        assert_eq!(config.churn_limit(10), 1);
+        assert_eq!(config.stake_churn_limit(160_000), 10_000);
+        assert_eq!(config.stake_churn_limit(1_600), MIN_STAKE);
+    }
+
+    #[test]
+    fn test_invalid_config_and_transitions() {
+        let registry = registry_with(&[(ALICE, 1000)]);
+        let zero = EpochConfig { epoch_length: 0, ..EpochConfig::default() };
+        assert_eq!(EpochManager::new(zero, &registry, Vec::new()), Err(EpochError::ZeroEpochLength));
+        assert_eq!(zero.epoch_of(5), 5);
+        assert!(serde_json::from_str::<EpochConfig>(r#"{"epoch_length": 0}"#).is_err());
+        let partial: EpochConfig = serde_json::from_str(r#"{"epoch_length": 8}"#).unwrap();
+        assert_eq!(partial, EpochConfig { epoch_length: 8, ..EpochConfig::default() });
+
+        let mut manager = EpochManager::new(config(1), &registry, Vec::new()).unwrap();
+        assert_eq!(
+            manager.transition(2, &registry, Vec::new()).unwrap_err(),
+            EpochError::NonSequentialEpoch { current: 0, requested: 2 }
+        );
+    }
+
+    #[test]
+    fn test_entry_queue_respects_churn_limit() {
+        let mut registry = registry_with(&[(ALICE, 1000)]);
+        let mut manager = EpochManager::new(config(1), &registry, b"genesis".to_vec()).unwrap();
+        assert_eq!(manager.snapshot().validators().len(), 1);
+
+        // Stake bonded mid-epoch doesn't change the current snapshot.
+        registry.bond(&BOB, 1000).unwrap();
+        registry.bond(&CHARLIE, 1000).unwrap();
+        assert_eq!(manager.snapshot().total_stake(), 1000);
+        assert!(!manager.snapshot().contains(&BOB));
+
+        // One validator enters per epoch, in queue order.
+        manager.transition(1, &registry, b"r1".to_vec()).unwrap();
+        assert_eq!(manager.snapshot().total_stake(), 2000);
+        assert_eq!(manager.entry_queue(), &VecDeque::from(vec![CHARLIE]));
+
+        manager.transition(2, &registry, b"r2".to_vec()).unwrap();
+        assert_eq!(manager.snapshot().total_stake(), 3000);
+        assert!(manager.entry_queue().is_empty());
+    }
+
+    #[test]
+    fn test_stake_churn_limit() {
+        let mut registry = registry_with(&[(ALICE, 16_000)]);
+        let mut manager = EpochManager::new(config(1), &registry, Vec::new()).unwrap();
+
+        // A large entrant and a top-up share one epoch's stake budget, the entrant first.
+        registry.bond(&BOB, 5_000).unwrap();
+        registry.bond(&ALICE, 3_000).unwrap();
+        manager.transition(1, &registry, Vec::new()).unwrap();
+        assert_eq!(manager.snapshot().stake_of(&BOB), 1_000);
+        assert_eq!(manager.snapshot().stake_of(&ALICE), 16_000);
+
+        // The budget grows with the active stake, and the rest phases in over later epochs.
+        manager.transition(2, &registry, Vec::new()).unwrap();
+        assert_eq!(manager.snapshot().total_stake(), 17_000 + 1_062);
+
+        // Stake that leaves loses its voting power at once.
+        registry.unbond(&ALICE, 10_000).unwrap();
+        manager.transition(3, &registry, Vec::new()).unwrap();
+        assert_eq!(manager.snapshot().stake_of(&ALICE), 9_000);
+
+        let mut epoch = 3;
+        while manager.snapshot().total_stake() < 14_000 {
+            epoch += 1;
+            assert!(epoch < 10, "stake failed to phase in");
+            manager.transition(epoch, &registry, Vec::new()).unwrap();
+        }
+        assert_eq!(manager.snapshot().stake_of(&BOB), 5_000);
+    }
+
+    #[test]
+    fn test_exit_queue_and_ineligible_validators() {
+        let mut registry = registry_with(&[(ALICE, 1000), (BOB, 1000), (CHARLIE, 1000)]);
+        let mut manager = EpochManager::new(config(1), &registry, Vec::new()).unwrap();
+
+        registry.unbond(&ALICE, 500).unwrap();
+        registry.jail(&BOB, 100).unwrap();
+        manager.transition(1, &registry, Vec::new()).unwrap();
+
+        // Only alice's exit is processed, but neither validator keeps voting power.
+        assert_eq!(manager.active().len(), 2);
+        assert_eq!(manager.exit_queue(), &VecDeque::from(vec![BOB]));
+        assert_eq!(manager.snapshot().validators().len(), 1);
+        assert!(manager.snapshot().contains(&CHARLIE));
+
+        // A queued exit is cancelled once the validator is eligible again.
+        registry.advance_epoch(100);
+        registry.unjail(&BOB).unwrap();
+        manager.transition(2, &registry, Vec::new()).unwrap();
+        assert!(manager.exit_queue().is_empty());
+        assert!(manager.snapshot().contains(&BOB));
+        assert!(!manager.active().contains(&ALICE));
+    }
+
+    #[test]
+    fn test_snapshot_carries_consensus_keys() {
+        let (_, public_key) = crate::utils::crypto::keypair_from_seed(b"alice");
+        let mut registry = registry_with(&[(ALICE, 1000), (BOB, 1000)]);
+        registry.register_key(&ALICE, &public_key).unwrap();
+        let manager = EpochManager::new(config(1), &registry, Vec::new()).unwrap();
+
+        let registered = registry.stake(&ALICE).unwrap().consensus_key.as_deref();
+        assert_eq!(manager.snapshot().consensus_key(&ALICE), registered);
+        assert_eq!(manager.snapshot().consensus_key(&BOB), None);
+    }
+
+    #[test]
+    fn test_snapshot_for_slot() {
+        let registry = registry_with(&[(ALICE, 1000)]);
+        let mut manager = EpochManager::new(config(1), &registry, Vec::new()).unwrap();
+        assert!(manager.snapshot_for_slot(9).is_some());
+        assert!(manager.snapshot_for_slot(10).is_none());
+        manager.transition(1, &registry, Vec::new()).unwrap();
+        assert!(manager.snapshot_for_slot(9).is_none());
+        assert_eq!(manager.snapshot_for_slot(15).unwrap().epoch(), 1);
+    }
+
+    #[test]
+    fn test_committee_sampling() {
+        let validators = BTreeMap::from([(ALICE, 1000), (BOB, 2000), (CHARLIE, 3000), (DAVE, 4000)]);
+        let snapshot = ValidatorSnapshot::new(0, validators.clone(), b"epoch randomness".to_vec());
+
+        // Committees are deterministic and have distinct members.
+        let committee = snapshot.committee(7, 3);
+        assert_eq!(committee, snapshot.committee(7, 3));
+        assert_eq!(committee.iter().collect::<BTreeSet<_>>().len(), 3);
+        assert_eq!(snapshot.committee(7, 10).len(), 4);
+
+        // Different randomness gives different committees across slots.
+        let other = ValidatorSnapshot::new(0, validators, b"other randomness".to_vec());
+        assert!((0..32).any(|slot| snapshot.committee(slot, 2) != other.committee(slot, 2)));
+
+        // Heavier validators are sampled first more often.
+        let mut first_seats: BTreeMap<ValidatorId, u64> = BTreeMap::new();
+        for slot in 0..4_000 {
+            *first_seats.entry(snapshot.committee(slot, 1)[0]).or_default() += 1;
+        }
+        assert!(first_seats[&DAVE] > first_seats[&CHARLIE]);
+        assert!(first_seats[&CHARLIE] > first_seats[&ALICE]);
+    }
+
+    #[test]
+    fn test_seed_fields_are_separated() {
+        let snapshot = ValidatorSnapshot::new(0, BTreeMap::from([(ALICE, 1000)]), b"epoch".to_vec());
+        let shifted = ValidatorSnapshot::new(0, BTreeMap::from([(ALICE, 1000)]), b"epochp".to_vec());
+        assert_ne!(snapshot.seed(b"proposer", b"x"), snapshot.seed(b"propose", b"rx"));
+        assert_ne!(snapshot.seed(b"proposer", b"x"), shifted.seed(b"roposer", b"x"));
+    }
+}
@@ -0,0 +1,382 @@
+use k256::SecretKey;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+use super::epoch::ValidatorSnapshot;
+use super::slashing::FfgEvidence;
+use super::staking::{StakeRegistry, ValidatorId};
+use crate::utils::crypto::{encode_tagged, verify_tagged, Secp256k1, SignatureScheme};
+use crate::utils::hashing::{ConsensusHasher, Domain};
+
+/// Custom error type for checkpoint votes
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FfgError {
+    #[error("Invalid checkpoint vote signature")]
+    InvalidSignature,
+    #[error("Signer is not in the validator snapshot")]
+    UnknownValidator,
+    #[error("Height {0} is not a checkpoint")]
+    NotCheckpoint(u64),
+    #[error("Target checkpoint must be above the source")]
+    InvalidLink,
+    #[error("Source checkpoint at height {0} is not justified")]
+    SourceNotJustified(u64),
+    #[error("Vote conflicts with an earlier vote by the same validator")]
+    Slashable(Box<FfgEvidence>),
+}
+
+/// A PoW block at a checkpoint height (a multiple of the checkpoint interval).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub height: u64,
+    pub block_hash: Vec<u8>,
+}
+
+/// A validator's signed vote for the link `source -> target`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointVote {
+    pub source: Checkpoint,
+    pub target: Checkpoint,
+    /// Public key of the signing validator, tagged with its signature scheme.
+    pub validator_pubkey: Vec<u8>,
+    /// Tagged signature over `vote_digest(source, target)`.
+    pub signature: Vec<u8>,
+}
+
+impl CheckpointVote {
+    /// Whether the two votes by one validator violate a Casper commandment:
+    /// two different votes for the same target height, or one vote's link
+    /// strictly surrounding the other's.
+    pub fn conflicts_with(&self, other: &CheckpointVote) -> bool {
+        if self.source == other.source && self.target == other.target {
+            return false;
+        }
+        let double_vote = self.target.height == other.target.height;
+        let surrounds = |outer: &CheckpointVote, inner: &CheckpointVote| {
+            outer.source.height < inner.source.height && inner.target.height < outer.target.height
+        };
+        double_vote || surrounds(self, other) || surrounds(other, self)
+    }
+}
+
+/// Digest a validator signs to vote for the link `source -> target`.
+pub fn vote_digest(source: &Checkpoint, target: &Checkpoint) -> Vec<u8> {
+    let mut hasher = ConsensusHasher::new(Domain::CheckpointVote);
+    for checkpoint in [source, target] {
+        hasher.field_u64(checkpoint.height).field(&checkpoint.block_hash);
+    }
+    hasher.finish()
+}
+
+/// Signs a vote for the link `source -> target` with a secp256k1 key.
+pub fn sign_checkpoint_vote(secret_key: &SecretKey, source: Checkpoint, target: Checkpoint) -> CheckpointVote {
+    sign_checkpoint_vote_with::<Secp256k1>(secret_key, source, target)
+}
+
+/// Signs a vote for the link `source -> target` with a key of scheme `S`.
+pub fn sign_checkpoint_vote_with<S: SignatureScheme>(
+    secret_key: &S::SecretKey,
+    source: Checkpoint,
+    target: Checkpoint,
+) -> CheckpointVote {
+    let signature = S::sign(secret_key, &vote_digest(&source, &target)).expect("digest is 32 bytes");
+    CheckpointVote {
+        source,
+        target,
+        validator_pubkey: encode_tagged(S::TAG, &S::public_key(secret_key)),
+        signature: encode_tagged(S::TAG, &signature),
+    }
+}
+
+/// Checks that a checkpoint vote is signed by its `validator_pubkey`.
+pub fn verify_checkpoint_vote(vote: &CheckpointVote) -> bool {
+    verify_tagged(&vote.validator_pubkey, &vote_digest(&vote.source, &vote.target), &vote.signature)
+}
+
+/// Checkpoints newly justified or finalized by a vote.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FinalityUpdate {
+    pub justified: Option<Checkpoint>,
+    pub finalized: Option<Checkpoint>,
+}
+
+/// Casper-FFG finality gadget over the PoW chain.
+///
+/// PoW produces blocks; every `interval` blocks, validators vote on a link
+/// from the latest justified checkpoint they know to the current one. A link
+/// backed by more than 2/3 of the snapshot's stake justifies its target, and
+/// a justified checkpoint whose direct child checkpoint is justified through
+/// it becomes final. Callers must check that a vote's target descends from
+/// its source before handing it in.
+#[derive(Debug, Clone)]
+pub struct FinalityTracker {
+    interval: u64,
+    validators: ValidatorSnapshot,
+    justified: BTreeSet<Checkpoint>,
+    last_justified: Checkpoint,
+    finalized: Checkpoint,
+    links: BTreeMap<(Checkpoint, Checkpoint), BTreeSet<ValidatorId>>,
+    votes: BTreeMap<ValidatorId, Vec<CheckpointVote>>,
+}
+
+impl FinalityTracker {
+    /// Starts with the genesis block justified and finalized.
+    pub fn new(interval: u64, genesis_hash: Vec<u8>, validators: ValidatorSnapshot) -> Self {
+        let genesis = Checkpoint { height: 0, block_hash: genesis_hash };
+        Self {
+            interval: interval.max(1),
+            validators,
+            justified: BTreeSet::from([genesis.clone()]),
+            last_justified: genesis.clone(),
+            finalized: genesis,
+            links: BTreeMap::new(),
+            votes: BTreeMap::new(),
+        }
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn is_checkpoint(&self, height: u64) -> bool {
+        height.is_multiple_of(self.interval)
+    }
+
+    pub fn is_justified(&self, checkpoint: &Checkpoint) -> bool {
+        self.justified.contains(checkpoint)
+    }
+
+    /// The highest justified checkpoint, which validators use as their vote source.
+    pub fn last_justified(&self) -> &Checkpoint {
+        &self.last_justified
+    }
+
+    pub fn finalized(&self) -> &Checkpoint {
+        &self.finalized
+    }
+
+    /// Replaces the voting validator set at an epoch boundary.
+    pub fn set_validators(&mut self, validators: ValidatorSnapshot) {
+        self.validators = validators;
+    }
+
+    /// Counts a checkpoint vote, returning any checkpoints it justified or
+    /// finalized. A vote conflicting with an earlier one from the same
+    /// validator is rejected with the evidence needed to slash it.
+    pub fn process_vote(
+        &mut self,
+        registry: &StakeRegistry,
+        vote: CheckpointVote,
+    ) -> Result<FinalityUpdate, FfgError> {
+        if !verify_checkpoint_vote(&vote) {
+            return Err(FfgError::InvalidSignature);
+        }
+        let validator = registry
+            .validator_by_key(&vote.validator_pubkey)
+            .filter(|validator| self.validators.contains(validator))
+            .copied()
+            .ok_or(FfgError::UnknownValidator)?;
+        for checkpoint in [&vote.source, &vote.target] {
+            if !self.is_checkpoint(checkpoint.height) {
+                return Err(FfgError::NotCheckpoint(checkpoint.height));
+            }
+        }
+        if vote.target.height <= vote.source.height {
+            return Err(FfgError::InvalidLink);
+        }
+
+        let previous = self.votes.entry(validator).or_default();
+        if previous.contains(&vote) {
+            return Ok(FinalityUpdate::default());
+        }
+        if let Some(conflict) = previous.iter().find(|earlier| earlier.conflicts_with(&vote)) {
+            let evidence = FfgEvidence { first: conflict.clone(), second: vote };
+            return Err(FfgError::Slashable(Box::new(evidence)));
+        }
+        if !self.justified.contains(&vote.source) {
+            return Err(FfgError::SourceNotJustified(vote.source.height));
+        }
+        previous.push(vote.clone());
+
+        let link = (vote.source, vote.target);
+        let voters = self.links.entry(link.clone()).or_default();
+        voters.insert(validator);
+        let stake: u128 = voters.iter().map(|voter| self.validators.stake_of(voter) as u128).sum();
+        if stake * 3 <= self.validators.total_stake() as u128 * 2 {
+            return Ok(FinalityUpdate::default());
+        }
+
+        let (source, target) = link;
+        let mut update = FinalityUpdate::default();
+        if self.justified.insert(target.clone()) {
+            if target.height > self.last_justified.height {
+                self.last_justified = target.clone();
+            }
+            update.justified = Some(target.clone());
+        }
+        if target.height == source.height + self.interval && source.height > self.finalized.height {
+            self.finalized = source.clone();
+            update.finalized = Some(source);
+        }
+        Ok(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::address::Address;
+    use crate::utils::crypto::generate_keypair;
+
+    struct Validators {
+        registry: StakeRegistry,
+        snapshot: ValidatorSnapshot,
+        keys: Vec<SecretKey>,
+    }
+
+    fn validators(count: usize) -> Validators {
+        let mut registry = StakeRegistry::default();
+        let mut keys = Vec::new();
+        let mut stakes = BTreeMap::new();
+        for _ in 0..count {
+            let (secret_key, public_key) = generate_keypair();
+            let validator = Address::from(&secret_key);
+            registry.bond(&validator, 1000).unwrap();
+            registry.register_key(&validator, &public_key).unwrap();
+            stakes.insert(validator, 1000);
+            keys.push(secret_key);
+        }
+        Validators { registry, snapshot: ValidatorSnapshot::new(0, stakes, Vec::new()), keys }
+    }
+
+    fn checkpoint(height: u64) -> Checkpoint {
+        Checkpoint { height, block_hash: format!("block {height}").into_bytes() }
+    }
+
+    fn genesis() -> Checkpoint {
+        Checkpoint { height: 0, block_hash: b"genesis".to_vec() }
+    }
+
+    #[test]
+    fn test_sign_and_verify_vote() {
+        let (secret_key, _) = generate_keypair();
+        let vote = sign_checkpoint_vote(&secret_key, genesis(), checkpoint(10));
+        assert!(verify_checkpoint_vote(&vote));
+
+        let tampered = CheckpointVote { target: checkpoint(20), ..vote };
+        assert!(!verify_checkpoint_vote(&tampered));
+    }
+
+    #[test]
+    fn test_justification_and_finalization() {
+        let Validators { registry, snapshot, keys } = validators(4);
+        let mut tracker = FinalityTracker::new(10, b"genesis".to_vec(), snapshot);
+
+        // Two of four validators are not a supermajority.
+        for key in &keys[..2] {
+            let update = tracker.process_vote(&registry, sign_checkpoint_vote(key, genesis(), checkpoint(10))).unwrap();
+            assert_eq!(update, FinalityUpdate::default());
+        }
+
+        // The third vote justifies the target and finalizes genesis' direct child link.
+        let update = tracker.process_vote(&registry, sign_checkpoint_vote(&keys[2], genesis(), checkpoint(10))).unwrap();
+        assert_eq!(update.justified, Some(checkpoint(10)));
+        assert_eq!(update.finalized, None); // Genesis is already final
+        assert_eq!(tracker.last_justified(), &checkpoint(10));
+
+        // Justifying the next checkpoint from it finalizes it.
+        for key in &keys[..3] {
+            tracker.process_vote(&registry, sign_checkpoint_vote(key, checkpoint(10), checkpoint(20))).unwrap();
+        }
+        assert_eq!(tracker.finalized(), &checkpoint(10));
+        assert!(tracker.is_justified(&checkpoint(20)));
+
+        // Skipping a checkpoint justifies the target without finalizing the source.
+        for key in &keys[..3] {
+            tracker.process_vote(&registry, sign_checkpoint_vote(key, checkpoint(20), checkpoint(40))).unwrap();
+        }
+        assert_eq!(tracker.last_justified(), &checkpoint(40));
+        assert_eq!(tracker.finalized(), &checkpoint(10));
+    }
+
+    #[test]
+    fn test_invalid_votes_rejected() {
+        let Validators { registry, snapshot, keys } = validators(4);
+        let mut tracker = FinalityTracker::new(10, b"genesis".to_vec(), snapshot);
+        let (outsider, _) = generate_keypair();
+
+        let vote = |key, source, target| sign_checkpoint_vote(key, source, target);
+        assert_eq!(
+            tracker.process_vote(&registry, vote(&outsider, genesis(), checkpoint(10))),
+            Err(FfgError::UnknownValidator)
+        );
+        assert_eq!(
+            tracker.process_vote(&registry, vote(&keys[0], genesis(), checkpoint(15))),
+            Err(FfgError::NotCheckpoint(15))
+        );
+        assert_eq!(
+            tracker.process_vote(&registry, vote(&keys[0], checkpoint(10), checkpoint(10))),
+            Err(FfgError::InvalidLink)
+        );
+        assert_eq!(
+            tracker.process_vote(&registry, vote(&keys[0], checkpoint(10), checkpoint(20))),
+            Err(FfgError::SourceNotJustified(10))
+        );
+
+        let mut forged = vote(&keys[0], genesis(), checkpoint(10));
+        forged.signature = vote(&keys[1], genesis(), checkpoint(10)).signature;
+        assert_eq!(tracker.process_vote(&registry, forged), Err(FfgError::InvalidSignature));
+    }
+
+    #[test]
+    fn test_double_vote_detected() {
+        let Validators { registry, snapshot, keys } = validators(4);
+        let mut tracker = FinalityTracker::new(10, b"genesis".to_vec(), snapshot);
+        let first = sign_checkpoint_vote(&keys[0], genesis(), checkpoint(10));
+        tracker.process_vote(&registry, first.clone()).unwrap();
+
+        // Re-sending the same vote is harmless.
+        assert_eq!(tracker.process_vote(&registry, first.clone()), Ok(FinalityUpdate::default()));
+
+        let fork = Checkpoint { height: 10, block_hash: b"fork".to_vec() };
+        let second = sign_checkpoint_vote(&keys[0], genesis(), fork);
+        match tracker.process_vote(&registry, second.clone()) {
+            Err(FfgError::Slashable(evidence)) => assert_eq!(*evidence, FfgEvidence { first, second }),
+            other => panic!("expected slashable vote, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_surround_vote_detected() {
+        let Validators { registry, snapshot, keys } = validators(4);
+        let mut tracker = FinalityTracker::new(10, b"genesis".to_vec(), snapshot);
+        for key in &keys[..3] {
+            tracker.process_vote(&registry, sign_checkpoint_vote(key, genesis(), checkpoint(10))).unwrap();
+        }
+        for key in &keys[..3] {
+            tracker.process_vote(&registry, sign_checkpoint_vote(key, checkpoint(10), checkpoint(20))).unwrap();
+        }
+
+        // genesis -> 30 surrounds 10 -> 20.
+        let surrounding = sign_checkpoint_vote(&keys[0], genesis(), checkpoint(30));
+        assert!(matches!(tracker.process_vote(&registry, surrounding), Err(FfgError::Slashable(_))));
+
+        // A validator that didn't vote 10 -> 20 may vote genesis -> 30.
+        let honest = sign_checkpoint_vote(&keys[3], genesis(), checkpoint(30));
+        assert!(tracker.process_vote(&registry, honest).is_ok());
+    }
+
+    #[test]
+    fn test_conflicts_with() {
+        let (key, _) = generate_keypair();
+        let vote = |source, target| sign_checkpoint_vote(&key, checkpoint(source), checkpoint(target));
+
+        assert!(!vote(0, 10).conflicts_with(&vote(0, 10)));
+        assert!(!vote(0, 10).conflicts_with(&vote(10, 20)));
+        assert!(!vote(0, 20).conflicts_with(&vote(10, 30)));
+        assert!(vote(0, 20).conflicts_with(&vote(10, 20)));
+        assert!(vote(0, 30).conflicts_with(&vote(10, 20)));
+        assert!(vote(10, 20).conflicts_with(&vote(0, 30)));
+    }
+}
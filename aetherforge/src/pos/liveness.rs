@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::epoch::ValidatorSnapshot;
+use super::rewards::FULL_PARTICIPATION;
+use super::staking::{StakeRegistry, StakingError, ValidatorId};
+
+/// Penalty parameters for offline validators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LivenessConfig {
+    /// Penalty per missed duty, in basis points of the validator's stake.
+    pub missed_duty_penalty_bps: u64,
+    /// Participation, in basis points, below which a validator counts as
+    /// inactive for the epoch.
+    pub active_participation_bps: u64,
+    /// Epochs without finality after which the inactivity leak starts (K).
+    pub inactivity_threshold_epochs: u64,
+    /// Inactivity score added for every inactive epoch.
+    pub inactivity_score_bias: u64,
+    /// Inactivity score forgiven per epoch while the chain is finalizing.
+    pub inactivity_score_recovery: u64,
+    /// Leak penalty is `stake * score / (bias * quotient)` per epoch.
+    pub inactivity_penalty_quotient: u64,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        Self {
+            missed_duty_penalty_bps: 1,
+            active_participation_bps: 5_000,
+            inactivity_threshold_epochs: 4,
+            inactivity_score_bias: 4,
+            inactivity_score_recovery: 16,
+            inactivity_penalty_quotient: 1 << 24,
+        }
+    }
+}
+
+/// Duties a validator was assigned and performed in the current epoch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DutyRecord {
+    pub assigned: u64,
+    pub performed: u64,
+}
+
+impl DutyRecord {
+    /// Share of duties performed, out of `FULL_PARTICIPATION`.
+    pub fn participation(&self) -> u64 {
+        if self.assigned == 0 {
+            return 0;
+        }
+        (self.performed as u128 * FULL_PARTICIPATION as u128 / self.assigned as u128) as u64
+    }
+}
+
+/// Penalties applied at the end of an epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LivenessReport {
+    pub epoch: u64,
+    pub in_leak: bool,
+    /// Stake burned per validator, for missed duties and the leak combined.
+    pub penalties: Vec<(ValidatorId, u64)>,
+}
+
+/// Tracks validator participation and applies liveness penalties.
+///
+/// Each missed duty costs a small fraction of stake. Validators that stay
+/// inactive also build up an inactivity score, and once the chain has gone
+/// more than `inactivity_threshold_epochs` without finality, each epoch costs
+/// them stake in proportion to that score. Since the score itself grows
+/// every epoch, the stake lost by an offline validator grows quadratically
+/// with the length of the stall, until the online validators hold the
+/// supermajority needed to finalize again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LivenessTracker {
+    config: LivenessConfig,
+    duties: BTreeMap<ValidatorId, DutyRecord>,
+    inactivity_scores: BTreeMap<ValidatorId, u64>,
+}
+
+impl LivenessTracker {
+    pub fn new(config: LivenessConfig) -> Self {
+        Self { config, duties: BTreeMap::new(), inactivity_scores: BTreeMap::new() }
+    }
+
+    pub fn config(&self) -> &LivenessConfig {
+        &self.config
+    }
+
+    /// Records one assigned duty (a vote, proposal or committee signature)
+    /// and whether the validator performed it.
+    pub fn record_duty(&mut self, validator: &ValidatorId, performed: bool) {
+        let record = self.duties.entry(*validator).or_default();
+        record.assigned += 1;
+        if performed {
+            record.performed += 1;
+        }
+    }
+
+    pub fn duties(&self, validator: &ValidatorId) -> DutyRecord {
+        self.duties.get(validator).copied().unwrap_or_default()
+    }
+
+    /// Participation of every validator with duties this epoch, in the form
+    /// `RewardEngine::compute_epoch` expects.
+    pub fn participation(&self) -> BTreeMap<ValidatorId, u64> {
+        self.duties
+            .iter()
+            .map(|(validator, record)| (*validator, record.participation()))
+            .collect()
+    }
+
+    pub fn inactivity_score(&self, validator: &ValidatorId) -> u64 {
+        self.inactivity_scores.get(validator).copied().unwrap_or(0)
+    }
+
+    /// Whether the chain is leaking at `epoch` given the last finalized epoch.
+    pub fn in_leak(&self, epoch: u64, finalized_epoch: u64) -> bool {
+        epoch.saturating_sub(finalized_epoch) > self.config.inactivity_threshold_epochs
+    }
+
+    /// Closes the snapshot's epoch: updates inactivity scores, burns
+    /// penalties sized by snapshot stake from the registry and resets the duty
+    /// records for the next epoch.
+    pub fn process_epoch(
+        &mut self,
+        registry: &mut StakeRegistry,
+        snapshot: &ValidatorSnapshot,
+        finalized_epoch: u64,
+    ) -> Result<LivenessReport, StakingError> {
+        let config = self.config;
+        let epoch = snapshot.epoch();
+        let in_leak = self.in_leak(epoch, finalized_epoch);
+        let mut penalties = Vec::new();
+
+        for (validator, record) in std::mem::take(&mut self.duties) {
+            let stake = snapshot.stake_of(&validator) as u128;
+            let missed = (record.assigned - record.performed) as u128;
+            let mut penalty = stake * missed * config.missed_duty_penalty_bps as u128 / 10_000;
+
+            let active = record.participation() >= config.active_participation_bps;
+            let score = self.inactivity_scores.entry(validator).or_default();
+            if active {
+                *score -= (*score).min(1);
+            } else {
+                *score += config.inactivity_score_bias;
+            }
+            if !in_leak {
+                *score -= (*score).min(config.inactivity_score_recovery);
+            }
+
+            let denominator = config.inactivity_score_bias as u128 * config.inactivity_penalty_quotient as u128;
+            if in_leak && denominator > 0 {
+                penalty += stake * *score as u128 / denominator;
+            }
+            if *score == 0 {
+                self.inactivity_scores.remove(&validator);
+            }
+
+            let burned = registry.penalize(&validator, penalty.min(u64::MAX as u128) as u64)?;
+            if burned > 0 {
+                penalties.push((validator, burned));
+            }
+        }
+
+        Ok(LivenessReport { epoch, in_leak, penalties })
+    }
+}
+
+impl Default for LivenessTracker {
+    fn default() -> Self {
+        Self::new(LivenessConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::address::testing::{ALICE, BOB, CHARLIE, DAVE};
+
+    fn registry_with(stakes: &[(ValidatorId, u64)]) -> StakeRegistry {
+        let mut registry = StakeRegistry::new(3);
+        for (validator, amount) in stakes {
+            registry.bond(validator, *amount).unwrap();
+        }
+        registry
+    }
+
+    fn snapshot(registry: &StakeRegistry, epoch: u64) -> ValidatorSnapshot {
+        ValidatorSnapshot::new(epoch, registry.active_validators().into_iter().collect(), Vec::new())
+    }
+
+    /// Gives every validator `duties` duties, performed only by the online ones.
+    fn run_duties(tracker: &mut LivenessTracker, validators: &[ValidatorId], online: &[ValidatorId], duties: u64) {
+        for validator in validators {
+            for _ in 0..duties {
+                tracker.record_duty(validator, online.contains(validator));
+            }
+        }
+    }
+
+    #[test]
+    fn test_participation() {
+        let mut tracker = LivenessTracker::default();
+        tracker.record_duty(&ALICE, true);
+        tracker.record_duty(&ALICE, false);
+        tracker.record_duty(&BOB, true);
+        assert_eq!(tracker.duties(&ALICE), DutyRecord { assigned: 2, performed: 1 });
+
+        let participation = tracker.participation();
+        assert_eq!(participation[&ALICE], 5_000);
+        assert_eq!(participation[&BOB], FULL_PARTICIPATION);
+        assert!(!participation.contains_key(&CHARLIE));
+    }
+
+    #[test]
+    fn test_missed_duty_penalty() {
+        let mut registry = registry_with(&[(ALICE, 1_000_000), (BOB, 1_000_000)]);
+        let mut tracker = LivenessTracker::default();
+        run_duties(&mut tracker, &[ALICE, BOB], &[ALICE], 32);
+
+        // One basis point of stake per missed duty, nothing for full participation.
+        let snapshot = snapshot(&registry, 1);
+
+        // Penalties are sized by the snapshot stake, not by stake bonded mid-epoch.
+        registry.bond(&BOB, 1_000_000).unwrap();
+        let report = tracker.process_epoch(&mut registry, &snapshot, 0).unwrap();
+        assert!(!report.in_leak);
+        assert_eq!(report.penalties, vec![(BOB, 3_200)]);
+        assert_eq!(registry.bonded(&BOB), 1_996_800);
+        assert!(tracker.participation().is_empty());
+
+        // Outside a leak the inactivity score is forgiven straight away.
+        assert_eq!(tracker.inactivity_score(&BOB), 0);
+    }
+
+    #[test]
+    fn test_inactivity_leak_is_quadratic() {
+        let config = LivenessConfig {
+            missed_duty_penalty_bps: 0,
+            inactivity_penalty_quotient: 1 << 20,
+            ..Default::default()
+        };
+        let mut registry = registry_with(&[(ALICE, 1 << 40), (BOB, 1 << 40)]);
+        let mut tracker = LivenessTracker::new(config);
+
+        let mut leaked = Vec::new();
+        for epoch in 1..=24 {
+            run_duties(&mut tracker, &[ALICE, BOB], &[ALICE], 1);
+            let snapshot = snapshot(&registry, epoch);
+            let report = tracker.process_epoch(&mut registry, &snapshot, 0).unwrap();
+            assert_eq!(report.in_leak, epoch > 4);
+            leaked.push(report.penalties.iter().map(|(_, amount)| *amount).sum::<u64>());
+        }
+
+        // Nothing leaks before K epochs without finality, and online validators never leak.
+        assert!(leaked[..4].iter().all(|amount| *amount == 0));
+        assert_eq!(registry.bonded(&ALICE), 1 << 40);
+        assert_eq!(tracker.inactivity_score(&ALICE), 0);
+        assert_eq!(tracker.inactivity_score(&BOB), 20 * 4);
+
+        // The per-epoch penalty grows by a constant step, so the total leaked grows quadratically.
+        for pair in leaked[4..].windows(2) {
+            let step = pair[1] as i64 - pair[0] as i64;
+            assert!((step - (1 << 20)).abs() < (1 << 20) / 100, "uneven leak step {step}");
+        }
+    }
+
+    #[test]
+    fn test_leak_restores_supermajority() {
+        let config = LivenessConfig { inactivity_penalty_quotient: 1 << 8, ..Default::default() };
+        let validators = [ALICE, BOB, CHARLIE, DAVE];
+        let mut registry = registry_with(&validators.map(|validator| (validator, 1_000_000)));
+        let mut tracker = LivenessTracker::new(config);
+
+        // Half the stake goes offline, so finality stalls at epoch 0.
+        let online = [ALICE, BOB];
+        let online_stake = |registry: &StakeRegistry| online.iter().map(|v| registry.bonded(v)).sum::<u64>();
+        let mut epoch = 0;
+        while online_stake(&registry) as u128 * 3 <= registry.total_bonded() as u128 * 2 {
+            epoch += 1;
+            assert!(epoch < 200, "leak failed to restore the supermajority");
+            run_duties(&mut tracker, &validators, &online, 4);
+            let snapshot = snapshot(&registry, epoch);
+            tracker.process_epoch(&mut registry, &snapshot, 0).unwrap();
+        }
+        assert_eq!(registry.bonded(&ALICE), 1_000_000);
+        assert!(registry.bonded(&CHARLIE) < 500_000);
+
+        // Once the chain finalizes again, scores of returning validators recover.
+        let score = tracker.inactivity_score(&CHARLIE);
+        run_duties(&mut tracker, &validators, &validators, 4);
+        let snapshot = snapshot(&registry, epoch + 1);
+        tracker.process_epoch(&mut registry, &snapshot, epoch).unwrap();
+        assert_eq!(tracker.inactivity_score(&CHARLIE), score.saturating_sub(1 + 16));
+    }
+}
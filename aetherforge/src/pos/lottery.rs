@@ -0,0 +1,172 @@
+use k256::SecretKey;
+
+use super::epoch::ValidatorSnapshot;
+use super::staking::ValidatorId;
+use crate::utils::crypto::{decode_tagged, SchemeTag};
+use crate::utils::hashing::{ConsensusHasher, Domain};
+use crate::utils::vrf::{self, VrfProof};
+
+/// Private proof that a validator won the proposer lottery for a slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EligibilityProof {
+    pub slot: u64,
+    pub proof: VrfProof,
+}
+
+/// Stake-weighted VRF lottery deciding who may propose in each slot.
+///
+/// A validator holding `stake` out of `total_stake` wins a slot with
+/// probability `active_slots * stake / total_stake`, where `active_slots`
+/// is the expected number of winners per slot in basis points. Stakes, keys
+/// and randomness all come from the epoch's validator snapshot. Since only
+/// the validator can evaluate its VRF, winners aren't known until they
+/// publish their proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotLottery {
+    active_slots_bps: u64,
+}
+
+impl SlotLottery {
+    pub fn new(active_slots_bps: u64) -> Self {
+        Self { active_slots_bps: active_slots_bps.min(10_000) }
+    }
+
+    /// Attempts to claim `slot` for `validator`, returning a proof if it is eligible.
+    pub fn try_claim(
+        &self,
+        secret_key: &SecretKey,
+        snapshot: &ValidatorSnapshot,
+        validator: &ValidatorId,
+        slot: u64,
+    ) -> Option<EligibilityProof> {
+        let proof = vrf::prove(secret_key, &lottery_input(snapshot.randomness(), slot)).ok()?;
+        if self.is_winning(&proof.output(), snapshot.stake_of(validator), snapshot.total_stake()) {
+            Some(EligibilityProof { slot, proof })
+        } else {
+            None
+        }
+    }
+
+    /// Checks a published eligibility proof against the consensus key and
+    /// stake `validator` has in the snapshot. Only secp256k1 keys can run the VRF.
+    pub fn verify(
+        &self,
+        snapshot: &ValidatorSnapshot,
+        validator: &ValidatorId,
+        eligibility: &EligibilityProof,
+    ) -> bool {
+        let Some(Ok((SchemeTag::Secp256k1, public_key))) = snapshot.consensus_key(validator).map(decode_tagged) else {
+            return false;
+        };
+        let input = lottery_input(snapshot.randomness(), eligibility.slot);
+        match vrf::verify(public_key, &input, &eligibility.proof) {
+            Ok(output) => self.is_winning(&output, snapshot.stake_of(validator), snapshot.total_stake()),
+            Err(_) => false,
+        }
+    }
+
+    /// Threshold out of 2^64 below which a VRF output wins.
+    pub fn threshold(&self, stake: u64, total_stake: u64) -> u128 {
+        if total_stake == 0 {
+            return 0;
+        }
+        let stake = stake.min(total_stake) as u128;
+        (stake * self.active_slots_bps as u128 * (1u128 << 64)) / (total_stake as u128 * 10_000)
+    }
+
+    fn is_winning(&self, output: &[u8], stake: u64, total_stake: u64) -> bool {
+        let draw = u64::from_be_bytes(output[..8].try_into().unwrap());
+        (draw as u128) < self.threshold(stake, total_stake)
+    }
+}
+
+impl Default for SlotLottery {
+    fn default() -> Self {
+        Self::new(5_000) // One proposer every other slot on average
+    }
+}
+
+/// VRF input for a slot, bound to the epoch randomness.
+pub fn lottery_input(randomness: &[u8], slot: u64) -> Vec<u8> {
+    ConsensusHasher::new(Domain::LotteryInput).field(randomness).field_u64(slot).finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::address::testing::{ALICE, BOB, CHARLIE};
+    use crate::utils::crypto::{compress_public_key, encode_tagged, generate_keypair};
+    use std::collections::BTreeMap;
+
+    /// Snapshot with `stakes` in which alice registered `public_key`.
+    fn snapshot(stakes: &[(ValidatorId, u64)], public_key: &[u8], randomness: &[u8]) -> ValidatorSnapshot {
+        let key = encode_tagged(SchemeTag::Secp256k1, &compress_public_key(public_key).unwrap());
+        ValidatorSnapshot::new(0, stakes.iter().copied().collect(), randomness.to_vec())
+            .with_consensus_keys(BTreeMap::from([(ALICE, key)]))
+    }
+
+    #[test]
+    fn test_claim_and_verify() {
+        let lottery = SlotLottery::new(10_000);
+        let (secret_key, public_key) = generate_keypair();
+        let (_, other_public_key) = generate_keypair();
+        let rekeyed = snapshot(&[(ALICE, 1000)], &other_public_key, b"epoch");
+        let reseeded = snapshot(&[(ALICE, 1000)], &public_key, b"other epoch");
+        let snapshot = snapshot(&[(ALICE, 1000)], &public_key, b"epoch");
+
+        // A validator with all the stake wins every slot.
+        for slot in 0..10 {
+            let eligibility = lottery.try_claim(&secret_key, &snapshot, &ALICE, slot).unwrap();
+            assert!(lottery.verify(&snapshot, &ALICE, &eligibility));
+
+            // The proof doesn't transfer to another key, validator, slot or randomness.
+            assert!(!lottery.verify(&rekeyed, &ALICE, &eligibility));
+            assert!(!lottery.verify(&snapshot, &BOB, &eligibility));
+            assert!(!lottery.verify(&reseeded, &ALICE, &eligibility));
+            let moved = EligibilityProof { slot: slot + 1, ..eligibility };
+            assert!(!lottery.verify(&snapshot, &ALICE, &moved));
+        }
+
+        // Validators outside the snapshot never win.
+        assert!(lottery.try_claim(&secret_key, &snapshot, &CHARLIE, 0).is_none());
+    }
+
+    #[test]
+    fn test_verify_rejects_inflated_stake() {
+        let lottery = SlotLottery::new(10_000);
+        let (secret_key, public_key) = generate_keypair();
+        let whale = snapshot(&[(ALICE, 1000)], &public_key, b"epoch");
+        let minnow = snapshot(&[(ALICE, 1), (BOB, 999)], &public_key, b"epoch");
+
+        // A slot won with full stake doesn't verify against a snapshot where the stake is tiny.
+        let eligibility = (0..1000)
+            .filter_map(|slot| lottery.try_claim(&secret_key, &whale, &ALICE, slot))
+            .find(|e| !lottery.is_winning(&e.proof.output(), 1, 1000))
+            .unwrap();
+        assert!(!lottery.verify(&minnow, &ALICE, &eligibility));
+    }
+
+    #[test]
+    fn test_win_rate_tracks_stake() {
+        let lottery = SlotLottery::new(10_000);
+        let (secret_key, public_key) = generate_keypair();
+        let snapshot = snapshot(&[(ALICE, 250), (BOB, 750)], &public_key, b"epoch");
+
+        // With a quarter of the stake, roughly a quarter of slots are won.
+        let slots = 2000;
+        let wins = (0..slots)
+            .filter(|slot| lottery.try_claim(&secret_key, &snapshot, &ALICE, *slot).is_some())
+            .count();
+        let rate = wins as f64 / slots as f64;
+        assert!((rate - 0.25).abs() < 0.05, "win rate {rate}");
+    }
+
+    #[test]
+    fn test_threshold() {
+        let lottery = SlotLottery::new(5_000);
+        assert_eq!(lottery.threshold(1000, 1000), 1u128 << 63);
+        assert_eq!(lottery.threshold(500, 1000), 1u128 << 62);
+        assert_eq!(lottery.threshold(1000, 0), 0);
+        assert_eq!(SlotLottery::new(20_000).threshold(1000, 1000), 1u128 << 64);
+    }
+}
@@ -1,50 +1,158 @@
 pub mod staking;
 pub mod consensus;
+pub mod lottery;
+pub mod rewards;
+pub mod slashing;
+pub mod bft;
+pub mod epoch;
+pub mod ffg;
+pub mod liveness;
+pub mod spec;
 
-/// Combines staking and consensus logic.
-pub fn anvil_block(validator: &str, stake: u64, block_data: &[u8]) -> Vec<u8> {
-    if staking::validate_stake(validator, stake) {
-        consensus::finalize_block(block_data)
-    } else {
-        Vec::new()
+use k256::SecretKey;
+use thiserror::Error;
+
+use crate::utils::crypto::{encode_tagged, Secp256k1, SignatureScheme};
+
+use consensus::Finalization;
+use epoch::ValidatorSnapshot;
+use staking::ValidatorId;
+
+/// Custom error type for block finalization
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AnvilError {
+    #[error("Signing key does not belong to a validator in the epoch snapshot")]
+    NotInSnapshot,
+    #[error("Invalid finalization signature")]
+    InvalidSignature,
+}
+
+/// Combines staking and consensus logic: the validator owning `secret_key`
+/// signs a finalization of `block_data` at `height`. Eligibility is decided
+/// by the epoch's snapshot, so stake moving mid-epoch doesn't change it.
+pub fn anvil_block(
+    snapshot: &ValidatorSnapshot,
+    secret_key: &SecretKey,
+    height: u64,
+    block_data: &[u8],
+) -> Result<Finalization, AnvilError> {
+    anvil_block_with::<Secp256k1>(snapshot, secret_key, height, block_data)
+}
+
+/// [`anvil_block`] for a validator whose consensus key uses scheme `S`.
+pub fn anvil_block_with<S: SignatureScheme>(
+    snapshot: &ValidatorSnapshot,
+    secret_key: &S::SecretKey,
+    height: u64,
+    block_data: &[u8],
+) -> Result<Finalization, AnvilError> {
+    eligible_validator(snapshot, &encode_tagged(S::TAG, &S::public_key(secret_key)))?;
+    let block_hash = consensus::finalize_block(block_data);
+    Ok(consensus::sign_finalization_with::<S>(secret_key, height, &block_hash))
+}
+
+/// Verifies a finalization's signature and that its signer is a validator
+/// of the epoch's snapshot, returning the validator.
+pub fn verify_finalization(
+    snapshot: &ValidatorSnapshot,
+    finalization: &Finalization,
+) -> Result<ValidatorId, AnvilError> {
+    if !consensus::verify_finalization(finalization) {
+        return Err(AnvilError::InvalidSignature);
     }
+    eligible_validator(snapshot, &finalization.validator_pubkey)
+}
+
+fn eligible_validator(snapshot: &ValidatorSnapshot, public_key: &[u8]) -> Result<ValidatorId, AnvilError> {
+    snapshot.validator_by_key(public_key).ok_or(AnvilError::NotInSnapshot)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::address::testing::BOB;
+    use crate::utils::address::Address;
+    use crate::utils::crypto::generate_keypair;
+    use epoch::{EpochConfig, EpochManager};
+    use staking::StakeRegistry;
 
-    // Mock implementations for testing
-    mod staking {
-        pub fn validate_stake(validator: &str, stake: u64) -> bool {
-            stake >= 1000 // Mock minimum stake
-        }
-    }
+    const VALIDATOR: Address = Address::repeat_byte(0x01);
 
-    mod consensus {
-        pub fn finalize_block(block_data: &[u8]) -> Vec<u8> {
-            // Mock consensus: return the input data with a byte appended.
-            let mut result = block_data.to_vec();
-            result.push(42);
-            result
-        }
+    fn epochs(registry: &StakeRegistry) -> EpochManager {
+        EpochManager::new(EpochConfig::default(), registry, b"genesis".to_vec()).unwrap()
     }
 
     #[test]
     fn test_anvil_block() {
-        let validator = "test_validator";
-        let valid_stake = 1500;
-        let invalid_stake = 500;
+        let (secret_key, public_key) = generate_keypair();
+        let (low_secret_key, low_public_key) = generate_keypair();
+        let (unknown_secret_key, _) = generate_keypair();
+        let mut registry = StakeRegistry::default();
+        registry.bond(&VALIDATOR, 1500).unwrap();
+        registry.register_key(&VALIDATOR, &public_key).unwrap();
+        registry.bond(&BOB, 500).unwrap();
+        registry.register_key(&BOB, &low_public_key).unwrap();
+        let snapshot = epochs(&registry).snapshot().clone();
         let block_data = b"test_block_data";
 
         // Test with valid stake
-        let result_valid = anvil_block(validator, valid_stake, block_data);
-        let mut expected_valid = block_data.to_vec();
-        expected_valid.push(42);
-        assert_eq!(result_valid, expected_valid);
-
-        // Test with invalid stake
-        let result_invalid = anvil_block(validator, invalid_stake, block_data);
-        assert_eq!(result_invalid, Vec::new());
+        let finalization = anvil_block(&snapshot, &secret_key, 1, block_data).unwrap();
+        assert_eq!(finalization.height, 1);
+        assert_eq!(finalization.block_hash, consensus::finalize_block(block_data));
+        assert_eq!(verify_finalization(&snapshot, &finalization), Ok(VALIDATOR));
+
+        // Test with insufficient stake, which keeps the validator out of the snapshot
+        let result_invalid = anvil_block(&snapshot, &low_secret_key, 1, block_data);
+        assert_eq!(result_invalid, Err(AnvilError::NotInSnapshot));
+
+        // Test with unregistered validator
+        let result_unknown = anvil_block(&snapshot, &unknown_secret_key, 1, block_data);
+        assert_eq!(result_unknown, Err(AnvilError::NotInSnapshot));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_verify_finalization() {
+        let (secret_key, public_key) = generate_keypair();
+        let mut registry = StakeRegistry::default();
+        registry.bond(&VALIDATOR, 1500).unwrap();
+        registry.register_key(&VALIDATOR, &public_key).unwrap();
+        let mut epochs = epochs(&registry);
+        let finalization = anvil_block(epochs.snapshot(), &secret_key, 3, b"block").unwrap();
+
+        // Tampered finalizations are rejected.
+        let tampered = Finalization { height: 4, ..finalization.clone() };
+        assert_eq!(verify_finalization(epochs.snapshot(), &tampered), Err(AnvilError::InvalidSignature));
+
+        // Unbonding mid-epoch doesn't change who may finalize in the current epoch...
+        registry.unbond(&VALIDATOR, 1000).unwrap();
+        assert_eq!(verify_finalization(epochs.snapshot(), &finalization), Ok(VALIDATOR));
+
+        // ...but the validator is out of the next epoch's snapshot.
+        epochs.transition(1, &registry, b"r1".to_vec()).unwrap();
+        assert_eq!(verify_finalization(epochs.snapshot(), &finalization), Err(AnvilError::NotInSnapshot));
+    }
+
+    #[test]
+    fn test_post_quantum_validator_finalizes_and_is_slashed() {
+        use crate::utils::crypto::SchemeTag;
+        use crate::utils::mldsa::MlDsa65;
+        use slashing::{slash_double_sign, Evidence, SlashingConfig};
+
+        let (secret_key, public_key) = MlDsa65::generate_keypair();
+        let mut registry = StakeRegistry::default();
+        registry.bond(&VALIDATOR, 10_000).unwrap();
+        registry.register_key(&VALIDATOR, &encode_tagged(SchemeTag::MlDsa65, &public_key)).unwrap();
+        let snapshot = epochs(&registry).snapshot().clone();
+
+        let finalization = anvil_block_with::<MlDsa65>(&snapshot, &secret_key, 5, b"block").unwrap();
+        assert_eq!(verify_finalization(&snapshot, &finalization), Ok(VALIDATOR));
+
+        // Finalizing a second block at the same height is slashable.
+        let conflicting = anvil_block_with::<MlDsa65>(&snapshot, &secret_key, 5, b"other block").unwrap();
+        let evidence = Evidence { first: finalization, second: conflicting };
+        let outcome = slash_double_sign(&mut registry, &evidence, &BOB, &SlashingConfig::default()).unwrap();
+        assert_eq!(outcome.offender, VALIDATOR);
+        assert_eq!(registry.bonded(&VALIDATOR), 10_000 - outcome.slashed);
+        assert!(registry.is_jailed(&VALIDATOR));
+    }
+}
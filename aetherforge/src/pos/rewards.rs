@@ -0,0 +1,391 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+use super::epoch::ValidatorSnapshot;
+use super::staking::{StakeRegistry, StakingError, ValidatorId};
+
+/// Fixed-point scale used for the decaying emission factor.
+const FACTOR_SCALE: u128 = 1_000_000_000_000;
+
+/// Participation value for a validator that performed all of its duties.
+pub const FULL_PARTICIPATION: u64 = 10_000;
+
+/// Custom error type for reward issuance
+#[derive(Error, Debug)]
+pub enum RewardError {
+    #[error("Epoch {0} was already processed")]
+    EpochAlreadyProcessed(u64),
+    #[error("Staking error: {0}")]
+    StakingError(#[from] StakingError),
+}
+
+/// Emission curve for per-epoch staking rewards.
+///
+/// Target issuance for an epoch is `factor * sqrt(total_stake)`, so the yield
+/// per staked token falls as more stake joins. The factor decays by
+/// `decay_bps` every `decay_period` epochs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IssuanceSchedule {
+    pub reward_factor: u64,
+    pub decay_bps: u64,
+    pub decay_period: u64,
+}
+
+impl IssuanceSchedule {
+    /// Emission factor at `epoch`, scaled by `FACTOR_SCALE`.
+    pub fn emission_factor(&self, epoch: u64) -> u128 {
+        let mut factor = self.reward_factor as u128 * FACTOR_SCALE;
+        let decay_bps = self.decay_bps.min(10_000) as u128;
+        if decay_bps == 0 || self.decay_period == 0 {
+            return factor;
+        }
+        for _ in 0..epoch / self.decay_period {
+            factor = factor * (10_000 - decay_bps) / 10_000;
+            if factor == 0 {
+                break;
+            }
+        }
+        factor
+    }
+
+    /// Tokens to issue in `epoch` given the total bonded stake.
+    pub fn target_issuance(&self, epoch: u64, total_stake: u64) -> u64 {
+        let issuance = mul_div(self.emission_factor(epoch), total_stake.isqrt() as u128, FACTOR_SCALE);
+        issuance.unwrap_or(u128::MAX).min(u64::MAX as u128) as u64
+    }
+}
+
+impl Default for IssuanceSchedule {
+    fn default() -> Self {
+        Self {
+            reward_factor: 64,
+            decay_bps: 1_000, // 10% less every period
+            decay_period: 8_192,
+        }
+    }
+}
+
+/// Auditable record of one epoch's issuance.
+///
+/// Every token of `carried_in + target_issuance` ends up in exactly one of
+/// `issued`, `unissued` (withheld for missed participation) or `carried_out`
+/// (rounding remainder paid in a later epoch).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochLedger {
+    pub epoch: u64,
+    pub total_stake: u64,
+    pub target_issuance: u64,
+    pub carried_in: u64,
+    pub issued: u64,
+    pub unissued: u64,
+    pub carried_out: u64,
+    pub payouts: Vec<(ValidatorId, u64)>,
+}
+
+impl EpochLedger {
+    /// Checks that the ledger accounts for every token exactly once.
+    pub fn is_balanced(&self) -> bool {
+        let paid: u128 = self.payouts.iter().map(|(_, amount)| *amount as u128).sum();
+        paid == self.issued as u128
+            && self.carried_in as u128 + self.target_issuance as u128
+                == self.issued as u128 + self.unissued as u128 + self.carried_out as u128
+    }
+}
+
+/// Computes per-epoch rewards and keeps the ledger of past epochs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardEngine {
+    schedule: IssuanceSchedule,
+    carry: u64,
+    ledger: Vec<EpochLedger>,
+}
+
+impl RewardEngine {
+    pub fn new(schedule: IssuanceSchedule) -> Self {
+        Self { schedule, carry: 0, ledger: Vec::new() }
+    }
+
+    pub fn schedule(&self) -> &IssuanceSchedule {
+        &self.schedule
+    }
+
+    /// Rounding remainder waiting to be paid in the next epoch.
+    pub fn carry(&self) -> u64 {
+        self.carry
+    }
+
+    pub fn ledger(&self) -> &[EpochLedger] {
+        &self.ledger
+    }
+
+    /// Computes the payouts for `epoch` and appends them to the ledger.
+    ///
+    /// `participation` gives each validator's performed duties in basis points
+    /// of `FULL_PARTICIPATION`; validators missing from it earn nothing. A
+    /// validator's payout is its stake share of the pool, weighted by its
+    /// participation.
+    pub fn compute_epoch(
+        &mut self,
+        epoch: u64,
+        validators: &[(ValidatorId, u64)],
+        participation: &BTreeMap<ValidatorId, u64>,
+    ) -> Result<&EpochLedger, RewardError> {
+        if self.ledger.last().is_some_and(|last| last.epoch >= epoch) {
+            return Err(RewardError::EpochAlreadyProcessed(epoch));
+        }
+
+        let total_stake: u128 = validators.iter().map(|(_, stake)| *stake as u128).sum();
+        let target_issuance = self.schedule.target_issuance(epoch, total_stake.min(u64::MAX as u128) as u64);
+        let carried_in = self.carry;
+        let pool = carried_in as u128 + target_issuance as u128;
+
+        let denominator = total_stake * FULL_PARTICIPATION as u128;
+        let mut weighted_stake = 0u128;
+        let mut payouts = Vec::new();
+        for (validator, stake) in validators {
+            let weight = participation.get(validator).copied().unwrap_or(0).min(FULL_PARTICIPATION);
+            let weighted = *stake as u128 * weight as u128;
+            weighted_stake += weighted;
+            let payout = mul_div(pool, weighted, denominator).unwrap_or(0) as u64;
+            if payout > 0 {
+                payouts.push((*validator, payout));
+            }
+        }
+
+        let earned = mul_div(pool, weighted_stake, denominator).unwrap_or(0);
+        let issued: u128 = payouts.iter().map(|(_, amount)| *amount as u128).sum();
+        let carried_out = (earned - issued) as u64;
+        self.carry = carried_out;
+
+        self.ledger.push(EpochLedger {
+            epoch,
+            total_stake: total_stake.min(u64::MAX as u128) as u64,
+            target_issuance,
+            carried_in,
+            issued: issued as u64,
+            unissued: (pool - earned) as u64,
+            carried_out,
+            payouts,
+        });
+        Ok(self.ledger.last().unwrap())
+    }
+
+    /// Computes the rewards of the snapshot's validators for its epoch, weighted
+    /// by their snapshot stake, and pays them out into the registry, splitting
+    /// each payout with delegators.
+    pub fn process_epoch(
+        &mut self,
+        registry: &mut StakeRegistry,
+        snapshot: &ValidatorSnapshot,
+        participation: &BTreeMap<ValidatorId, u64>,
+    ) -> Result<&EpochLedger, RewardError> {
+        let validators: Vec<(ValidatorId, u64)> = snapshot.validators().iter().map(|(v, s)| (*v, *s)).collect();
+        let ledger = self.compute_epoch(snapshot.epoch(), &validators, participation)?;
+        for (validator, amount) in &ledger.payouts {
+            registry.distribute_reward(validator, *amount)?;
+        }
+        Ok(ledger)
+    }
+}
+
+impl Default for RewardEngine {
+    fn default() -> Self {
+        Self::new(IssuanceSchedule::default())
+    }
+}
+
+/// `a * b / divisor` rounded down, through a 256-bit intermediate product so
+/// that 18-decimal stakes can't overflow it. `None` if the divisor is zero
+/// or the quotient doesn't fit in 128 bits.
+fn mul_div(a: u128, b: u128, divisor: u128) -> Option<u128> {
+    const LOW: u128 = u64::MAX as u128;
+    if divisor == 0 {
+        return None;
+    }
+
+    // Schoolbook product of the 64-bit halves into (high, low).
+    let (a_high, a_low, b_high, b_low) = (a >> 64, a & LOW, b >> 64, b & LOW);
+    let low_low = a_low * b_low;
+    let high_low = a_high * b_low;
+    let low_high = a_low * b_high;
+    let middle = (low_low >> 64) + (high_low & LOW) + (low_high & LOW);
+    let low = (middle << 64) | (low_low & LOW);
+    let high = a_high * b_high + (high_low >> 64) + (low_high >> 64) + (middle >> 64);
+    if high >= divisor {
+        return None;
+    }
+
+    // Shift-subtract long division; the remainder stays below the divisor,
+    // so a bit carried out of it means the divisor fits.
+    let (mut remainder, mut quotient) = (high, 0u128);
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::address::testing::{ALICE, BOB, CHARLIE, DAVE};
+
+    fn full(validators: &[ValidatorId]) -> BTreeMap<ValidatorId, u64> {
+        validators.iter().map(|v| (*v, FULL_PARTICIPATION)).collect()
+    }
+
+    #[test]
+    fn test_target_issuance_grows_sublinearly() {
+        let schedule = IssuanceSchedule { reward_factor: 10, decay_bps: 0, decay_period: 1 };
+        assert_eq!(schedule.target_issuance(0, 0), 0);
+        assert_eq!(schedule.target_issuance(0, 10_000), 1_000);
+        assert_eq!(schedule.target_issuance(0, 40_000), 2_000);
+        assert_eq!(schedule.target_issuance(500, 40_000), 2_000);
+    }
+
+    #[test]
+    fn test_emission_decays() {
+        let schedule = IssuanceSchedule { reward_factor: 100, decay_bps: 5_000, decay_period: 10 };
+        assert_eq!(schedule.target_issuance(0, 100), 1_000);
+        assert_eq!(schedule.target_issuance(9, 100), 1_000);
+        assert_eq!(schedule.target_issuance(10, 100), 500);
+        assert_eq!(schedule.target_issuance(25, 100), 250);
+
+        // Long after, emission runs dry without looping forever.
+        assert_eq!(schedule.target_issuance(u64::MAX, 100), 0);
+    }
+
+    #[test]
+    fn test_payouts_follow_stake_and_participation() {
+        let schedule = IssuanceSchedule { reward_factor: 10, decay_bps: 0, decay_period: 1 };
+        let mut engine = RewardEngine::new(schedule);
+        let validators = [(ALICE, 2_500), (BOB, 2_500), (CHARLIE, 5_000)];
+
+        // Issuance is 1000; charlie only did half of the assigned duties.
+        let mut participation = full(&[ALICE, BOB]);
+        participation.insert(CHARLIE, 5_000);
+        let ledger = engine.compute_epoch(0, &validators, &participation).unwrap();
+
+        assert_eq!(ledger.target_issuance, 1_000);
+        assert_eq!(
+            ledger.payouts,
+            vec![(ALICE, 250), (BOB, 250), (CHARLIE, 250)]
+        );
+        assert_eq!(ledger.issued, 750);
+        assert_eq!(ledger.unissued, 250);
+        assert_eq!(ledger.carried_out, 0);
+        assert!(ledger.is_balanced());
+    }
+
+    #[test]
+    fn test_realistic_stakes_do_not_overflow() {
+        // 18-decimal stakes and a large reward factor push `pool * stake` past 128 bits.
+        let schedule = IssuanceSchedule { reward_factor: 10_000_000, decay_bps: 0, decay_period: 1 };
+        let mut engine = RewardEngine::new(schedule);
+        let token = 10u64.pow(18);
+        let validators = [(ALICE, 4 * token), (BOB, 3 * token), (CHARLIE, 2 * token), (DAVE, token)];
+        let ledger = engine.compute_epoch(0, &validators, &full(&[ALICE, BOB, CHARLIE, DAVE])).unwrap();
+
+        // sqrt(10^19) = 3162277660, times the factor.
+        assert_eq!(ledger.target_issuance, 31_622_776_600_000_000);
+        assert_eq!(
+            ledger.payouts,
+            vec![
+                (ALICE, 12_649_110_640_000_000),
+                (BOB, 9_486_832_980_000_000),
+                (CHARLIE, 6_324_555_320_000_000),
+                (DAVE, 3_162_277_660_000_000),
+            ]
+        );
+        assert!(ledger.is_balanced());
+
+        // Issuance saturates instead of overflowing at the extremes.
+        let extreme = IssuanceSchedule { reward_factor: u64::MAX, decay_bps: 0, decay_period: 1 };
+        assert_eq!(extreme.target_issuance(0, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_mul_div() {
+        assert_eq!(mul_div(6, 7, 3), Some(14));
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div(u128::MAX, 3, 4), Some(u128::MAX / 4 * 3 + 2));
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 80), Some(1 << 120));
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div(1, 1, 0), None);
+    }
+
+    #[test]
+    fn test_remainders_carry_forward() {
+        let schedule = IssuanceSchedule { reward_factor: 1, decay_bps: 0, decay_period: 1 };
+        let mut engine = RewardEngine::new(schedule);
+        let validators = [(ALICE, 3), (BOB, 3), (CHARLIE, 3)];
+        let participation = full(&[ALICE, BOB, CHARLIE]);
+
+        // sqrt(9) * 1 = 3 tokens split three ways leaves nothing over.
+        let ledger = engine.compute_epoch(0, &validators, &participation).unwrap().clone();
+        assert_eq!((ledger.issued, ledger.carried_out), (3, 0));
+
+        // With 4 validators at 3 each, 3 tokens can't be split: all carry forward.
+        let validators = [(ALICE, 3), (BOB, 3), (CHARLIE, 3), (DAVE, 3)];
+        let participation = full(&[ALICE, BOB, CHARLIE, DAVE]);
+        let ledger = engine.compute_epoch(1, &validators, &participation).unwrap().clone();
+        assert_eq!((ledger.issued, ledger.carried_out), (0, 3));
+        assert!(ledger.is_balanced());
+
+        // Next epoch the pool is 3 + 3 = 6, paying 1 each and carrying 2.
+        let ledger = engine.compute_epoch(2, &validators, &participation).unwrap().clone();
+        assert_eq!(ledger.carried_in, 3);
+        assert_eq!((ledger.issued, ledger.carried_out), (4, 2));
+        assert!(ledger.is_balanced());
+        assert_eq!(engine.carry(), 2);
+
+        // Over the whole ledger nothing was created or lost.
+        let minted: u64 = engine.ledger().iter().map(|l| l.target_issuance).sum();
+        let issued: u64 = engine.ledger().iter().map(|l| l.issued).sum();
+        assert_eq!(minted, issued + engine.carry());
+    }
+
+    #[test]
+    fn test_epochs_processed_once() {
+        let mut engine = RewardEngine::default();
+        let validators = [(ALICE, 1_000)];
+        engine.compute_epoch(5, &validators, &full(&[ALICE])).unwrap();
+        assert!(matches!(
+            engine.compute_epoch(5, &validators, &full(&[ALICE])),
+            Err(RewardError::EpochAlreadyProcessed(5))
+        ));
+        assert!(engine.compute_epoch(4, &validators, &full(&[ALICE])).is_err());
+        assert!(engine.compute_epoch(6, &validators, &full(&[ALICE])).is_ok());
+    }
+
+    #[test]
+    fn test_process_epoch_pays_registry() {
+        let schedule = IssuanceSchedule { reward_factor: 100, decay_bps: 0, decay_period: 1 };
+        let mut engine = RewardEngine::new(schedule);
+        let mut registry = StakeRegistry::default();
+        registry.bond(&ALICE, 1_000).unwrap();
+        registry.delegate(&DAVE, &ALICE, 3_000).unwrap();
+        registry.set_commission(&ALICE, 1_000).unwrap();
+        registry.bond(&BOB, 4_000).unwrap();
+        let snapshot = ValidatorSnapshot::new(0, registry.active_validators().into_iter().collect(), Vec::new());
+
+        // Stake bonded mid-epoch neither earns nor dilutes this epoch's rewards.
+        registry.bond(&CHARLIE, 8_000).unwrap();
+
+        // sqrt(8000) = 89, so 8900 tokens split evenly between alice's and bob's pools.
+        let ledger = engine.process_epoch(&mut registry, &snapshot, &full(&[ALICE, BOB, CHARLIE])).unwrap();
+        assert_eq!(ledger.issued, 8_900);
+        assert_eq!(registry.withdrawable(&ALICE), 445);
+        assert_eq!(registry.bonded(&ALICE), 4_000 + 4_005);
+        assert_eq!(registry.delegation(&DAVE, &ALICE), 3_000 + 3_003);
+        assert_eq!(registry.bonded(&BOB), 4_000 + 4_450);
+        assert_eq!(registry.bonded(&CHARLIE), 8_000);
+    }
+}
@@ -0,0 +1,450 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::bft::{verify_signed_vote, SignedVote};
+use super::consensus::{verify_finalization, Finalization};
+use super::ffg::{verify_checkpoint_vote, CheckpointVote};
+use super::staking::{InfractionKind, StakeRegistry, StakingError, ValidatorId};
+use crate::utils::address::Address;
+
+/// Custom error type for slashing
+#[derive(Error, Debug)]
+pub enum SlashingError {
+    #[error("Finalizations are signed by different validators")]
+    DifferentSigners,
+    #[error("Finalizations are for different heights")]
+    DifferentHeights,
+    #[error("Votes are for different heights, rounds or steps")]
+    DifferentRounds,
+    #[error("Finalizations are for the same block")]
+    NotConflicting,
+    #[error("Checkpoint votes are neither a double vote nor a surround vote")]
+    NotSlashable,
+    #[error("Invalid signature in evidence")]
+    InvalidSignature,
+    #[error("Signer is not a registered validator")]
+    UnknownSigner,
+    #[error("Validator was already slashed for {0:?} at height {1}")]
+    AlreadySlashed(InfractionKind, u64),
+    #[error("Staking error: {0}")]
+    StakingError(#[from] StakingError),
+}
+
+/// Proof that a validator signed two different blocks at the same height.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Evidence {
+    pub first: Finalization,
+    pub second: Finalization,
+}
+
+impl Evidence {
+    /// Checks that both finalizations are validly signed by the same key
+    /// and finalize different blocks at the same height.
+    pub fn verify(&self) -> Result<(), SlashingError> {
+        if self.first.validator_pubkey != self.second.validator_pubkey {
+            return Err(SlashingError::DifferentSigners);
+        }
+        if self.first.height != self.second.height {
+            return Err(SlashingError::DifferentHeights);
+        }
+        if self.first.block_hash == self.second.block_hash {
+            return Err(SlashingError::NotConflicting);
+        }
+        if !verify_finalization(&self.first) || !verify_finalization(&self.second) {
+            return Err(SlashingError::InvalidSignature);
+        }
+        Ok(())
+    }
+
+    pub fn height(&self) -> u64 {
+        self.first.height
+    }
+}
+
+/// Proof that a validator cast two conflicting checkpoint votes: a double
+/// vote or a surround vote.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FfgEvidence {
+    pub first: CheckpointVote,
+    pub second: CheckpointVote,
+}
+
+impl FfgEvidence {
+    /// Checks that both votes are validly signed by the same key and conflict.
+    pub fn verify(&self) -> Result<(), SlashingError> {
+        if self.first.validator_pubkey != self.second.validator_pubkey {
+            return Err(SlashingError::DifferentSigners);
+        }
+        if !self.first.conflicts_with(&self.second) {
+            return Err(SlashingError::NotSlashable);
+        }
+        if !verify_checkpoint_vote(&self.first) || !verify_checkpoint_vote(&self.second) {
+            return Err(SlashingError::InvalidSignature);
+        }
+        Ok(())
+    }
+
+    /// Height the infraction is recorded at: the higher of the two targets.
+    pub fn height(&self) -> u64 {
+        self.first.target.height.max(self.second.target.height)
+    }
+}
+
+/// Proof that a validator cast two different BFT votes of the same type in
+/// the same height and round.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundEvidence {
+    pub first: SignedVote,
+    pub second: SignedVote,
+}
+
+impl RoundEvidence {
+    /// Checks that both votes are validly signed by the same key and vote
+    /// differently in the same step of the same round.
+    pub fn verify(&self) -> Result<(), SlashingError> {
+        let (first, second) = (&self.first.vote, &self.second.vote);
+        if self.first.validator_pubkey != self.second.validator_pubkey || first.validator != second.validator {
+            return Err(SlashingError::DifferentSigners);
+        }
+        if (first.height, first.round, first.vote_type) != (second.height, second.round, second.vote_type) {
+            return Err(SlashingError::DifferentRounds);
+        }
+        if first.block_hash == second.block_hash {
+            return Err(SlashingError::NotConflicting);
+        }
+        if !verify_signed_vote(&self.first) || !verify_signed_vote(&self.second) {
+            return Err(SlashingError::InvalidSignature);
+        }
+        Ok(())
+    }
+
+    pub fn height(&self) -> u64 {
+        self.first.vote.height
+    }
+}
+
+/// Penalty parameters for double signing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlashingConfig {
+    /// Fraction of the offender's stake removed, in basis points.
+    pub slash_fraction_bps: u64,
+    /// Fraction of the slashed amount paid to the reporter, in basis points.
+    pub whistleblower_reward_bps: u64,
+    /// Number of epochs the offender is jailed for.
+    pub jail_epochs: u64,
+}
+
+impl Default for SlashingConfig {
+    fn default() -> Self {
+        Self {
+            slash_fraction_bps: 500,        // 5%
+            whistleblower_reward_bps: 1_000, // 10% of the slashed amount
+            jail_epochs: 36,
+        }
+    }
+}
+
+/// Result of applying double-sign evidence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlashOutcome {
+    pub offender: ValidatorId,
+    pub slashed: u64,
+    pub burned: u64,
+    pub whistleblower_reward: u64,
+}
+
+/// Verifies `evidence`, slashes and jails the offender, and rewards `reporter`.
+pub fn slash_double_sign(
+    registry: &mut StakeRegistry,
+    evidence: &Evidence,
+    reporter: &Address,
+    config: &SlashingConfig,
+) -> Result<SlashOutcome, SlashingError> {
+    evidence.verify()?;
+    punish(
+        registry,
+        &evidence.first.validator_pubkey,
+        InfractionKind::DoubleSign,
+        evidence.height(),
+        reporter,
+        config,
+    )
+}
+
+/// Verifies FFG `evidence`, slashes and jails the offender, and rewards `reporter`.
+pub fn slash_ffg_violation(
+    registry: &mut StakeRegistry,
+    evidence: &FfgEvidence,
+    reporter: &Address,
+    config: &SlashingConfig,
+) -> Result<SlashOutcome, SlashingError> {
+    evidence.verify()?;
+    punish(
+        registry,
+        &evidence.first.validator_pubkey,
+        InfractionKind::FfgViolation,
+        evidence.height(),
+        reporter,
+        config,
+    )
+}
+
+/// Verifies BFT round `evidence`, slashes and jails the offender, and rewards `reporter`.
+pub fn slash_round_equivocation(
+    registry: &mut StakeRegistry,
+    evidence: &RoundEvidence,
+    reporter: &Address,
+    config: &SlashingConfig,
+) -> Result<SlashOutcome, SlashingError> {
+    evidence.verify()?;
+    punish(
+        registry,
+        &evidence.first.validator_pubkey,
+        InfractionKind::RoundEquivocation,
+        evidence.height(),
+        reporter,
+        config,
+    )
+}
+
+fn punish(
+    registry: &mut StakeRegistry,
+    validator_pubkey: &[u8],
+    kind: InfractionKind,
+    height: u64,
+    reporter: &Address,
+    config: &SlashingConfig,
+) -> Result<SlashOutcome, SlashingError> {
+    let offender = registry
+        .validator_by_key(validator_pubkey)
+        .copied()
+        .ok_or(SlashingError::UnknownSigner)?;
+    if !registry.record_infraction(&offender, kind, height)? {
+        return Err(SlashingError::AlreadySlashed(kind, height));
+    }
+
+    let slashed = registry.slash(&offender, config.slash_fraction_bps)?;
+    let whistleblower_reward =
+        (slashed as u128 * config.whistleblower_reward_bps.min(10_000) as u128 / 10_000) as u64;
+    registry.credit_withdrawable(reporter, whistleblower_reward)?;
+    registry.jail(&offender, registry.current_epoch() + config.jail_epochs)?;
+
+    Ok(SlashOutcome {
+        offender,
+        slashed,
+        burned: slashed - whistleblower_reward,
+        whistleblower_reward,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::address::testing::ALICE;
+
+    const REPORTER: Address = Address::repeat_byte(0x02);
+    use crate::pos::consensus::sign_finalization;
+    use crate::pos::ffg::{sign_checkpoint_vote, Checkpoint};
+    use crate::utils::crypto::generate_keypair;
+
+    fn double_sign(secret_key: &k256::SecretKey, height: u64) -> Evidence {
+        Evidence {
+            first: sign_finalization(secret_key, height, b"block a"),
+            second: sign_finalization(secret_key, height, b"block b"),
+        }
+    }
+
+    #[test]
+    fn test_verify_evidence() {
+        let (secret_key, _) = generate_keypair();
+        let (other_secret_key, _) = generate_keypair();
+        assert!(double_sign(&secret_key, 5).verify().is_ok());
+
+        // Same block twice is not an offence.
+        let same_block = Evidence {
+            first: sign_finalization(&secret_key, 5, b"block a"),
+            second: sign_finalization(&secret_key, 5, b"block a"),
+        };
+        assert!(matches!(same_block.verify(), Err(SlashingError::NotConflicting)));
+
+        // Different heights are not conflicting.
+        let different_heights = Evidence {
+            first: sign_finalization(&secret_key, 5, b"block a"),
+            second: sign_finalization(&secret_key, 6, b"block b"),
+        };
+        assert!(matches!(different_heights.verify(), Err(SlashingError::DifferentHeights)));
+
+        // Two different signers can't be combined into evidence.
+        let different_signers = Evidence {
+            first: sign_finalization(&secret_key, 5, b"block a"),
+            second: sign_finalization(&other_secret_key, 5, b"block b"),
+        };
+        assert!(matches!(different_signers.verify(), Err(SlashingError::DifferentSigners)));
+
+        // Forged signatures are rejected.
+        let mut forged = double_sign(&secret_key, 5);
+        forged.second.signature = forged.first.signature.clone();
+        assert!(matches!(forged.verify(), Err(SlashingError::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_slash_double_sign() {
+        let (secret_key, public_key) = generate_keypair();
+        let mut registry = StakeRegistry::default();
+        registry.bond(&ALICE, 10_000).unwrap();
+        registry.register_key(&ALICE, &public_key).unwrap();
+
+        let config = SlashingConfig::default();
+        let evidence = double_sign(&secret_key, 5);
+        let outcome = slash_double_sign(&mut registry, &evidence, &REPORTER, &config).unwrap();
+
+        assert_eq!(
+            outcome,
+            SlashOutcome { offender: ALICE, slashed: 500, burned: 450, whistleblower_reward: 50 }
+        );
+        assert_eq!(registry.bonded(&ALICE), 9_500);
+        assert!(registry.is_jailed(&ALICE));
+        assert_eq!(registry.stake(&ALICE).unwrap().jailed_until, Some(36));
+        assert_eq!(registry.withdraw(&REPORTER).unwrap(), 50);
+
+        // The same infraction can't be slashed twice.
+        let resubmitted = double_sign(&secret_key, 5);
+        assert!(matches!(
+            slash_double_sign(&mut registry, &resubmitted, &REPORTER, &config),
+            Err(SlashingError::AlreadySlashed(InfractionKind::DoubleSign, 5))
+        ));
+        assert_eq!(registry.bonded(&ALICE), 9_500);
+    }
+
+    #[test]
+    fn test_evidence_not_replayed_after_rebonding() {
+        let (secret_key, public_key) = generate_keypair();
+        let mut registry = StakeRegistry::default();
+        registry.bond(&ALICE, 10_000).unwrap();
+        registry.register_key(&ALICE, &public_key).unwrap();
+        let config = SlashingConfig::default();
+        slash_double_sign(&mut registry, &double_sign(&secret_key, 5), &REPORTER, &config).unwrap();
+
+        // Alice leaves entirely and serves out her jail sentence...
+        registry.unbond(&ALICE, 9_500).unwrap();
+        registry.advance_epoch(40);
+        assert_eq!(registry.withdraw(&ALICE).unwrap(), 9_500);
+
+        // ...but her infraction record survives, so bonding again doesn't reopen it.
+        registry.bond(&ALICE, 10_000).unwrap();
+        registry.register_key(&ALICE, &public_key).unwrap();
+        assert!(matches!(
+            slash_double_sign(&mut registry, &double_sign(&secret_key, 5), &REPORTER, &config),
+            Err(SlashingError::AlreadySlashed(InfractionKind::DoubleSign, 5))
+        ));
+        assert_eq!(registry.bonded(&ALICE), 10_000);
+    }
+
+    #[test]
+    fn test_slash_unknown_signer() {
+        let (secret_key, _) = generate_keypair();
+        let mut registry = StakeRegistry::default();
+        let evidence = double_sign(&secret_key, 1);
+        assert!(matches!(
+            slash_double_sign(&mut registry, &evidence, &REPORTER, &SlashingConfig::default()),
+            Err(SlashingError::UnknownSigner)
+        ));
+    }
+
+    #[test]
+    fn test_slash_ffg_violation() {
+        let (secret_key, public_key) = generate_keypair();
+        let mut registry = StakeRegistry::default();
+        registry.bond(&ALICE, 10_000).unwrap();
+        registry.register_key(&ALICE, &public_key).unwrap();
+        let checkpoint = |height: u64| Checkpoint { height, block_hash: format!("block {height}").into_bytes() };
+
+        // Honest consecutive votes are not slashable.
+        let honest = FfgEvidence {
+            first: sign_checkpoint_vote(&secret_key, checkpoint(0), checkpoint(10)),
+            second: sign_checkpoint_vote(&secret_key, checkpoint(10), checkpoint(20)),
+        };
+        assert!(matches!(honest.verify(), Err(SlashingError::NotSlashable)));
+
+        let surround = FfgEvidence {
+            first: sign_checkpoint_vote(&secret_key, checkpoint(10), checkpoint(20)),
+            second: sign_checkpoint_vote(&secret_key, checkpoint(0), checkpoint(30)),
+        };
+        let outcome = slash_ffg_violation(&mut registry, &surround, &REPORTER, &SlashingConfig::default()).unwrap();
+        assert_eq!(outcome.offender, ALICE);
+        assert_eq!(outcome.slashed, 500);
+        assert!(registry.is_jailed(&ALICE));
+        assert!(matches!(
+            slash_ffg_violation(&mut registry, &surround, &REPORTER, &SlashingConfig::default()),
+            Err(SlashingError::AlreadySlashed(InfractionKind::FfgViolation, 30))
+        ));
+    }
+
+    #[test]
+    fn test_offence_kinds_slashed_separately() {
+        let (secret_key, public_key) = generate_keypair();
+        let mut registry = StakeRegistry::default();
+        registry.bond(&ALICE, 10_000).unwrap();
+        registry.register_key(&ALICE, &public_key).unwrap();
+        let config = SlashingConfig::default();
+        let checkpoint = |height: u64| Checkpoint { height, block_hash: format!("block {height}").into_bytes() };
+
+        // A double sign and an FFG surround vote at height 30 are distinct offences.
+        slash_double_sign(&mut registry, &double_sign(&secret_key, 30), &REPORTER, &config).unwrap();
+        let surround = FfgEvidence {
+            first: sign_checkpoint_vote(&secret_key, checkpoint(10), checkpoint(20)),
+            second: sign_checkpoint_vote(&secret_key, checkpoint(0), checkpoint(30)),
+        };
+        slash_ffg_violation(&mut registry, &surround, &REPORTER, &config).unwrap();
+        assert_eq!(registry.bonded(&ALICE), 9_025);
+        assert!(matches!(
+            slash_double_sign(&mut registry, &double_sign(&secret_key, 30), &REPORTER, &config),
+            Err(SlashingError::AlreadySlashed(InfractionKind::DoubleSign, 30))
+        ));
+    }
+
+    #[test]
+    fn test_slash_round_equivocation() {
+        use crate::pos::bft::{sign_vote, Vote, VoteType};
+
+        let (secret_key, public_key) = generate_keypair();
+        let mut registry = StakeRegistry::default();
+        registry.bond(&ALICE, 10_000).unwrap();
+        registry.register_key(&ALICE, &public_key).unwrap();
+        let vote = |vote_type: VoteType, round: u32, block_hash: Option<&[u8]>| Vote {
+            vote_type,
+            height: 9,
+            round,
+            block_hash: block_hash.map(<[u8]>::to_vec),
+            validator: ALICE,
+        };
+        let evidence = |first: Vote, second: Vote| RoundEvidence {
+            first: sign_vote(&secret_key, first),
+            second: sign_vote(&secret_key, second),
+        };
+
+        // Votes in different rounds or steps, or for the same block, are not equivocations.
+        let next_round = evidence(vote(VoteType::Prevote, 0, Some(b"a")), vote(VoteType::Prevote, 1, Some(b"b")));
+        assert!(matches!(next_round.verify(), Err(SlashingError::DifferentRounds)));
+        let next_step = evidence(vote(VoteType::Prevote, 0, Some(b"a")), vote(VoteType::Precommit, 0, Some(b"b")));
+        assert!(matches!(next_step.verify(), Err(SlashingError::DifferentRounds)));
+        let repeated = evidence(vote(VoteType::Prevote, 0, Some(b"a")), vote(VoteType::Prevote, 0, Some(b"a")));
+        assert!(matches!(repeated.verify(), Err(SlashingError::NotConflicting)));
+        let mut forged = evidence(vote(VoteType::Prevote, 0, Some(b"a")), vote(VoteType::Prevote, 0, None));
+        forged.second.vote.block_hash = Some(b"b".to_vec());
+        assert!(matches!(forged.verify(), Err(SlashingError::InvalidSignature)));
+
+        // A block and nil in the same precommit step is slashable.
+        let equivocation = evidence(vote(VoteType::Precommit, 2, Some(b"a")), vote(VoteType::Precommit, 2, None));
+        let outcome =
+            slash_round_equivocation(&mut registry, &equivocation, &REPORTER, &SlashingConfig::default()).unwrap();
+        assert_eq!(outcome.offender, ALICE);
+        assert_eq!(outcome.slashed, 500);
+        assert!(registry.is_jailed(&ALICE));
+        assert!(matches!(
+            slash_round_equivocation(&mut registry, &equivocation, &REPORTER, &SlashingConfig::default()),
+            Err(SlashingError::AlreadySlashed(InfractionKind::RoundEquivocation, 9))
+        ));
+    }
+}
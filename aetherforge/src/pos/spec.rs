@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::bft::TimeoutConfig;
+use super::epoch::{EpochConfig, EpochError};
+use super::liveness::LivenessConfig;
+use super::rewards::IssuanceSchedule;
+use super::slashing::SlashingConfig;
+use super::staking::DEFAULT_UNBONDING_PERIOD;
+
+/// Basis points in a whole.
+const MAX_BPS: u64 = 10_000;
+
+/// Custom error type for chain spec validation
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SpecError {
+    #[error("Invalid epoch config: {0}")]
+    Epoch(#[from] EpochError),
+    #[error("{0} must be non-zero")]
+    Zero(&'static str),
+    #[error("{0} must be at most {MAX_BPS} basis points")]
+    OutOfRange(&'static str),
+}
+
+/// Consensus parameters of a chain, loadable from JSON.
+///
+/// Missing fields fall back to their defaults, at every level of nesting, so
+/// a spec only needs to list what it changes. Specs are validated when
+/// deserialized.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedChainSpec")]
+pub struct ChainSpec {
+    pub epoch: EpochConfig,
+    /// Blocks between Casper-FFG checkpoints.
+    pub checkpoint_interval: u64,
+    pub unbonding_period: u64,
+    pub bft_timeouts: TimeoutConfig,
+    pub issuance: IssuanceSchedule,
+    pub slashing: SlashingConfig,
+    pub liveness: LivenessConfig,
+}
+
+impl ChainSpec {
+    /// Checks that no parameter would stall the chain, divide by zero or
+    /// take more than a validator's whole stake.
+    pub fn validate(&self) -> Result<(), SpecError> {
+        self.epoch.validate()?;
+        let zero = [
+            ("epoch.min_churn", self.epoch.min_churn == 0),
+            ("epoch.churn_quotient", self.epoch.churn_quotient == 0),
+            ("epoch.committee_size", self.epoch.committee_size == 0),
+            ("checkpoint_interval", self.checkpoint_interval == 0),
+            ("unbonding_period", self.unbonding_period == 0),
+            ("bft_timeouts.propose", self.bft_timeouts.propose.is_zero()),
+            ("bft_timeouts.prevote", self.bft_timeouts.prevote.is_zero()),
+            ("bft_timeouts.precommit", self.bft_timeouts.precommit.is_zero()),
+            ("liveness.inactivity_score_bias", self.liveness.inactivity_score_bias == 0),
+            ("liveness.inactivity_penalty_quotient", self.liveness.inactivity_penalty_quotient == 0),
+        ];
+        if let Some((name, _)) = zero.iter().find(|(_, is_zero)| *is_zero) {
+            return Err(SpecError::Zero(name));
+        }
+
+        let bps = [
+            ("issuance.decay_bps", self.issuance.decay_bps),
+            ("slashing.slash_fraction_bps", self.slashing.slash_fraction_bps),
+            ("slashing.whistleblower_reward_bps", self.slashing.whistleblower_reward_bps),
+            ("liveness.missed_duty_penalty_bps", self.liveness.missed_duty_penalty_bps),
+            ("liveness.active_participation_bps", self.liveness.active_participation_bps),
+        ];
+        if let Some((name, _)) = bps.iter().find(|(_, value)| *value > MAX_BPS) {
+            return Err(SpecError::OutOfRange(name));
+        }
+        Ok(())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl Default for ChainSpec {
+    fn default() -> Self {
+        Self {
+            epoch: EpochConfig::default(),
+            checkpoint_interval: 64,
+            unbonding_period: DEFAULT_UNBONDING_PERIOD,
+            bft_timeouts: TimeoutConfig::default(),
+            issuance: IssuanceSchedule::default(),
+            slashing: SlashingConfig::default(),
+            liveness: LivenessConfig::default(),
+        }
+    }
+}
+
+/// Wire form of `ChainSpec`, validated on deserialization.
+#[derive(Deserialize)]
+#[serde(default)]
+struct UncheckedChainSpec {
+    epoch: EpochConfig,
+    checkpoint_interval: u64,
+    unbonding_period: u64,
+    bft_timeouts: TimeoutConfig,
+    issuance: IssuanceSchedule,
+    slashing: SlashingConfig,
+    liveness: LivenessConfig,
+}
+
+impl Default for UncheckedChainSpec {
+    fn default() -> Self {
+        let spec = ChainSpec::default();
+        Self {
+            epoch: spec.epoch,
+            checkpoint_interval: spec.checkpoint_interval,
+            unbonding_period: spec.unbonding_period,
+            bft_timeouts: spec.bft_timeouts,
+            issuance: spec.issuance,
+            slashing: spec.slashing,
+            liveness: spec.liveness,
+        }
+    }
+}
+
+impl TryFrom<UncheckedChainSpec> for ChainSpec {
+    type Error = SpecError;
+
+    fn try_from(unchecked: UncheckedChainSpec) -> Result<Self, Self::Error> {
+        let spec = Self {
+            epoch: unchecked.epoch,
+            checkpoint_interval: unchecked.checkpoint_interval,
+            unbonding_period: unchecked.unbonding_period,
+            bft_timeouts: unchecked.bft_timeouts,
+            issuance: unchecked.issuance,
+            slashing: unchecked.slashing,
+            liveness: unchecked.liveness,
+        };
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let spec = ChainSpec::default();
+        assert_eq!(ChainSpec::from_json(&spec.to_json().unwrap()).unwrap(), spec);
+    }
+
+    #[test]
+    fn test_partial_spec_uses_defaults() {
+        let spec = ChainSpec::from_json(
+            r#"{ "checkpoint_interval": 32, "liveness": { "inactivity_threshold_epochs": 8 },
+                "slashing": {}, "epoch": { "committee_size": 7 } }"#,
+        )
+        .unwrap();
+        assert_eq!(spec.checkpoint_interval, 32);
+        assert_eq!(spec.liveness, LivenessConfig { inactivity_threshold_epochs: 8, ..LivenessConfig::default() });
+        assert_eq!(spec.slashing, SlashingConfig::default());
+        assert_eq!(spec.epoch, EpochConfig { committee_size: 7, ..EpochConfig::default() });
+        assert_eq!(spec.issuance, IssuanceSchedule::default());
+        assert!(ChainSpec::from_json(r#"{ "checkpoint_interval": "soon" }"#).is_err());
+    }
+
+    #[test]
+    fn test_invalid_specs_rejected() {
+        assert_eq!(ChainSpec::default().validate(), Ok(()));
+
+        let zero_epoch = ChainSpec {
+            epoch: EpochConfig { epoch_length: 0, ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(zero_epoch.validate(), Err(SpecError::Epoch(EpochError::ZeroEpochLength)));
+        let no_unbonding = ChainSpec { unbonding_period: 0, ..Default::default() };
+        assert_eq!(no_unbonding.validate(), Err(SpecError::Zero("unbonding_period")));
+        let no_churn = ChainSpec {
+            epoch: EpochConfig { churn_quotient: 0, ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(no_churn.validate(), Err(SpecError::Zero("epoch.churn_quotient")));
+        let over_slash = ChainSpec {
+            slashing: SlashingConfig { slash_fraction_bps: 10_001, ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(over_slash.validate(), Err(SpecError::OutOfRange("slashing.slash_fraction_bps")));
+
+        for json in [
+            r#"{ "epoch": { "epoch_length": 0 } }"#,
+            r#"{ "unbonding_period": 0 }"#,
+            r#"{ "checkpoint_interval": 0 }"#,
+            r#"{ "epoch": { "min_churn": 0 } }"#,
+            r#"{ "liveness": { "active_participation_bps": 20000 } }"#,
+            r#"{ "issuance": { "decay_bps": 10001 } }"#,
+        ] {
+            assert!(ChainSpec::from_json(json).is_err(), "accepted {json}");
+        }
+    }
+}
@@ -1,31 +1,859 @@
-/// Validates a validator's stake.
-pub fn validate_stake(validator: &str, stake: u64) -> bool {
-    stake >= 1000 // Minimum stake requirement
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::utils::address::Address;
+use crate::utils::crypto::{compress_public_key, decode_tagged, encode_tagged, SchemeTag};
+use crate::utils::mldsa::ML_DSA_65_PUBLIC_KEY_LEN;
+
+/// Address a validator is registered under.
+pub type ValidatorId = Address;
+
+/// Minimum bonded stake for a validator to be eligible.
+pub const MIN_STAKE: u64 = 1000;
+
+/// Default number of epochs unbonded stake stays locked.
+pub const DEFAULT_UNBONDING_PERIOD: u64 = 21;
+
+/// Kind of slashable offence, so that different offences at the same height
+/// are punished separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum InfractionKind {
+    /// Two finalizations for different blocks at the same height.
+    DoubleSign,
+    /// A Casper FFG double or surround vote.
+    FfgViolation,
+    /// Two different BFT votes in the same step of the same round.
+    RoundEquivocation,
+}
+
+/// Custom error type for staking operations
+#[derive(Error, Debug)]
+pub enum StakingError {
+    #[error("Amount must be greater than zero")]
+    ZeroAmount,
+    #[error("Unknown validator: {0}")]
+    UnknownValidator(ValidatorId),
+    #[error("Insufficient bonded stake: have {bonded}, requested {requested}")]
+    InsufficientBond { bonded: u64, requested: u64 },
+    #[error("Nothing to withdraw")]
+    NothingToWithdraw,
+    #[error("Invalid consensus key")]
+    InvalidKey,
+    #[error("Consensus key already registered to {0}")]
+    KeyInUse(ValidatorId),
+    #[error("Validator is jailed until epoch {0}")]
+    Jailed(u64),
+    #[error("Commission must be at most 10000 basis points, got {0}")]
+    InvalidCommission(u64),
+    #[error("Cannot redelegate to the same validator")]
+    SameValidator,
+    #[error("Stake overflow")]
+    Overflow,
+    #[error("Failed to access registry file")]
+    IoError(#[from] std::io::Error),
+    #[error("Failed to (de)serialize registry")]
+    SerdeError(#[from] serde_json::Error),
+}
+
+/// Stake that has been unbonded by `delegator` and is released at `release_epoch`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnbondingEntry {
+    pub delegator: Address,
+    pub amount: u64,
+    pub release_epoch: u64,
+}
+
+/// Stake `delegator` moved from a validator to `destination` at `creation_epoch`.
+///
+/// The entry stays with the source validator, so the moved stake can still
+/// be slashed for the source's faults until the unbonding period has passed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedelegationEntry {
+    pub delegator: Address,
+    pub destination: ValidatorId,
+    pub amount: u64,
+    pub creation_epoch: u64,
 }
 
-/// Calculates the validator's reward based on their stake.
-pub fn calculate_reward(stake: u64) -> u64 {
-    stake / 100 // 1% reward for simplicity
+/// Per-validator stake accounting.
+///
+/// Bonded stake is a pool owned by delegators through shares, with the
+/// validator's self-bond being a delegation from itself. Rewards and slashes
+/// change the pool's tokens, so they split pro rata without touching each
+/// delegation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorStake {
+    /// Total tokens bonded to the validator, self-bond included.
+    pub bonded: u64,
+    pub total_shares: u128,
+    pub delegations: BTreeMap<Address, u128>,
+    /// Share of rewards kept by the validator, in basis points.
+    pub commission_bps: u64,
+    pub unbonding: Vec<UnbondingEntry>,
+    /// Stake redelegated away from this validator that is still slashable here.
+    #[serde(default)]
+    pub redelegations: Vec<RedelegationEntry>,
+    /// Key the validator signs consensus messages with, tagged with its
+    /// signature scheme. Secp256k1 keys are stored SEC1 compressed.
+    #[serde(default)]
+    pub consensus_key: Option<Vec<u8>>,
+    /// Epoch until which the validator is excluded from the active set.
+    #[serde(default)]
+    pub jailed_until: Option<u64>,
+    /// Infractions the validator has already been slashed for, by kind and height.
+    #[serde(default)]
+    pub slashed_infractions: BTreeSet<(InfractionKind, u64)>,
+}
+
+impl ValidatorStake {
+    /// Token value of `shares` in this pool.
+    pub fn tokens_for_shares(&self, shares: u128) -> u64 {
+        if self.total_shares == 0 {
+            return 0;
+        }
+        (shares * self.bonded as u128 / self.total_shares) as u64
+    }
+
+    /// Tokens `delegator` currently has bonded to this validator.
+    pub fn delegation(&self, delegator: &Address) -> u64 {
+        self.delegations.get(delegator).map_or(0, |shares| self.tokens_for_shares(*shares))
+    }
+}
+
+/// Split of a reward between the validator's commission and its delegators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewardSplit {
+    pub commission: u64,
+    pub delegators: u64,
+}
+
+/// Tracks bonded, unbonding and withdrawable stake for every validator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakeRegistry {
+    validators: BTreeMap<ValidatorId, ValidatorStake>,
+    /// Withdrawable balance per account.
+    balances: BTreeMap<Address, u64>,
+    unbonding_period: u64,
+    current_epoch: u64,
+}
+
+/// The registry doubles as the validator set.
+pub type ValidatorSet = StakeRegistry;
+
+impl StakeRegistry {
+    pub fn new(unbonding_period: u64) -> Self {
+        Self {
+            validators: BTreeMap::new(),
+            balances: BTreeMap::new(),
+            unbonding_period,
+            current_epoch: 0,
+        }
+    }
+
+    /// Loads a registry previously written with `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, StakingError> {
+        let data = fs::read(path)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Persists the registry as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), StakingError> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn current_epoch(&self) -> u64 {
+        self.current_epoch
+    }
+
+    pub fn unbonding_period(&self) -> u64 {
+        self.unbonding_period
+    }
+
+    /// Bonded stake of a validator, zero if unknown.
+    pub fn bonded(&self, validator: &ValidatorId) -> u64 {
+        self.validators.get(validator).map_or(0, |v| v.bonded)
+    }
+
+    /// Tokens `delegator` has bonded to `validator`.
+    pub fn delegation(&self, delegator: &Address, validator: &ValidatorId) -> u64 {
+        self.validators.get(validator).map_or(0, |v| v.delegation(delegator))
+    }
+
+    /// Balance `account` can withdraw.
+    pub fn withdrawable(&self, account: &Address) -> u64 {
+        self.balances.get(account).copied().unwrap_or(0)
+    }
+
+    /// Full stake record of a validator.
+    pub fn stake(&self, validator: &ValidatorId) -> Option<&ValidatorStake> {
+        self.validators.get(validator)
+    }
+
+    /// Whether the validator is jailed at the current epoch.
+    pub fn is_jailed(&self, validator: &ValidatorId) -> bool {
+        self.validators
+            .get(validator)
+            .and_then(|v| v.jailed_until)
+            .is_some_and(|until| until > self.current_epoch)
+    }
+
+    /// Validator that registered `consensus_key`, tagged or as a bare SEC1 key.
+    pub fn validator_by_key(&self, consensus_key: &[u8]) -> Option<&ValidatorId> {
+        let key = normalize_key(consensus_key).ok()?;
+        self.validators
+            .iter()
+            .find(|(_, v)| v.consensus_key.as_deref() == Some(key.as_slice()))
+            .map(|(id, _)| id)
+    }
+
+    /// Sum of all bonded stake.
+    pub fn total_bonded(&self) -> u64 {
+        self.validators.values().map(|v| v.bonded).sum()
+    }
+
+    /// Validators meeting the minimum stake, with their bonded amounts.
+    pub fn active_validators(&self) -> Vec<(ValidatorId, u64)> {
+        self.validators
+            .iter()
+            .filter(|(_, v)| v.bonded >= MIN_STAKE)
+            .filter(|(_, v)| v.jailed_until.is_none_or(|until| until <= self.current_epoch))
+            .map(|(id, v)| (*id, v.bonded))
+            .collect()
+    }
+
+    /// Adds `amount` to the validator's self-bond.
+    pub fn bond(&mut self, validator: &ValidatorId, amount: u64) -> Result<u64, StakingError> {
+        self.delegate(validator, validator, amount)
+    }
+
+    /// Bonds `amount` from `delegator` to `validator` and returns the validator's total bond.
+    pub fn delegate(&mut self, delegator: &Address, validator: &ValidatorId, amount: u64) -> Result<u64, StakingError> {
+        if amount == 0 {
+            return Err(StakingError::ZeroAmount);
+        }
+        let entry = self.validators.entry(*validator).or_default();
+        let bonded = entry.bonded.checked_add(amount).ok_or(StakingError::Overflow)?;
+        if entry.bonded == 0 {
+            // A pool wiped out by slashing restarts at one share per token.
+            entry.total_shares = 0;
+            entry.delegations.clear();
+        }
+        let shares = if entry.total_shares == 0 {
+            amount as u128
+        } else {
+            amount as u128 * entry.total_shares / entry.bonded as u128
+        };
+        entry.bonded = bonded;
+        entry.total_shares += shares;
+        *entry.delegations.entry(*delegator).or_default() += shares;
+        Ok(entry.bonded)
+    }
+
+    /// Sets the validator's commission rate.
+    pub fn set_commission(&mut self, validator: &ValidatorId, commission_bps: u64) -> Result<(), StakingError> {
+        if commission_bps > 10_000 {
+            return Err(StakingError::InvalidCommission(commission_bps));
+        }
+        let entry = self
+            .validators
+            .get_mut(validator)
+            .ok_or(StakingError::UnknownValidator(*validator))?;
+        entry.commission_bps = commission_bps;
+        Ok(())
+    }
+
+    /// Registers the key the validator signs consensus messages with, either
+    /// tagged with its scheme or as a bare SEC1 secp256k1 key.
+    pub fn register_key(&mut self, validator: &ValidatorId, consensus_key: &[u8]) -> Result<(), StakingError> {
+        let key = normalize_key(consensus_key)?;
+        if let Some(owner) = self.validator_by_key(&key) {
+            if owner != validator {
+                return Err(StakingError::KeyInUse(*owner));
+            }
+        }
+        let entry = self
+            .validators
+            .get_mut(validator)
+            .ok_or(StakingError::UnknownValidator(*validator))?;
+        entry.consensus_key = Some(key);
+        Ok(())
+    }
+
+    /// Moves `amount` of the validator's self-bond into an unbonding entry and returns its release epoch.
+    pub fn unbond(&mut self, validator: &ValidatorId, amount: u64) -> Result<u64, StakingError> {
+        self.undelegate(validator, validator, amount)
+    }
+
+    /// Moves `amount` of `delegator`'s bond into an unbonding entry and returns its release epoch.
+    pub fn undelegate(
+        &mut self,
+        delegator: &Address,
+        validator: &ValidatorId,
+        amount: u64,
+    ) -> Result<u64, StakingError> {
+        let release_epoch = self
+            .current_epoch
+            .checked_add(self.unbonding_period)
+            .ok_or(StakingError::Overflow)?;
+        self.remove_delegation(delegator, validator, amount)?;
+        let entry = self.validators.get_mut(validator).expect("delegation was just removed");
+        entry.unbonding.push(UnbondingEntry { delegator: *delegator, amount, release_epoch });
+        Ok(release_epoch)
+    }
+
+    /// Moves `amount` of `delegator`'s bond from one validator to another
+    /// without unbonding. The source keeps a redelegation entry so the moved
+    /// stake stays slashable there for the unbonding period.
+    pub fn redelegate(
+        &mut self,
+        delegator: &Address,
+        from: &ValidatorId,
+        to: &ValidatorId,
+        amount: u64,
+    ) -> Result<u64, StakingError> {
+        if from == to {
+            return Err(StakingError::SameValidator);
+        }
+        self.remove_delegation(delegator, from, amount)?;
+        let bonded = self.delegate(delegator, to, amount)?;
+        let creation_epoch = self.current_epoch;
+        let source = self.validators.get_mut(from).expect("delegation was just removed");
+        source.redelegations.push(RedelegationEntry {
+            delegator: *delegator,
+            destination: *to,
+            amount,
+            creation_epoch,
+        });
+        Ok(bonded)
+    }
+
+    /// Splits a reward between the validator's commission and its delegators.
+    ///
+    /// The commission is paid out to the validator's withdrawable balance; the
+    /// rest is added to the bonded pool, growing every delegation pro rata.
+    pub fn distribute_reward(&mut self, validator: &ValidatorId, amount: u64) -> Result<RewardSplit, StakingError> {
+        let entry = self
+            .validators
+            .get_mut(validator)
+            .ok_or(StakingError::UnknownValidator(*validator))?;
+        let (commission, delegators) = if entry.total_shares == 0 {
+            (amount, 0)
+        } else {
+            let commission = (amount as u128 * entry.commission_bps as u128 / 10_000) as u64;
+            (commission, amount - commission)
+        };
+        entry.bonded = entry.bonded.checked_add(delegators).ok_or(StakingError::Overflow)?;
+        self.credit_withdrawable(validator, commission)?;
+        Ok(RewardSplit { commission, delegators })
+    }
+
+    /// Advances to `epoch`, releasing matured unbonding entries into
+    /// withdrawable balance and dropping redelegation entries past the
+    /// unbonding period. Empty validators are pruned, unless they were ever
+    /// slashed: their infraction record must outlive them so old evidence
+    /// can't be replayed once the address bonds again.
+    pub fn advance_epoch(&mut self, epoch: u64) {
+        self.current_epoch = self.current_epoch.max(epoch);
+        let current_epoch = self.current_epoch;
+        let unbonding_period = self.unbonding_period;
+        for stake in self.validators.values_mut() {
+            stake
+                .redelegations
+                .retain(|entry| entry.creation_epoch.saturating_add(unbonding_period) > current_epoch);
+            let balances = &mut self.balances;
+            stake.unbonding.retain(|entry| {
+                let matured = entry.release_epoch <= current_epoch;
+                if matured {
+                    let balance = balances.entry(entry.delegator).or_default();
+                    *balance = balance.saturating_add(entry.amount);
+                }
+                !matured
+            });
+        }
+        self.validators.retain(|_, stake| {
+            let jailed = stake.jailed_until.is_some_and(|until| until > current_epoch);
+            let in_use = stake.total_shares > 0 || !stake.unbonding.is_empty() || !stake.redelegations.is_empty();
+            in_use || jailed || !stake.slashed_infractions.is_empty()
+        });
+    }
+
+    /// Withdraws the account's entire withdrawable balance.
+    pub fn withdraw(&mut self, account: &Address) -> Result<u64, StakingError> {
+        match self.balances.remove(account) {
+            Some(amount) if amount > 0 => Ok(amount),
+            _ => Err(StakingError::NothingToWithdraw),
+        }
+    }
+
+    fn remove_delegation(
+        &mut self,
+        delegator: &Address,
+        validator: &ValidatorId,
+        amount: u64,
+    ) -> Result<(), StakingError> {
+        if amount == 0 {
+            return Err(StakingError::ZeroAmount);
+        }
+        let entry = self
+            .validators
+            .get_mut(validator)
+            .ok_or(StakingError::UnknownValidator(*validator))?;
+        let bonded = entry.delegation(delegator);
+        if bonded < amount {
+            return Err(StakingError::InsufficientBond { bonded, requested: amount });
+        }
+
+        // Round the burned shares up so the remaining delegators never lose value.
+        let shares = (amount as u128 * entry.total_shares).div_ceil(entry.bonded as u128);
+        let held = entry.delegations.get_mut(delegator).expect("delegation has a positive value");
+        *held -= shares;
+        if *held == 0 {
+            entry.delegations.remove(delegator);
+        }
+        entry.total_shares -= shares;
+        entry.bonded -= amount;
+        Ok(())
+    }
+}
+
+impl StakeRegistry {
+    /// Burns `fraction_bps` of the validator's bonded, unbonding and
+    /// redelegated stake.
+    ///
+    /// Unbonding and redelegation entries are included so stake can't escape
+    /// a penalty by leaving just before the evidence lands; redelegated stake
+    /// is burned at its destination. Delegators share the loss pro rata
+    /// through the pool. Returns the amount removed.
+    pub fn slash(&mut self, validator: &ValidatorId, fraction_bps: u64) -> Result<u64, StakingError> {
+        let fraction_bps = fraction_bps.min(10_000) as u128;
+        let entry = self
+            .validators
+            .get_mut(validator)
+            .ok_or(StakingError::UnknownValidator(*validator))?;
+
+        let cut = |amount: u64| (amount as u128 * fraction_bps / 10_000) as u64;
+        let mut slashed = cut(entry.bonded);
+        entry.bonded -= slashed;
+        for unbonding in entry.unbonding.iter_mut() {
+            let penalty = cut(unbonding.amount);
+            unbonding.amount -= penalty;
+            slashed += penalty;
+        }
+        entry.unbonding.retain(|unbonding| unbonding.amount > 0);
+
+        let mut redelegated = Vec::with_capacity(entry.redelegations.len());
+        for redelegation in entry.redelegations.iter_mut() {
+            let penalty = cut(redelegation.amount);
+            redelegation.amount -= penalty;
+            redelegated.push((redelegation.delegator, redelegation.destination, penalty));
+        }
+        entry.redelegations.retain(|redelegation| redelegation.amount > 0);
+        for (delegator, destination, penalty) in redelegated {
+            slashed += self.burn_redelegated(&delegator, &destination, penalty);
+        }
+        Ok(slashed)
+    }
+
+    /// Burns up to `amount` of the stake `delegator` holds with `validator`,
+    /// from the delegation first and then from its pending unbonding entries.
+    fn burn_redelegated(&mut self, delegator: &Address, validator: &ValidatorId, amount: u64) -> u64 {
+        let bonded = self.delegation(delegator, validator).min(amount);
+        if bonded > 0 {
+            self.remove_delegation(delegator, validator, bonded).expect("delegation covers the amount");
+        }
+        let mut burned = bonded;
+        if let Some(entry) = self.validators.get_mut(validator) {
+            for unbonding in entry.unbonding.iter_mut().filter(|unbonding| unbonding.delegator == *delegator) {
+                let penalty = (amount - burned).min(unbonding.amount);
+                unbonding.amount -= penalty;
+                burned += penalty;
+            }
+            entry.unbonding.retain(|unbonding| unbonding.amount > 0);
+        }
+        burned
+    }
+
+    /// Burns up to `amount` of the validator's bonded stake, leaving pending
+    /// unbonding entries alone. Used for liveness penalties, which are
+    /// smaller and more frequent than slashes. Returns the amount removed.
+    pub fn penalize(&mut self, validator: &ValidatorId, amount: u64) -> Result<u64, StakingError> {
+        let entry = self
+            .validators
+            .get_mut(validator)
+            .ok_or(StakingError::UnknownValidator(*validator))?;
+        let penalty = amount.min(entry.bonded);
+        entry.bonded -= penalty;
+        Ok(penalty)
+    }
+
+    /// Excludes the validator from the active set until `until_epoch`.
+    pub fn jail(&mut self, validator: &ValidatorId, until_epoch: u64) -> Result<(), StakingError> {
+        let entry = self
+            .validators
+            .get_mut(validator)
+            .ok_or(StakingError::UnknownValidator(*validator))?;
+        entry.jailed_until = Some(entry.jailed_until.unwrap_or(0).max(until_epoch));
+        Ok(())
+    }
+
+    /// Lifts an expired jail sentence.
+    pub fn unjail(&mut self, validator: &ValidatorId) -> Result<(), StakingError> {
+        let current_epoch = self.current_epoch;
+        let entry = self
+            .validators
+            .get_mut(validator)
+            .ok_or(StakingError::UnknownValidator(*validator))?;
+        match entry.jailed_until {
+            Some(until) if until > current_epoch => Err(StakingError::Jailed(until)),
+            _ => {
+                entry.jailed_until = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Credits `amount` straight to an account's withdrawable balance.
+    pub fn credit_withdrawable(&mut self, account: &Address, amount: u64) -> Result<(), StakingError> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let balance = self.balances.entry(*account).or_default();
+        *balance = balance.checked_add(amount).ok_or(StakingError::Overflow)?;
+        Ok(())
+    }
+
+    /// Records a slashed infraction, returning `false` if the same kind was already recorded at `height`.
+    pub fn record_infraction(
+        &mut self,
+        validator: &ValidatorId,
+        kind: InfractionKind,
+        height: u64,
+    ) -> Result<bool, StakingError> {
+        let entry = self
+            .validators
+            .get_mut(validator)
+            .ok_or(StakingError::UnknownValidator(*validator))?;
+        Ok(entry.slashed_infractions.insert((kind, height)))
+    }
+}
+
+impl Default for StakeRegistry {
+    fn default() -> Self {
+        Self::new(DEFAULT_UNBONDING_PERIOD)
+    }
+}
+
+/// Validates a validator's stake against the registry.
+pub fn validate_stake(registry: &StakeRegistry, validator: &ValidatorId) -> bool {
+    registry.bonded(validator) >= MIN_STAKE // Minimum stake requirement
+        && !registry.is_jailed(validator)
+}
+
+/// Re-encodes a consensus key in tagged form, compressing secp256k1 keys.
+///
+/// SEC1 prefixes never collide with scheme tags, so an untagged key is read
+/// as secp256k1.
+pub(crate) fn normalize_key(consensus_key: &[u8]) -> Result<Vec<u8>, StakingError> {
+    let (scheme, key) = decode_tagged(consensus_key).unwrap_or((SchemeTag::Secp256k1, consensus_key));
+    let key = match scheme {
+        SchemeTag::Secp256k1 => compress_public_key(key).map_err(|_| StakingError::InvalidKey)?,
+        SchemeTag::MlDsa65 if key.len() == ML_DSA_65_PUBLIC_KEY_LEN => key.to_vec(),
+        SchemeTag::MlDsa65 => return Err(StakingError::InvalidKey),
+    };
+    Ok(encode_tagged(scheme, &key))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::address::testing::{ALICE, BOB, CHARLIE, DAVE, ERIN};
+
+    const VALIDATOR: Address = Address::repeat_byte(0x01);
+    const REPORTER: Address = Address::repeat_byte(0x02);
+
+    fn registry_with(stakes: &[(Address, u64)]) -> StakeRegistry {
+        let mut registry = StakeRegistry::new(3);
+        for (validator, stake) in stakes {
+            registry.bond(validator, *stake).unwrap();
+        }
+        registry
+    }
 
     #[test]
     fn test_validate_stake() {
-        assert_eq!(validate_stake("validator1", 1000), true);
-        assert_eq!(validate_stake("validator2", 1500), true);
-        assert_eq!(validate_stake("validator3", 999), false);
-        assert_eq!(validate_stake("validator4", 0), false);
+        let registry = registry_with(&[(ALICE, 1000), (BOB, 1500), (CHARLIE, 999)]);
+        assert!(validate_stake(&registry, &ALICE));
+        assert!(validate_stake(&registry, &BOB));
+        assert!(!validate_stake(&registry, &CHARLIE));
+        assert!(!validate_stake(&registry, &DAVE));
+    }
+
+    #[test]
+    fn test_bond_and_unbond() {
+        let mut registry = registry_with(&[(ALICE, 1500)]);
+        assert_eq!(registry.bond(&ALICE, 500).unwrap(), 2000);
+        assert_eq!(registry.total_bonded(), 2000);
+
+        // Unbonding releases after the unbonding period.
+        assert_eq!(registry.unbond(&ALICE, 1200).unwrap(), 3);
+        assert_eq!(registry.bonded(&ALICE), 800);
+        assert!(!validate_stake(&registry, &ALICE));
+
+        // Errors for bad amounts and unknown validators.
+        assert!(matches!(registry.bond(&ALICE, 0), Err(StakingError::ZeroAmount)));
+        assert!(matches!(
+            registry.unbond(&ALICE, 801),
+            Err(StakingError::InsufficientBond { bonded: 800, requested: 801 })
+        ));
+        assert!(matches!(registry.unbond(&BOB, 1), Err(StakingError::UnknownValidator(_))));
+    }
+
+    #[test]
+    fn test_unbonding_period_and_withdraw() {
+        let mut registry = registry_with(&[(ALICE, 2000)]);
+        registry.unbond(&ALICE, 500).unwrap();
+        registry.advance_epoch(1);
+        registry.unbond(&ALICE, 300).unwrap();
+
+        // Nothing has matured yet.
+        registry.advance_epoch(2);
+        assert!(matches!(registry.withdraw(&ALICE), Err(StakingError::NothingToWithdraw)));
+
+        // The first entry matures at epoch 3, the second at epoch 4.
+        registry.advance_epoch(3);
+        assert_eq!(registry.withdraw(&ALICE).unwrap(), 500);
+        assert_eq!(registry.stake(&ALICE).unwrap().unbonding.len(), 1);
+
+        registry.advance_epoch(10);
+        assert_eq!(registry.withdraw(&ALICE).unwrap(), 300);
+        assert_eq!(registry.bonded(&ALICE), 1200);
+
+        // Epochs never move backwards.
+        registry.advance_epoch(5);
+        assert_eq!(registry.current_epoch(), 10);
+
+        // A release epoch past u64::MAX is an error and leaves the bond untouched.
+        let mut registry = StakeRegistry::new(u64::MAX);
+        registry.bond(&ALICE, 2000).unwrap();
+        registry.advance_epoch(1);
+        assert!(matches!(registry.unbond(&ALICE, 500), Err(StakingError::Overflow)));
+        assert_eq!(registry.bonded(&ALICE), 2000);
+    }
+
+    #[test]
+    fn test_fully_withdrawn_validator_is_removed() {
+        let mut registry = registry_with(&[(ALICE, 1000)]);
+        registry.unbond(&ALICE, 1000).unwrap();
+        registry.advance_epoch(3);
+        assert_eq!(registry.withdraw(&ALICE).unwrap(), 1000);
+        assert!(registry.stake(&ALICE).is_none());
+    }
+
+    #[test]
+    fn test_active_validators() {
+        let registry = registry_with(&[(ALICE, 100), (BOB, 2000), (CHARLIE, 1000)]);
+        assert_eq!(registry.active_validators(), vec![(BOB, 2000), (CHARLIE, 1000)]);
+    }
+
+    #[test]
+    fn test_register_key() {
+        let mut registry = registry_with(&[(ALICE, 1000), (BOB, 1000)]);
+        let (_, public_key) = crate::utils::crypto::generate_keypair();
+
+        registry.register_key(&ALICE, &public_key).unwrap();
+        let compressed = compress_public_key(&public_key).unwrap();
+        let tagged = encode_tagged(SchemeTag::Secp256k1, &compressed);
+        assert_eq!(registry.stake(&ALICE).unwrap().consensus_key, Some(tagged.clone()));
+
+        // Lookups work with any SEC1 encoding, tagged or bare.
+        assert_eq!(registry.validator_by_key(&public_key), Some(&ALICE));
+        assert_eq!(registry.validator_by_key(&compressed), Some(&ALICE));
+        assert_eq!(registry.validator_by_key(&tagged), Some(&ALICE));
+        assert_eq!(registry.validator_by_key(&encode_tagged(SchemeTag::Secp256k1, &public_key)), Some(&ALICE));
+
+        // A key can only belong to one validator.
+        assert!(matches!(registry.register_key(&BOB, &public_key), Err(StakingError::KeyInUse(_))));
+        assert!(matches!(registry.register_key(&BOB, &[1, 2, 3]), Err(StakingError::InvalidKey)));
+        let truncated = encode_tagged(SchemeTag::MlDsa65, &[0; 32]);
+        assert!(matches!(registry.register_key(&BOB, &truncated), Err(StakingError::InvalidKey)));
+        let (_, other_public_key) = crate::utils::crypto::generate_keypair();
+        assert!(matches!(
+            registry.register_key(&CHARLIE, &other_public_key),
+            Err(StakingError::UnknownValidator(_))
+        ));
+    }
+
+    #[test]
+    fn test_slash_and_jail() {
+        let mut registry = registry_with(&[(ALICE, 3000), (BOB, 1000)]);
+        registry.unbond(&ALICE, 1000).unwrap();
+
+        // 10% of both bonded and unbonding stake is burned.
+        assert_eq!(registry.slash(&ALICE, 1_000).unwrap(), 300);
+        assert_eq!(registry.bonded(&ALICE), 1800);
+        assert_eq!(registry.stake(&ALICE).unwrap().unbonding[0].amount, 900);
+
+        // Jailed validators leave the active set until the sentence ends.
+        registry.jail(&ALICE, 2).unwrap();
+        assert!(registry.is_jailed(&ALICE));
+        assert!(!validate_stake(&registry, &ALICE));
+        assert_eq!(registry.active_validators(), vec![(BOB, 1000)]);
+        assert!(matches!(registry.unjail(&ALICE), Err(StakingError::Jailed(2))));
+
+        registry.advance_epoch(2);
+        assert!(!registry.is_jailed(&ALICE));
+        registry.unjail(&ALICE).unwrap();
+        assert_eq!(registry.stake(&ALICE).unwrap().jailed_until, None);
+        assert_eq!(registry.active_validators(), vec![(ALICE, 1800), (BOB, 1000)]);
     }
 
     #[test]
-    fn test_calculate_reward() {
-        assert_eq!(calculate_reward(1000), 10);
-        assert_eq!(calculate_reward(2500), 25);
-        assert_eq!(calculate_reward(999), 9);
-        assert_eq!(calculate_reward(0), 0);
-        assert_eq!(calculate_reward(1001), 10);
+    fn test_penalize() {
+        let mut registry = registry_with(&[(ALICE, 2000)]);
+        registry.unbond(&ALICE, 500).unwrap();
+        assert_eq!(registry.penalize(&ALICE, 100).unwrap(), 100);
+        assert_eq!(registry.bonded(&ALICE), 1400);
+        assert_eq!(registry.stake(&ALICE).unwrap().unbonding[0].amount, 500);
+
+        // Penalties are capped at the bonded stake.
+        assert_eq!(registry.penalize(&ALICE, 5000).unwrap(), 1400);
+        assert_eq!(registry.bonded(&ALICE), 0);
+        assert!(matches!(registry.penalize(&BOB, 1), Err(StakingError::UnknownValidator(_))));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_credit_and_record_infraction() {
+        let mut registry = registry_with(&[(ALICE, 1000)]);
+        registry.credit_withdrawable(&REPORTER, 50).unwrap();
+        assert_eq!(registry.withdraw(&REPORTER).unwrap(), 50);
+
+        assert!(registry.record_infraction(&ALICE, InfractionKind::DoubleSign, 10).unwrap());
+        assert!(!registry.record_infraction(&ALICE, InfractionKind::DoubleSign, 10).unwrap());
+        assert!(registry.record_infraction(&ALICE, InfractionKind::DoubleSign, 11).unwrap());
+        assert!(registry.record_infraction(&ALICE, InfractionKind::FfgViolation, 10).unwrap());
+    }
+
+    #[test]
+    fn test_delegate_and_undelegate() {
+        let mut registry = registry_with(&[(VALIDATOR, 1000)]);
+        assert_eq!(registry.delegate(&DAVE, &VALIDATOR, 500).unwrap(), 1500);
+        assert_eq!(registry.delegate(&ERIN, &VALIDATOR, 250).unwrap(), 1750);
+        assert_eq!(registry.delegation(&DAVE, &VALIDATOR), 500);
+        assert_eq!(registry.delegation(&VALIDATOR, &VALIDATOR), 1000);
+
+        // Delegators unbond independently of the validator.
+        assert_eq!(registry.undelegate(&DAVE, &VALIDATOR, 200).unwrap(), 3);
+        assert_eq!(registry.delegation(&DAVE, &VALIDATOR), 300);
+        assert_eq!(registry.bonded(&VALIDATOR), 1550);
+        assert!(matches!(
+            registry.undelegate(&ERIN, &VALIDATOR, 251),
+            Err(StakingError::InsufficientBond { bonded: 250, requested: 251 })
+        ));
+
+        registry.advance_epoch(3);
+        assert_eq!(registry.withdraw(&DAVE).unwrap(), 200);
+        assert!(matches!(registry.withdraw(&VALIDATOR), Err(StakingError::NothingToWithdraw)));
+    }
+
+    #[test]
+    fn test_redelegate() {
+        let mut registry = registry_with(&[(ALICE, 1000), (BOB, 1000)]);
+        registry.delegate(&DAVE, &ALICE, 600).unwrap();
+
+        assert_eq!(registry.redelegate(&DAVE, &ALICE, &BOB, 400).unwrap(), 1400);
+        assert_eq!(registry.delegation(&DAVE, &ALICE), 200);
+        assert_eq!(registry.delegation(&DAVE, &BOB), 400);
+        assert_eq!(registry.total_bonded(), 2600);
+
+        assert!(matches!(registry.redelegate(&DAVE, &ALICE, &ALICE, 1), Err(StakingError::SameValidator)));
+        assert!(matches!(
+            registry.redelegate(&DAVE, &ALICE, &BOB, 201),
+            Err(StakingError::InsufficientBond { .. })
+        ));
+    }
+
+    #[test]
+    fn test_redelegated_stake_stays_slashable() {
+        let mut registry = registry_with(&[(ALICE, 1000), (BOB, 1000)]);
+        registry.delegate(&DAVE, &ALICE, 1000).unwrap();
+        registry.redelegate(&DAVE, &ALICE, &BOB, 600).unwrap();
+        registry.advance_epoch(1);
+        registry.redelegate(&DAVE, &ALICE, &BOB, 400).unwrap();
+        registry.undelegate(&DAVE, &BOB, 950).unwrap();
+
+        // Evidence against alice lands after dave moved everything to bob:
+        // 10% of alice's own bond plus 10% of each redelegation is burned,
+        // from dave's remaining bond at bob and then his unbonding entry there.
+        assert_eq!(registry.slash(&ALICE, 1_000).unwrap(), 200);
+        assert_eq!(registry.bonded(&ALICE), 900);
+        assert_eq!(registry.delegation(&DAVE, &BOB), 0);
+        assert_eq!(registry.delegation(&BOB, &BOB), 1000);
+        assert_eq!(registry.stake(&BOB).unwrap().unbonding[0].amount, 900);
+        let remaining: Vec<u64> = registry.stake(&ALICE).unwrap().redelegations.iter().map(|r| r.amount).collect();
+        assert_eq!(remaining, vec![540, 360]);
+
+        // Entries expire once the unbonding period has passed since each redelegation.
+        registry.advance_epoch(3);
+        assert_eq!(registry.stake(&ALICE).unwrap().redelegations.len(), 1);
+        registry.advance_epoch(4);
+        assert!(registry.stake(&ALICE).unwrap().redelegations.is_empty());
+        assert_eq!(registry.slash(&ALICE, 1_000).unwrap(), 90);
+    }
+
+    #[test]
+    fn test_rewards_split_with_commission() {
+        let mut registry = registry_with(&[(VALIDATOR, 1000)]);
+        registry.delegate(&DAVE, &VALIDATOR, 3000).unwrap();
+        registry.set_commission(&VALIDATOR, 1_000).unwrap();
+        assert!(matches!(registry.set_commission(&VALIDATOR, 10_001), Err(StakingError::InvalidCommission(_))));
+
+        // 10% commission, the remaining 360 split 1:3 through the pool.
+        let split = registry.distribute_reward(&VALIDATOR, 400).unwrap();
+        assert_eq!(split, RewardSplit { commission: 40, delegators: 360 });
+        assert_eq!(registry.withdrawable(&VALIDATOR), 40);
+        assert_eq!(registry.delegation(&VALIDATOR, &VALIDATOR), 1090);
+        assert_eq!(registry.delegation(&DAVE, &VALIDATOR), 3270);
+
+        // New delegations buy in at the current share price.
+        registry.delegate(&ERIN, &VALIDATOR, 436).unwrap();
+        assert_eq!(registry.delegation(&ERIN, &VALIDATOR), 436);
+        assert_eq!(registry.delegation(&DAVE, &VALIDATOR), 3270);
+    }
+
+    #[test]
+    fn test_slash_splits_pro_rata() {
+        let mut registry = registry_with(&[(VALIDATOR, 1000)]);
+        registry.delegate(&DAVE, &VALIDATOR, 3000).unwrap();
+        registry.undelegate(&DAVE, &VALIDATOR, 1000).unwrap();
+
+        // 10% of the pool and of pending unbonding is burned.
+        assert_eq!(registry.slash(&VALIDATOR, 1_000).unwrap(), 400);
+        assert_eq!(registry.delegation(&VALIDATOR, &VALIDATOR), 900);
+        assert_eq!(registry.delegation(&DAVE, &VALIDATOR), 1800);
+        assert_eq!(registry.stake(&VALIDATOR).unwrap().unbonding[0].amount, 900);
+
+        // A fully slashed pool restarts cleanly for new delegators.
+        registry.slash(&VALIDATOR, 10_000).unwrap();
+        assert_eq!(registry.bonded(&VALIDATOR), 0);
+        registry.delegate(&ERIN, &VALIDATOR, 100).unwrap();
+        assert_eq!(registry.delegation(&ERIN, &VALIDATOR), 100);
+        assert_eq!(registry.delegation(&DAVE, &VALIDATOR), 0);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut registry = registry_with(&[(ALICE, 1500), (BOB, 2500)]);
+        registry.unbond(&BOB, 500).unwrap();
+
+        let path = std::env::temp_dir().join(format!("aetherforge_registry_{}.json", std::process::id()));
+        registry.save(&path).unwrap();
+        let loaded = StakeRegistry::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, registry);
+    }
+}
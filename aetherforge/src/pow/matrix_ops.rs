@@ -1,10 +1,28 @@
 use ndarray::Array2;
 
+use crate::utils::hashing::{ConsensusHasher, Domain};
+
 /// Compute-intensive matrix operations.
 pub fn matrix_operation(matrix: Array2<f64>) -> Array2<f64> {
     matrix.dot(&matrix.t())
 }
 
+/// Expands a seed into a deterministic `size` x `size` matrix with entries in [0, 1).
+pub fn seeded_matrix(seed: &[u8], size: usize) -> Array2<f64> {
+    let mut values = Vec::with_capacity(size * size);
+    let mut counter: u64 = 0;
+    while values.len() < size * size {
+        let block = ConsensusHasher::new(Domain::MatrixSeed).field(seed).field_u64(counter).finish();
+        for chunk in block.chunks_exact(4) {
+            let word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            values.push(word as f64 / (u32::MAX as f64 + 1.0));
+        }
+        counter += 1;
+    }
+    values.truncate(size * size);
+    Array2::from_shape_vec((size, size), values).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -40,4 +58,18 @@ mod tests {
             assert_eq!(result4, expected4);
         }
     }
+
+    #[test]
+    fn test_seeded_matrix() {
+        let matrix = seeded_matrix(b"seed", 5);
+        assert_eq!(matrix.shape(), &[5, 5]);
+        assert!(matrix.iter().all(|&x| (0.0..1.0).contains(&x)));
+
+        // Same seed gives the same matrix, different seeds differ.
+        assert_eq!(matrix, seeded_matrix(b"seed", 5));
+        assert_ne!(matrix, seeded_matrix(b"other seed", 5));
+
+        // Zero size yields an empty matrix.
+        assert_eq!(seeded_matrix(b"seed", 0).len(), 0);
+    }
 }
\ No newline at end of file
@@ -2,11 +2,16 @@ use argon2::{self, Config, ThreadMode, Variant, Version};
 
 /// Memory-hard hashing using Argon2.
 pub fn memory_hard_hash(input: &[u8], salt: &[u8]) -> Vec<u8> {
+    memory_hard_hash_with_cost(input, salt, 65536, 3) // Adjust based on device capabilities
+}
+
+/// Memory-hard hashing using Argon2 with explicit memory (KiB) and time costs.
+pub fn memory_hard_hash_with_cost(input: &[u8], salt: &[u8], mem_cost: u32, time_cost: u32) -> Vec<u8> {
     let config = Config {
         variant: Variant::Argon2id,
         version: Version::Version13,
-        mem_cost: 65536, // Adjust based on device capabilities
-        time_cost: 3,
+        mem_cost,
+        time_cost,
         lanes: 4,
         thread_mode: ThreadMode::Parallel,
         secret: &[],
@@ -14,4 +19,4 @@ pub fn memory_hard_hash(input: &[u8], salt: &[u8]) -> Vec<u8> {
         hash_length: 32,
     };
     argon2::hash_raw(input, salt, &config).unwrap()
-}
\ No newline at end of file
+}
@@ -4,18 +4,61 @@ pub mod puzzles;
 
 use ndarray::Array2;
 
+use crate::block::{BlockError, Hash, Header, POW_SALT_LEN};
+use crate::difficulty::{CostParams, DifficultySchedule};
+use crate::utils::hashing::{ConsensusHasher, Domain};
+
 /// Combines memory-hard hashing, matrix operations, and puzzle solving.
 pub fn forge_block(input: &[u8], salt: &[u8], matrix: Array2<f64>, puzzle_data: &[u8]) -> Vec<u8> {
     let memory_hash = memory_hard::memory_hard_hash(input, salt);
     let matrix_result = matrix_ops::matrix_operation(matrix);
     let puzzle_result = puzzles::solve_puzzle(puzzle_data);
 
-    let mut combined_input = Vec::new();
-    combined_input.extend_from_slice(&memory_hash);
-    combined_input.extend_from_slice(matrix_result.as_slice().unwrap());
-    combined_input.extend_from_slice(&puzzle_result);
+    combine(&memory_hash, &matrix_result, &puzzle_result, &[])
+}
+
+/// Forges a block with cost parameters derived from a `DifficultySchedule`.
+///
+/// The matrix is seeded from the Argon2 output so the compute work can't be
+/// precomputed, and the parameter commitment is bound into the final hash.
+pub fn forge_block_with_params(input: &[u8], salt: &[u8], params: &CostParams, puzzle_data: &[u8]) -> Vec<u8> {
+    let memory_hash = memory_hard::memory_hard_hash_with_cost(
+        input,
+        salt,
+        params.argon2_mem_cost,
+        params.argon2_time_cost,
+    );
+    let matrix = matrix_ops::seeded_matrix(&memory_hash, params.matrix_size as usize);
+    let matrix_result = matrix_ops::matrix_operation(matrix);
+    let puzzle_result = puzzles::solve_puzzle_with_difficulty(puzzle_data, params.puzzle_difficulty);
+
+    combine(&memory_hash, &matrix_result, &puzzle_result, &params.commitment())
+}
+
+fn combine(memory_hash: &[u8], matrix_result: &Array2<f64>, puzzle_result: &[u8], commitment: &[u8]) -> Vec<u8> {
+    let matrix_bytes: Vec<u8> = matrix_result.iter().flat_map(|value| value.to_be_bytes()).collect();
+    ConsensusHasher::digest(Domain::Block, &[memory_hash, &matrix_bytes, puzzle_result, commitment])
+}
+
+/// Forges the proof of work for `header` and stores it in `pow_hash`.
+pub fn seal_header(header: &mut Header) {
+    header.pow_hash = header_pow(header);
+}
+
+/// Recomputes the proof of work of `header` and checks it against `pow_hash`.
+pub fn verify_header_pow(header: &Header, schedule: &DifficultySchedule) -> Result<(), BlockError> {
+    header.validate_cost_params(schedule)?;
+    if !POW_SALT_LEN.contains(&header.pow_salt.len()) {
+        return Err(BlockError::InvalidPowSalt(header.pow_salt.len()));
+    }
+    if header_pow(header) != header.pow_hash {
+        return Err(BlockError::PowMismatch);
+    }
+    Ok(())
+}
 
-    let mut hasher = sha3::Sha3_256::new();
-    hasher.update(combined_input);
-    hasher.finalize().to_vec()
+fn header_pow(header: &Header) -> Hash {
+    forge_block_with_params(&header.seal_hash(), &header.pow_salt, &header.cost_params, &header.puzzle_input)
+        .try_into()
+        .expect("SHA3-256 digest is 32 bytes")
 }
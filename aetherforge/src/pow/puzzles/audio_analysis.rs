@@ -1,8 +1,9 @@
-use sha3::{Digest, Sha3_256};
 use hound::{WavReader, WavSpec};
 use rustfft::{FftPlanner, num_complex::Complex};
 use thiserror::Error; // For custom error handling
 
+use crate::utils::hashing::{sha3_256_hash, Domain};
+
 /// Custom error type for audio analysis
 #[derive(Error, Debug)]
 pub enum AudioAnalysisError {
@@ -35,11 +36,8 @@ pub fn solve(audio_data: &[u8], difficulty: u8) -> Result<Vec<u8>, AudioAnalysis
     let features = extract_features(&samples, spec.sample_rate as usize, difficulty)?;
 
     // 3. Hash the features
-    let mut hasher = Sha3_256::new();
-    for feature in features {
-        hasher.update(feature.to_be_bytes());
-    }
-    let hash = hasher.finalize().to_vec();
+    let feature_bytes: Vec<u8> = features.iter().flat_map(|feature| feature.to_be_bytes()).collect();
+    let hash = sha3_256_hash(Domain::AudioPuzzle, &feature_bytes);
 
     Ok(hash)
 }
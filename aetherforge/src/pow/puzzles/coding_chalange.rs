@@ -1,8 +1,9 @@
-use sha3::{Digest, Sha3_256};
 use rhai::{Engine, EvalAltResult, Scope, Position};
 use thiserror::Error; // For custom error handling
 use std::time::{Duration, Instant};
 
+use crate::utils::hashing::{sha3_256_hash, Domain};
+
 /// Custom error type for coding challenge
 #[derive(Error, Debug)]
 pub enum CodingChallengeError {
@@ -48,10 +49,7 @@ pub fn solve(code: &[u8], expected_result: i64) -> Result<Vec<u8>, CodingChallen
     // 6. Check if the result matches the expected value
     if result == expected_result {
         // 7. Hash the code if the result is correct
-        let mut hasher = Sha3_256::new();
-        hasher.update(code);
-        let hash = hasher.finalize().to_vec();
-        Ok(hash)
+        Ok(sha3_256_hash(Domain::CodingPuzzle, code))
     } else {
         Err(CodingChallengeError::ResultMismatch)
     }
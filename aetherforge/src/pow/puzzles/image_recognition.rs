@@ -1,7 +1,8 @@
-use sha3::{Digest, Sha3_256};
 use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Pixel};
 use thiserror::Error; // For custom error handling
-use opencv::{core, imgproc, types};
+use opencv::{core, imgproc, prelude::*, types};
+
+use crate::utils::hashing::{ConsensusHasher, Domain};
 
 /// Custom error type for image recognition
 #[derive(Error, Debug)]
@@ -14,31 +15,42 @@ pub enum ImageRecognitionError {
 
 /// Solves an image recognition puzzle by detecting edges and hashing the result.
 pub fn solve(image_data: &[u8]) -> Result<Vec<u8>, ImageRecognitionError> {
+    solve_with_difficulty(image_data, 1)
+}
+
+/// Solves an image recognition puzzle at `difficulty`: edges are detected on
+/// `difficulty` successively halved scales of the image and hashed together.
+/// Difficulty 1 (and 0) is the single-scale puzzle `solve` computes.
+pub fn solve_with_difficulty(image_data: &[u8], difficulty: u8) -> Result<Vec<u8>, ImageRecognitionError> {
     // 1. Load the image
     let img = image::load_from_memory(image_data)?;
 
     // 2. Convert to grayscale
     let img = img.to_luma8();
+    let mut scale = opencv_image_from_buffer(&img)?;
 
-    // 3. Detect edges using OpenCV
-    let edges = detect_edges(&img)?;
-
-    // 4. Hash the edge data
-    let mut hasher = Sha3_256::new();
-    hasher.update(edges.as_raw());
-    let hash = hasher.finalize().to_vec();
+    // 3. Detect edges at each scale, and 4. hash the edge data
+    let mut hasher = ConsensusHasher::new(Domain::ImagePuzzle);
+    for level in 0..difficulty.max(1) {
+        if level > 0 {
+            if scale.rows() < 2 || scale.cols() < 2 {
+                break;
+            }
+            let mut smaller = core::Mat::default();
+            imgproc::pyr_down(&scale, &mut smaller, core::Size::default(), core::BORDER_DEFAULT)?;
+            scale = smaller;
+        }
+        let edges = detect_edges(&scale)?;
+        hasher.field(edges.data_bytes()?);
+    }
 
-    Ok(hash)
+    Ok(hasher.finish())
 }
 
 /// Detects edges in an image using OpenCV's Canny edge detection.
-fn detect_edges(img: &ImageBuffer<Luma<u8>, Vec<u8>>) -> Result<core::Mat, opencv::Error> {
-    // Convert image::ImageBuffer to OpenCV Mat
-    let mat = opencv_image_from_buffer(img)?;
-
-    // Detect edges using Canny edge detection
+fn detect_edges(mat: &core::Mat) -> Result<core::Mat, opencv::Error> {
     let mut edges = core::Mat::default();
-    imgproc::canny(&mat, &mut edges, 50.0, 150.0, 3, false)?;
+    imgproc::canny(mat, &mut edges, 50.0, 150.0, 3, false)?;
 
     Ok(edges)
 }
@@ -67,4 +79,17 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap().len(), 32); // SHA3-256 output is 32 bytes
     }
+
+    #[test]
+    fn test_solve_with_difficulty() {
+        let img = GrayImage::from_fn(16, 16, |x, y| Luma([if (x / 4 + y / 4) & 1 == 0 { 0 } else { 255 }]));
+        let mut png = Vec::new();
+        DynamicImage::ImageLuma8(img)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+
+        // Difficulty 1 is the plain puzzle; higher levels add scales and change the result.
+        assert_eq!(solve_with_difficulty(&png, 1).unwrap(), solve(&png).unwrap());
+        assert_ne!(solve_with_difficulty(&png, 2).unwrap(), solve(&png).unwrap());
+    }
 }
\ No newline at end of file
@@ -6,8 +6,13 @@ use sha3::{Digest, Sha3_256};
 
 /// Solves a puzzle based on the input data.
 pub fn solve_puzzle(input: &[u8]) -> Vec<u8> {
+    solve_puzzle_with_difficulty(input, 1)
+}
+
+/// Solves a puzzle at the `puzzle_difficulty` derived by the difficulty schedule.
+pub fn solve_puzzle_with_difficulty(input: &[u8], difficulty: u8) -> Vec<u8> {
     // Placeholder: Use image recognition by default.
-    image_recognition::solve(input)
+    image_recognition::solve_with_difficulty(input, difficulty)
 }
 
 #[cfg(test)]
@@ -1,3 +1,2 @@
 pub mod pow_tests;
-pub mod pos_tests;
 pub mod difficulty_tests;
\ No newline at end of file
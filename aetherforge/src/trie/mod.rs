@@ -0,0 +1,478 @@
+pub mod node;
+pub mod storage;
+
+use sha3::{Digest, Keccak256};
+use std::mem;
+use thiserror::Error;
+
+use node::{to_nibbles, Node};
+pub use storage::{MemoryStore, NodeStore};
+
+/// A Keccak-256 node hash.
+pub type Hash = [u8; 32];
+
+/// Root of the empty trie: Keccak-256 of the RLP empty string.
+pub const EMPTY_ROOT: Hash = [
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e, 0x5b, 0x48, 0xe0,
+    0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+];
+
+/// Custom error type for trie operations
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TrieError {
+    #[error("Node {} missing from storage", hex::encode(.0))]
+    MissingNode(Hash),
+    #[error("Invalid node encoding: {0}")]
+    InvalidNode(#[from] rlp::DecoderError),
+    #[error("Proof does not contain the nodes on the key's path")]
+    InvalidProof,
+}
+
+/// A hexary Merkle Patricia Trie with RLP node encoding over Keccak-256, as
+/// used for Ethereum's state, storage and receipt roots.
+///
+/// Changes are kept in memory until [`Trie::root_hash`] commits them to the
+/// node store; nodes untouched since the last commit are loaded on demand.
+#[derive(Debug, Clone)]
+pub struct Trie<S: NodeStore = MemoryStore> {
+    store: S,
+    root: Node,
+}
+
+impl<S: NodeStore> Trie<S> {
+    /// An empty trie backed by `store`.
+    pub fn new(store: S) -> Self {
+        Self { store, root: Node::Empty }
+    }
+
+    /// The trie with the given root, whose nodes are read from `store`.
+    pub fn open(store: S, root: Hash) -> Self {
+        let root = if root == EMPTY_ROOT { Node::Empty } else { Node::Hash(root) };
+        Self { store, root }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, TrieError> {
+        let path = to_nibbles(key);
+        let mut node = self.root.clone();
+        let mut path = path.as_slice();
+        loop {
+            node = match self.resolve(node)? {
+                Node::Empty => return Ok(None),
+                Node::Leaf { path: leaf_path, value } => return Ok((leaf_path == path).then_some(value)),
+                Node::Extension { path: prefix, child } => match path.strip_prefix(prefix.as_slice()) {
+                    Some(rest) => {
+                        path = rest;
+                        *child
+                    }
+                    None => return Ok(None),
+                },
+                Node::Branch { mut children, value } => match path.split_first() {
+                    Some((&index, rest)) => {
+                        path = rest;
+                        mem::replace(&mut children[index as usize], Node::Empty)
+                    }
+                    None => return Ok(value),
+                },
+                Node::Hash(_) => unreachable!("resolved above"),
+            };
+        }
+    }
+
+    /// Sets `key` to `value`. An empty value removes the key, as in Ethereum.
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) -> Result<(), TrieError> {
+        if value.is_empty() {
+            return self.remove(key).map(|_| ());
+        }
+        let root = mem::replace(&mut self.root, Node::Empty);
+        self.root = self.insert_at(root, &to_nibbles(key), value)?;
+        Ok(())
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove(&mut self, key: &[u8]) -> Result<bool, TrieError> {
+        let root = mem::replace(&mut self.root, Node::Empty);
+        let (root, removed) = self.remove_at(root, &to_nibbles(key))?;
+        self.root = root;
+        Ok(removed)
+    }
+
+    /// Writes all pending nodes to the store and returns the root hash.
+    pub fn root_hash(&mut self) -> Hash {
+        let root = match &self.root {
+            Node::Empty => return EMPTY_ROOT,
+            Node::Hash(hash) => return *hash,
+            root => {
+                let encoded = root.encode(&mut self.store);
+                let hash = keccak(&encoded);
+                self.store.insert(hash, encoded);
+                hash
+            }
+        };
+        self.root = Node::Hash(root);
+        root
+    }
+
+    /// Merkle proof for `key`: the encodings of the hash-referenced nodes on
+    /// its path, starting with the root. Proves absence as well as presence.
+    pub fn prove(&mut self, key: &[u8]) -> Result<Vec<Vec<u8>>, TrieError> {
+        self.root_hash();
+        let path = to_nibbles(key);
+        let mut path = path.as_slice();
+        let mut proof = Vec::new();
+        let mut node = self.root.clone();
+        loop {
+            if let Node::Hash(hash) = node {
+                let encoded = self.store.get(&hash).ok_or(TrieError::MissingNode(hash))?;
+                node = Node::decode(&encoded)?;
+                proof.push(encoded);
+            }
+            node = match node {
+                Node::Extension { path: prefix, child } if path.starts_with(&prefix) => {
+                    path = &path[prefix.len()..];
+                    *child
+                }
+                Node::Branch { mut children, .. } if !path.is_empty() => {
+                    let index = path[0] as usize;
+                    path = &path[1..];
+                    mem::replace(&mut children[index], Node::Empty)
+                }
+                _ => return Ok(proof),
+            };
+        }
+    }
+
+    fn resolve(&self, node: Node) -> Result<Node, TrieError> {
+        match node {
+            Node::Hash(hash) => {
+                let encoded = self.store.get(&hash).ok_or(TrieError::MissingNode(hash))?;
+                Ok(Node::decode(&encoded)?)
+            }
+            node => Ok(node),
+        }
+    }
+
+    fn insert_at(&self, node: Node, path: &[u8], value: Vec<u8>) -> Result<Node, TrieError> {
+        Ok(match self.resolve(node)? {
+            Node::Empty => Node::Leaf { path: path.to_vec(), value },
+            Node::Leaf { path: leaf_path, value: leaf_value } => {
+                let common = common_prefix(&leaf_path, path);
+                if common == leaf_path.len() && common == path.len() {
+                    return Ok(Node::Leaf { path: leaf_path, value });
+                }
+                let mut children = Node::empty_branch();
+                let mut branch_value = None;
+                for (node_path, node_value) in [(leaf_path.as_slice(), leaf_value), (path, value)] {
+                    match node_path.get(common) {
+                        Some(&index) => {
+                            children[index as usize] =
+                                Node::Leaf { path: node_path[common + 1..].to_vec(), value: node_value }
+                        }
+                        None => branch_value = Some(node_value),
+                    }
+                }
+                extend(&path[..common], Node::Branch { children, value: branch_value })
+            }
+            Node::Extension { path: prefix, child } => {
+                let common = common_prefix(&prefix, path);
+                if common == prefix.len() {
+                    let child = self.insert_at(*child, &path[common..], value)?;
+                    return Ok(Node::Extension { path: prefix, child: Box::new(child) });
+                }
+                let mut children = Node::empty_branch();
+                children[prefix[common] as usize] = extend(&prefix[common + 1..], *child);
+                let mut branch = Node::Branch { children, value: None };
+                branch = self.insert_at(branch, &path[common..], value)?;
+                extend(&path[..common], branch)
+            }
+            Node::Branch { mut children, value: branch_value } => match path.split_first() {
+                Some((&index, rest)) => {
+                    let child = mem::replace(&mut children[index as usize], Node::Empty);
+                    children[index as usize] = self.insert_at(child, rest, value)?;
+                    Node::Branch { children, value: branch_value }
+                }
+                None => Node::Branch { children, value: Some(value) },
+            },
+            Node::Hash(_) => unreachable!("resolved above"),
+        })
+    }
+
+    fn remove_at(&self, node: Node, path: &[u8]) -> Result<(Node, bool), TrieError> {
+        Ok(match self.resolve(node)? {
+            Node::Empty => (Node::Empty, false),
+            Node::Leaf { path: leaf_path, value } => {
+                if leaf_path == path {
+                    (Node::Empty, true)
+                } else {
+                    (Node::Leaf { path: leaf_path, value }, false)
+                }
+            }
+            Node::Extension { path: prefix, child } => {
+                let Some(rest) = path.strip_prefix(prefix.as_slice()) else {
+                    return Ok((Node::Extension { path: prefix, child }, false));
+                };
+                let (child, removed) = self.remove_at(*child, rest)?;
+                (self.normalize(extend(&prefix, child))?, removed)
+            }
+            Node::Branch { mut children, value } => {
+                let removed;
+                let value = match path.split_first() {
+                    Some((&index, rest)) => {
+                        let child = mem::replace(&mut children[index as usize], Node::Empty);
+                        let (child, child_removed) = self.remove_at(child, rest)?;
+                        children[index as usize] = child;
+                        removed = child_removed;
+                        value
+                    }
+                    None => {
+                        removed = value.is_some();
+                        None
+                    }
+                };
+                let branch = Node::Branch { children, value };
+                if removed {
+                    (self.normalize(branch)?, true)
+                } else {
+                    (branch, false)
+                }
+            }
+            Node::Hash(_) => unreachable!("resolved above"),
+        })
+    }
+
+    /// Restores the canonical shape after a removal: collapses branches left
+    /// with a single entry and merges extensions into the node below them.
+    fn normalize(&self, node: Node) -> Result<Node, TrieError> {
+        match node {
+            Node::Branch { mut children, value } => {
+                let mut occupied = children.iter().enumerate().filter(|(_, child)| **child != Node::Empty);
+                let only_child = match (occupied.next(), occupied.next()) {
+                    (None, _) => None,
+                    (Some((index, _)), None) => Some(index),
+                    _ => return Ok(Node::Branch { children, value }),
+                };
+                match (only_child, value) {
+                    (None, None) => Ok(Node::Empty),
+                    (None, Some(value)) => Ok(Node::Leaf { path: Vec::new(), value }),
+                    (Some(index), None) => {
+                        let child = mem::replace(&mut children[index], Node::Empty);
+                        self.normalize(Node::Extension { path: vec![index as u8], child: Box::new(child) })
+                    }
+                    (Some(_), Some(value)) => Ok(Node::Branch { children, value: Some(value) }),
+                }
+            }
+            Node::Extension { mut path, child } => match self.resolve(*child)? {
+                Node::Empty => Ok(Node::Empty),
+                Node::Leaf { path: rest, value } => {
+                    path.extend(rest);
+                    Ok(Node::Leaf { path, value })
+                }
+                Node::Extension { path: rest, child } => {
+                    path.extend(rest);
+                    Ok(Node::Extension { path, child })
+                }
+                branch => Ok(Node::Extension { path, child: Box::new(branch) }),
+            },
+            node => Ok(node),
+        }
+    }
+}
+
+impl Default for Trie<MemoryStore> {
+    fn default() -> Self {
+        Self::new(MemoryStore::default())
+    }
+}
+
+/// Looks up `key` in the trie with root `root` using only the nodes in `proof`.
+pub fn verify_proof<T: AsRef<[u8]>>(root: &Hash, key: &[u8], proof: &[T]) -> Result<Option<Vec<u8>>, TrieError> {
+    Trie::open(MemoryStore::from_nodes(proof), *root).get(key).map_err(|err| match err {
+        TrieError::MissingNode(_) => TrieError::InvalidProof,
+        err => err,
+    })
+}
+
+/// `child` below `path`, or `child` itself if the path is empty.
+fn extend(path: &[u8], child: Node) -> Node {
+    if path.is_empty() {
+        child
+    } else {
+        Node::Extension { path: path.to_vec(), child: Box::new(child) }
+    }
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn keccak(input: &[u8]) -> Hash {
+    Keccak256::digest(input).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn root_of(entries: &[(&str, Option<&str>)]) -> String {
+        let mut trie = Trie::default();
+        for (key, value) in entries {
+            let value = value.map(|value| value.as_bytes().to_vec()).unwrap_or_default();
+            trie.insert(key.as_bytes(), value).unwrap();
+        }
+        hex::encode(trie.root_hash())
+    }
+
+    #[test]
+    fn test_official_vectors() {
+        // From the `TrieTests` suite of ethereum/tests; `None` deletes a key.
+        assert_eq!(root_of(&[]), hex::encode(EMPTY_ROOT));
+        assert_eq!(hex::encode(keccak(&rlp::NULL_RLP)), hex::encode(EMPTY_ROOT));
+        assert_eq!(
+            root_of(&[("doe", Some("reindeer")), ("dog", Some("puppy")), ("dogglesworth", Some("cat"))]),
+            "8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3"
+        );
+        assert_eq!(
+            root_of(&[
+                ("do", Some("verb")),
+                ("horse", Some("stallion")),
+                ("doge", Some("coin")),
+                ("dog", Some("puppy")),
+            ]),
+            "5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"
+        );
+        assert_eq!(
+            root_of(&[
+                ("do", Some("verb")),
+                ("ether", Some("wookiedoo")),
+                ("horse", Some("stallion")),
+                ("shaman", Some("horse")),
+                ("doge", Some("coin")),
+                ("ether", None),
+                ("dog", Some("puppy")),
+                ("shaman", None),
+            ]),
+            "5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"
+        );
+        assert_eq!(
+            root_of(&[("foo", Some("bar")), ("food", Some("bass"))]),
+            "17beaa1648bafa633cda809c90c04af50fc8aed3cb40d16efbddee6fdf63c4c3"
+        );
+        assert_eq!(
+            root_of(&[("be", Some("e")), ("dog", Some("puppy")), ("bed", Some("d"))]),
+            "3f67c7a47520f79faa29255d2d3c084a7a6df0453116ed7232ff10277a8be68b"
+        );
+        assert_eq!(
+            root_of(&[("test", Some("test")), ("te", Some("testy"))]),
+            "8452568af70d8d140f58d941338542f645fcca50094b20f3c3d8c3df49337928"
+        );
+    }
+
+    #[test]
+    fn test_get_insert_remove() {
+        let mut trie = Trie::default();
+        let keys: Vec<Vec<u8>> = (0u32..200).map(|i| (i * 7919).to_be_bytes()[1..].to_vec()).collect();
+        for (i, key) in keys.iter().enumerate() {
+            trie.insert(key, vec![i as u8 + 1]).unwrap();
+        }
+        let full_root = trie.root_hash();
+
+        // Reopening from the store sees the same contents.
+        let mut reopened = Trie::open(trie.store().clone(), full_root);
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(reopened.get(key).unwrap(), Some(vec![i as u8 + 1]));
+        }
+        assert_eq!(reopened.get(b"missing").unwrap(), None);
+
+        // Removing the second half gives the root of a trie that only ever held the first half.
+        for key in &keys[100..] {
+            assert!(reopened.remove(key).unwrap());
+        }
+        assert!(!reopened.remove(&keys[150]).unwrap());
+        let mut half = Trie::default();
+        for (i, key) in keys[..100].iter().enumerate() {
+            half.insert(key, vec![i as u8 + 1]).unwrap();
+        }
+        assert_eq!(reopened.root_hash(), half.root_hash());
+
+        for key in &keys[..100] {
+            reopened.insert(key, Vec::new()).unwrap();
+        }
+        assert_eq!(reopened.root_hash(), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn test_proofs() {
+        let mut trie = Trie::default();
+        for (key, value) in [("doe", "reindeer"), ("dog", "puppy"), ("dogglesworth", "cat")] {
+            trie.insert(key.as_bytes(), value.as_bytes().to_vec()).unwrap();
+        }
+        let root = trie.root_hash();
+
+        let proof = trie.prove(b"dog").unwrap();
+        assert_eq!(verify_proof(&root, b"dog", &proof).unwrap(), Some(b"puppy".to_vec()));
+
+        // Absence of a key is provable too.
+        let proof = trie.prove(b"dot").unwrap();
+        assert_eq!(verify_proof(&root, b"dot", &proof).unwrap(), None);
+
+        // A proof for one key doesn't cover a key on another path, nor another root.
+        let proof = trie.prove(b"doe").unwrap();
+        assert_eq!(verify_proof(&root, b"dogglesworth", &proof), Err(TrieError::InvalidProof));
+        assert_eq!(verify_proof(&[0u8; 32], b"doe", &proof), Err(TrieError::InvalidProof));
+    }
+
+    #[test]
+    fn test_missing_node() {
+        let trie = Trie::open(MemoryStore::default(), [1u8; 32]);
+        assert_eq!(trie.get(b"key"), Err(TrieError::MissingNode([1u8; 32])));
+    }
+
+    /// Short keys over a few byte values, so that paths share prefixes.
+    fn key() -> impl Strategy<Value = Vec<u8>> {
+        prop::collection::vec(prop::sample::select(vec![0x00u8, 0x01, 0x10, 0x11, 0xf0]), 0..5)
+    }
+
+    proptest! {
+        #[test]
+        fn prop_root_independent_of_history(
+            entries in prop::collection::btree_map(key(), 1u8.., 0..40),
+            transient in prop::collection::btree_set(key(), 0..20),
+            seed in any::<u64>(),
+        ) {
+            // Insert the entries and some transient keys in a scrambled order, then remove the transient keys.
+            let transient: Vec<Vec<u8>> = transient.into_iter().filter(|key| !entries.contains_key(key)).collect();
+            let mut inserts: Vec<(&Vec<u8>, u8)> = entries.iter().map(|(key, value)| (key, *value)).collect();
+            inserts.extend(transient.iter().map(|key| (key, 0xff)));
+            inserts.sort_by_key(|(key, _)| keccak(&[key.as_slice(), &seed.to_be_bytes()].concat()));
+            let mut trie = Trie::default();
+            for (key, value) in inserts {
+                trie.insert(key, vec![value]).unwrap();
+            }
+            // Commit first, so the removals have to load nodes from the store.
+            trie.root_hash();
+            for key in &transient {
+                prop_assert!(trie.remove(key).unwrap());
+            }
+
+            let mut expected = Trie::default();
+            for (key, value) in &entries {
+                expected.insert(key, vec![*value]).unwrap();
+            }
+            prop_assert_eq!(trie.root_hash(), expected.root_hash());
+            for (key, value) in &entries {
+                prop_assert_eq!(trie.get(key).unwrap(), Some(vec![*value]));
+            }
+            for key in &transient {
+                prop_assert_eq!(trie.get(key).unwrap(), None);
+            }
+        }
+    }
+}
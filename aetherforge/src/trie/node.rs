@@ -0,0 +1,183 @@
+use rlp::{DecoderError, Rlp, RlpStream};
+
+use super::storage::NodeStore;
+use super::{keccak, Hash};
+
+/// A trie node. Children are either held in memory or referenced by the hash
+/// of their encoding in the node store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Empty,
+    Leaf { path: Vec<u8>, value: Vec<u8> },
+    Extension { path: Vec<u8>, child: Box<Node> },
+    Branch { children: Box<[Node; 16]>, value: Option<Vec<u8>> },
+    Hash(Hash),
+}
+
+impl Node {
+    pub fn empty_branch() -> Box<[Node; 16]> {
+        Box::new(std::array::from_fn(|_| Node::Empty))
+    }
+
+    /// RLP encoding of the node. Children whose encoding is 32 bytes or longer
+    /// are written to `store` and referenced by hash; shorter ones are inlined.
+    pub fn encode<S: NodeStore>(&self, store: &mut S) -> Vec<u8> {
+        match self {
+            Node::Empty => rlp::NULL_RLP.to_vec(),
+            Node::Leaf { path, value } => {
+                let mut stream = RlpStream::new_list(2);
+                stream.append(&encode_path(path, true).as_slice()).append(&value.as_slice());
+                stream.out().to_vec()
+            }
+            Node::Extension { path, child } => {
+                let mut stream = RlpStream::new_list(2);
+                stream.append(&encode_path(path, false).as_slice());
+                append_child(&mut stream, child, store);
+                stream.out().to_vec()
+            }
+            Node::Branch { children, value } => {
+                let mut stream = RlpStream::new_list(17);
+                for child in children.iter() {
+                    append_child(&mut stream, child, store);
+                }
+                match value {
+                    Some(value) => stream.append(&value.as_slice()),
+                    None => stream.append_empty_data(),
+                };
+                stream.out().to_vec()
+            }
+            Node::Hash(hash) => rlp::encode(&hash.as_slice()).to_vec(),
+        }
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecoderError> {
+        decode_node(&Rlp::new(bytes))
+    }
+}
+
+fn append_child<S: NodeStore>(stream: &mut RlpStream, child: &Node, store: &mut S) {
+    match child {
+        Node::Empty => {
+            stream.append_empty_data();
+        }
+        Node::Hash(hash) => {
+            stream.append(&hash.as_slice());
+        }
+        _ => {
+            let encoded = child.encode(store);
+            if encoded.len() < 32 {
+                stream.append_raw(&encoded, 1);
+            } else {
+                let hash = keccak(&encoded);
+                store.insert(hash, encoded);
+                stream.append(&hash.as_slice());
+            }
+        }
+    }
+}
+
+fn decode_node(rlp: &Rlp) -> Result<Node, DecoderError> {
+    match rlp.item_count()? {
+        2 => {
+            let (path, is_leaf) = decode_path(rlp.at(0)?.data()?)?;
+            if is_leaf {
+                Ok(Node::Leaf { path, value: rlp.at(1)?.data()?.to_vec() })
+            } else {
+                Ok(Node::Extension { path, child: Box::new(decode_child(&rlp.at(1)?)?) })
+            }
+        }
+        17 => {
+            let mut children = Node::empty_branch();
+            for (i, child) in children.iter_mut().enumerate() {
+                *child = decode_child(&rlp.at(i)?)?;
+            }
+            let value = rlp.at(16)?.data()?;
+            Ok(Node::Branch { children, value: (!value.is_empty()).then(|| value.to_vec()) })
+        }
+        _ => Err(DecoderError::RlpIncorrectListLen),
+    }
+}
+
+fn decode_child(rlp: &Rlp) -> Result<Node, DecoderError> {
+    if rlp.is_list() {
+        return decode_node(rlp);
+    }
+    match rlp.data()? {
+        [] => Ok(Node::Empty),
+        hash if hash.len() == 32 => Ok(Node::Hash(hash.try_into().expect("slice is 32 bytes"))),
+        _ => Err(DecoderError::Custom("invalid child reference")),
+    }
+}
+
+/// Splits bytes into nibbles, high nibble first.
+pub fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+/// Hex-prefix encoding of a nibble path, flagging leaves and odd lengths.
+fn encode_path(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 0x20 } else { 0x00 };
+    let mut encoded = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        encoded.push(flag | 0x10 | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        encoded.push(flag);
+        nibbles
+    };
+    encoded.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    encoded
+}
+
+fn decode_path(encoded: &[u8]) -> Result<(Vec<u8>, bool), DecoderError> {
+    let (&first, rest) = encoded.split_first().ok_or(DecoderError::RlpIsTooShort)?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(DecoderError::Custom("invalid hex-prefix flag"));
+    }
+    let mut nibbles = Vec::with_capacity(2 * encoded.len());
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(to_nibbles(rest));
+    Ok((nibbles, flag & 2 == 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trie::storage::MemoryStore;
+
+    #[test]
+    fn test_hex_prefix() {
+        // Examples from the Patricia tree specification.
+        assert_eq!(encode_path(&[1, 2, 3, 4, 5], false), vec![0x11, 0x23, 0x45]);
+        assert_eq!(encode_path(&[0, 1, 2, 3, 4, 5], false), vec![0x00, 0x01, 0x23, 0x45]);
+        assert_eq!(encode_path(&[0, 0xf, 1, 0xc, 0xb, 8], true), vec![0x20, 0x0f, 0x1c, 0xb8]);
+        assert_eq!(encode_path(&[0xf, 1, 0xc, 0xb, 8], true), vec![0x3f, 0x1c, 0xb8]);
+        for (nibbles, is_leaf) in [(vec![], true), (vec![7], false), (vec![1, 2, 3, 4], true)] {
+            assert_eq!(decode_path(&encode_path(&nibbles, is_leaf)).unwrap(), (nibbles, is_leaf));
+        }
+    }
+
+    #[test]
+    fn test_node_round_trip() {
+        let mut children = Node::empty_branch();
+        children[1] = Node::Leaf { path: vec![2], value: b"inline".to_vec() };
+        children[9] = Node::Hash([0xab; 32]);
+        let branch = Node::Branch { children, value: Some(b"value".to_vec()) };
+        let extension = Node::Extension { path: vec![3, 4, 5], child: Box::new(branch) };
+        let mut store = MemoryStore::default();
+        let encoded = extension.encode(&mut store);
+
+        // The branch is too large to inline, so the extension refers to it by hash.
+        let Node::Extension { child, .. } = Node::decode(&encoded).unwrap() else {
+            panic!("expected an extension");
+        };
+        let Node::Hash(hash) = *child else {
+            panic!("expected a hash reference");
+        };
+        let Node::Extension { child: branch, .. } = extension else { unreachable!() };
+        assert_eq!(Node::decode(&store.get(&hash).unwrap()).unwrap(), *branch);
+    }
+}
@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use super::{keccak, Hash};
+
+/// Backing storage for trie nodes, keyed by the Keccak-256 hash of their
+/// RLP encoding. Nodes shorter than 32 bytes are inlined in their parent and
+/// never stored on their own.
+pub trait NodeStore {
+    fn get(&self, hash: &Hash) -> Option<Vec<u8>>;
+    fn insert(&mut self, hash: Hash, node: Vec<u8>);
+}
+
+/// In-memory node store.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStore {
+    nodes: HashMap<Hash, Vec<u8>>,
+}
+
+impl MemoryStore {
+    /// Store holding the given encoded nodes, e.g. the nodes of a proof.
+    pub fn from_nodes<T: AsRef<[u8]>>(nodes: &[T]) -> Self {
+        let nodes = nodes.iter().map(|node| (keccak(node.as_ref()), node.as_ref().to_vec())).collect();
+        Self { nodes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl NodeStore for MemoryStore {
+    fn get(&self, hash: &Hash) -> Option<Vec<u8>> {
+        self.nodes.get(hash).cloned()
+    }
+
+    fn insert(&mut self, hash: Hash, node: Vec<u8>) {
+        self.nodes.insert(hash, node);
+    }
+}
@@ -0,0 +1,236 @@
+use elliptic_curve::sec1::ToEncodedPoint;
+use k256::ecdsa::{SigningKey, VerifyingKey};
+use k256::{PublicKey, SecretKey};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Keccak256};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use super::crypto::decompress_public_key;
+
+/// Length of an account address in bytes.
+pub const ADDRESS_LEN: usize = 20;
+
+/// Custom error type for address parsing
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AddressError {
+    #[error("Address must be 20 bytes, got {0}")]
+    InvalidLength(usize),
+    #[error("Address is not valid hex")]
+    InvalidHex,
+    #[error("Address checksum mismatch")]
+    InvalidChecksum,
+    #[error("Invalid public key encoding")]
+    InvalidPublicKey,
+}
+
+/// Account identity: the last 20 bytes of the Keccak-256 hash of the
+/// uncompressed public key, without its `0x04` prefix.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Address([u8; ADDRESS_LEN]);
+
+impl Address {
+    pub const ZERO: Address = Address([0; ADDRESS_LEN]);
+
+    pub const fn new(bytes: [u8; ADDRESS_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Address with every byte set to `byte`.
+    pub const fn repeat_byte(byte: u8) -> Self {
+        Self([byte; ADDRESS_LEN])
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, AddressError> {
+        let bytes: [u8; ADDRESS_LEN] = bytes.try_into().map_err(|_| AddressError::InvalidLength(bytes.len()))?;
+        Ok(Self(bytes))
+    }
+
+    /// Derives the address of a SEC1 public key, compressed or not.
+    pub fn from_public_key(public_key: &[u8]) -> Result<Self, AddressError> {
+        let uncompressed = decompress_public_key(public_key).map_err(|_| AddressError::InvalidPublicKey)?;
+        Ok(Self::from_uncompressed(&uncompressed))
+    }
+
+    fn from_uncompressed(uncompressed: &[u8]) -> Self {
+        let hash = Keccak256::digest(&uncompressed[1..]);
+        Self(hash[12..].try_into().expect("Keccak-256 output is 32 bytes"))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; ADDRESS_LEN] {
+        &self.0
+    }
+
+    /// EIP-55 mixed-case checksum encoding, `0x`-prefixed.
+    pub fn to_checksum(&self) -> String {
+        let lower = hex::encode(self.0);
+        let hash = Keccak256::digest(lower.as_bytes());
+        let mut checksummed = String::with_capacity(2 + 2 * ADDRESS_LEN);
+        checksummed.push_str("0x");
+        for (i, c) in lower.chars().enumerate() {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            checksummed.push(if nibble >= 8 { c.to_ascii_uppercase() } else { c });
+        }
+        checksummed
+    }
+}
+
+impl From<[u8; ADDRESS_LEN]> for Address {
+    fn from(bytes: [u8; ADDRESS_LEN]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&PublicKey> for Address {
+    fn from(public_key: &PublicKey) -> Self {
+        Self::from_uncompressed(public_key.to_encoded_point(false).as_bytes())
+    }
+}
+
+impl From<&VerifyingKey> for Address {
+    fn from(verifying_key: &VerifyingKey) -> Self {
+        Self::from_uncompressed(verifying_key.to_encoded_point(false).as_bytes())
+    }
+}
+
+impl From<&SecretKey> for Address {
+    fn from(secret_key: &SecretKey) -> Self {
+        Self::from(&secret_key.public_key())
+    }
+}
+
+impl From<&SigningKey> for Address {
+    fn from(signing_key: &SigningKey) -> Self {
+        Self::from(signing_key.verifying_key())
+    }
+}
+
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+/// Parses a hex address with or without `0x`. All-lowercase and
+/// all-uppercase inputs carry no checksum; mixed case must match EIP-55.
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+        if digits.len() != 2 * ADDRESS_LEN {
+            return Err(AddressError::InvalidLength(digits.len() / 2));
+        }
+        let bytes = hex::decode(digits).map_err(|_| AddressError::InvalidHex)?;
+        let address = Self::from_slice(&bytes)?;
+        let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+        let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+        if has_lower && has_upper && address.to_checksum()[2..] != *digits {
+            return Err(AddressError::InvalidChecksum);
+        }
+        Ok(address)
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_checksum())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Fixed accounts for tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::Address;
+
+    pub const ALICE: Address = Address::repeat_byte(0x0a);
+    pub const BOB: Address = Address::repeat_byte(0x0b);
+    pub const CHARLIE: Address = Address::repeat_byte(0x0c);
+    pub const DAVE: Address = Address::repeat_byte(0x0d);
+    pub const ERIN: Address = Address::repeat_byte(0x0e);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto::{compress_public_key, generate_keypair};
+
+    const EIP55_VECTORS: [&str; 4] = [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn test_eip55_checksum() {
+        for vector in EIP55_VECTORS {
+            let address: Address = vector.parse().unwrap();
+            assert_eq!(address.to_checksum(), vector);
+            assert_eq!(address.to_string(), vector);
+
+            // Single-case input carries no checksum and is always accepted.
+            assert_eq!(vector.to_lowercase().parse::<Address>().unwrap(), address);
+            assert_eq!(vector[2..].to_uppercase().parse::<Address>().unwrap(), address);
+        }
+
+        // Flipping the case of one letter breaks the checksum.
+        let broken = EIP55_VECTORS[0].replacen('a', "A", 1);
+        assert_eq!(broken.parse::<Address>(), Err(AddressError::InvalidChecksum));
+        assert_eq!("0x1234".parse::<Address>(), Err(AddressError::InvalidLength(2)));
+        assert_eq!(
+            "0xzzAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse::<Address>(),
+            Err(AddressError::InvalidHex)
+        );
+    }
+
+    #[test]
+    fn test_address_from_keys() {
+        let secret_bytes = hex::decode("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap();
+        let secret_key = SecretKey::from_slice(&secret_bytes).unwrap();
+        let expected: Address = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23".parse().unwrap();
+        assert_eq!(Address::from(&secret_key), expected);
+        assert_eq!(Address::from(&secret_key.public_key()), expected);
+        assert_eq!(Address::from(&SigningKey::from(&secret_key)), expected);
+
+        // Both SEC1 encodings of the key from `generate_keypair` give the same address.
+        let (secret_key, public_key) = generate_keypair();
+        let address = Address::from_public_key(&public_key).unwrap();
+        assert_eq!(Address::from_public_key(&compress_public_key(&public_key).unwrap()).unwrap(), address);
+        assert_eq!(Address::from(&secret_key), address);
+        assert_eq!(Address::from_public_key(&[0u8; 33]), Err(AddressError::InvalidPublicKey));
+    }
+
+    #[test]
+    fn test_serde_as_checksummed_string() {
+        let address: Address = EIP55_VECTORS[1].parse().unwrap();
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, format!("\"{}\"", EIP55_VECTORS[1]));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+
+        // Usable as a JSON object key.
+        let map = std::collections::BTreeMap::from([(address, 1u64)]);
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(serde_json::from_str::<std::collections::BTreeMap<Address, u64>>(&json).unwrap(), map);
+    }
+}
@@ -1,12 +1,316 @@
 use elliptic_curve::sec1::ToEncodedPoint;
-use k256::{Secp256k1, SecretKey};
+use k256::ecdsa::signature::hazmat::{PrehashSigner, PrehashVerifier};
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+use k256::{PublicKey, SecretKey};
 use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use super::address::Address;
+use super::hashing::eip191_hash_message;
+use super::mldsa::MlDsa65;
+
+/// Domain tag for seeded key derivation.
+const KEYPAIR_SEED_TAG: &[u8] = b"aetherforge/keypair-seed";
+
+/// Length of a message digest accepted for signing.
+pub const DIGEST_LEN: usize = 32;
+
+/// Length of a compact `r || s` signature.
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Length of a recoverable `r || s || v` signature.
+pub const RECOVERABLE_SIGNATURE_LEN: usize = 65;
+
+/// Custom error type for signature operations
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CryptoError {
+    #[error("Digest must be 32 bytes, got {0}")]
+    InvalidDigestLength(usize),
+    #[error("Invalid public key encoding")]
+    InvalidPublicKey,
+    #[error("Invalid signature encoding")]
+    InvalidSignature,
+    #[error("Invalid recovery id {0}")]
+    InvalidRecoveryId(u8),
+    #[error("Public key recovery failed")]
+    RecoveryFailed,
+    #[error("Missing signature scheme tag")]
+    MissingSchemeTag,
+    #[error("Unknown signature scheme tag {0}")]
+    UnknownScheme(u8),
+}
+
+/// Signature algorithm of a key or signature. Tagged encodings start with
+/// this byte, so a new scheme can be rolled out without changing the format
+/// of the structures that carry keys and signatures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum SchemeTag {
+    /// ECDSA over secp256k1 with compressed SEC1 public keys.
+    #[default]
+    Secp256k1 = 0x00,
+    /// ML-DSA-65 (FIPS 204, Dilithium), a post-quantum lattice scheme.
+    MlDsa65 = 0x01,
+}
+
+impl TryFrom<u8> for SchemeTag {
+    type Error = CryptoError;
+
+    fn try_from(tag: u8) -> Result<Self, Self::Error> {
+        match tag {
+            0x00 => Ok(Self::Secp256k1),
+            0x01 => Ok(Self::MlDsa65),
+            tag => Err(CryptoError::UnknownScheme(tag)),
+        }
+    }
+}
+
+/// A signature algorithm validator keys can use. Schemes sign 32-byte
+/// consensus digests, and keys and signatures travel as raw bytes.
+pub trait SignatureScheme {
+    const TAG: SchemeTag;
+    type SecretKey;
+
+    /// Generates a new key pair, returning the encoded public key.
+    fn generate_keypair() -> (Self::SecretKey, Vec<u8>);
+    fn public_key(secret_key: &Self::SecretKey) -> Vec<u8>;
+    fn sign(secret_key: &Self::SecretKey, digest: &[u8]) -> Result<Vec<u8>, CryptoError>;
+    fn verify(public_key: &[u8], digest: &[u8], signature: &[u8]) -> bool;
+}
+
+/// Low-S ECDSA over secp256k1, the scheme validators use today.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Secp256k1;
+
+impl SignatureScheme for Secp256k1 {
+    const TAG: SchemeTag = SchemeTag::Secp256k1;
+    type SecretKey = SecretKey;
+
+    fn generate_keypair() -> (SecretKey, Vec<u8>) {
+        let (secret_key, _) = generate_keypair();
+        let public_key = public_key_of(&secret_key, true);
+        (secret_key, public_key)
+    }
+
+    fn public_key(secret_key: &SecretKey) -> Vec<u8> {
+        public_key_of(secret_key, true)
+    }
+
+    fn sign(secret_key: &SecretKey, digest: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        sign_digest(secret_key, digest)
+    }
+
+    fn verify(public_key: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+        verify_digest(public_key, digest, signature)
+    }
+}
+
+/// Verifies `signature` over `digest` with the scheme named by `scheme`.
+pub fn verify_with_scheme(scheme: SchemeTag, public_key: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+    match scheme {
+        SchemeTag::Secp256k1 => Secp256k1::verify(public_key, digest, signature),
+        SchemeTag::MlDsa65 => MlDsa65::verify(public_key, digest, signature),
+    }
+}
+
+/// Prefixes an encoded public key or signature with its scheme tag.
+pub fn encode_tagged(scheme: SchemeTag, bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(1 + bytes.len());
+    encoded.push(scheme as u8);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+/// Splits a tagged encoding into its scheme and payload.
+pub fn decode_tagged(encoded: &[u8]) -> Result<(SchemeTag, &[u8]), CryptoError> {
+    let (&tag, bytes) = encoded.split_first().ok_or(CryptoError::MissingSchemeTag)?;
+    Ok((SchemeTag::try_from(tag)?, bytes))
+}
+
+/// Verifies a tagged signature against a tagged public key. Both must name
+/// the same scheme, so a signature can't be replayed under another algorithm.
+pub fn verify_tagged(public_key: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+    match (decode_tagged(public_key), decode_tagged(signature)) {
+        (Ok((key_scheme, public_key)), Ok((signature_scheme, signature))) if key_scheme == signature_scheme => {
+            verify_with_scheme(key_scheme, public_key, digest, signature)
+        }
+        _ => false,
+    }
+}
 
 /// Generates a new ECDSA key pair.
 pub fn generate_keypair() -> (SecretKey, Vec<u8>) {
     let secret_key = SecretKey::random(&mut OsRng);
-    let public_key = secret_key.public_key().to_encoded_point(false);
-    (secret_key, public_key.as_bytes().to_vec())
+    let public_key = public_key_of(&secret_key, false);
+    (secret_key, public_key)
+}
+
+/// Derives an ECDSA key pair from `seed`, reproducibly across runs and
+/// platforms. Anyone who knows the seed has the key, so this is only for
+/// test fixtures and devnets.
+pub fn keypair_from_seed(seed: &[u8]) -> (SecretKey, Vec<u8>) {
+    // Retry with a counter in the (negligible) case the hash isn't a valid scalar.
+    let secret_key = (0u32..)
+        .find_map(|counter| {
+            let candidate = Sha256::new()
+                .chain_update(KEYPAIR_SEED_TAG)
+                .chain_update((seed.len() as u64).to_be_bytes())
+                .chain_update(seed)
+                .chain_update(counter.to_be_bytes())
+                .finalize();
+            SecretKey::from_slice(&candidate).ok()
+        })
+        .expect("a valid scalar is found within a few attempts");
+    let public_key = public_key_of(&secret_key, false);
+    (secret_key, public_key)
+}
+
+/// SEC1 encoding of the secret key's public key.
+pub fn public_key_of(secret_key: &SecretKey, compressed: bool) -> Vec<u8> {
+    secret_key.public_key().to_encoded_point(compressed).as_bytes().to_vec()
+}
+
+/// Re-encodes a SEC1 public key in compressed (33-byte) form.
+pub fn compress_public_key(public_key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let public_key = PublicKey::from_sec1_bytes(public_key).map_err(|_| CryptoError::InvalidPublicKey)?;
+    Ok(public_key.to_encoded_point(true).as_bytes().to_vec())
+}
+
+/// Re-encodes a SEC1 public key in uncompressed (65-byte) form.
+pub fn decompress_public_key(public_key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let public_key = PublicKey::from_sec1_bytes(public_key).map_err(|_| CryptoError::InvalidPublicKey)?;
+    Ok(public_key.to_encoded_point(false).as_bytes().to_vec())
+}
+
+/// Signs a 32-byte digest, returning a low-S `r || s` signature.
+pub fn sign_digest(secret_key: &SecretKey, digest: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    check_digest(digest)?;
+    let signature: Signature = SigningKey::from(secret_key)
+        .sign_prehash(digest)
+        .map_err(|_| CryptoError::InvalidSignature)?;
+    Ok(signature.normalize_s().unwrap_or(signature).to_bytes().to_vec())
+}
+
+/// Verifies an `r || s` signature over a 32-byte digest against a SEC1
+/// public key. High-S signatures are rejected to rule out malleability.
+pub fn verify_digest(public_key: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+    if check_digest(digest).is_err() {
+        return false;
+    }
+    let Ok(verifying_key) = VerifyingKey::from_sec1_bytes(public_key) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return false;
+    };
+    verifying_key.verify_prehash(digest, &signature).is_ok()
+}
+
+/// Whether an `r || s` signature has its `s` in the lower half of the curve order.
+pub fn is_low_s(signature: &[u8]) -> Result<bool, CryptoError> {
+    let signature = Signature::from_slice(signature).map_err(|_| CryptoError::InvalidSignature)?;
+    Ok(signature.normalize_s().is_none())
+}
+
+/// Replaces `s` with `n - s` if it is in the upper half of the curve order.
+pub fn normalize_s(signature: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let signature = Signature::from_slice(signature).map_err(|_| CryptoError::InvalidSignature)?;
+    Ok(signature.normalize_s().unwrap_or(signature).to_bytes().to_vec())
+}
+
+/// A low-S ECDSA signature with the recovery id of the signing key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoverableSignature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    /// Recovery id (0 or 1; 2 and 3 only occur for `r` overflowing the order).
+    pub v: u8,
+}
+
+impl RecoverableSignature {
+    /// Encodes the signature as `r || s || v`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(RECOVERABLE_SIGNATURE_LEN);
+        bytes.extend_from_slice(&self.r);
+        bytes.extend_from_slice(&self.s);
+        bytes.push(self.v);
+        bytes
+    }
+
+    /// Decodes `r || s || v`, accepting the legacy `v` values 27 and 28.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != RECOVERABLE_SIGNATURE_LEN {
+            return Err(CryptoError::InvalidSignature);
+        }
+        let v = match bytes[64] {
+            v @ 0..=3 => v,
+            v @ (27 | 28) => v - 27,
+            v => return Err(CryptoError::InvalidRecoveryId(v)),
+        };
+        Ok(Self {
+            r: bytes[..32].try_into().unwrap(),
+            s: bytes[32..64].try_into().unwrap(),
+            v,
+        })
+    }
+
+    /// Encodes the signature as `r || s || v` with `v` offset by 27, the
+    /// form Solidity's `ecrecover` expects.
+    pub fn to_legacy_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.to_bytes();
+        bytes[64] += 27;
+        bytes
+    }
+
+    /// The signature without its recovery id.
+    pub fn to_compact(&self) -> Vec<u8> {
+        let mut bytes = self.r.to_vec();
+        bytes.extend_from_slice(&self.s);
+        bytes
+    }
+}
+
+/// Signs a 32-byte digest, returning a low-S signature the public key can be recovered from.
+pub fn sign_recoverable(secret_key: &SecretKey, digest: &[u8]) -> Result<RecoverableSignature, CryptoError> {
+    check_digest(digest)?;
+    let (signature, recovery_id) = SigningKey::from(secret_key)
+        .sign_prehash_recoverable(digest)
+        .map_err(|_| CryptoError::InvalidSignature)?;
+    let (r, s) = signature.split_bytes();
+    Ok(RecoverableSignature { r: r.into(), s: s.into(), v: recovery_id.to_byte() })
+}
+
+/// Recovers the uncompressed SEC1 public key that produced `signature` over `digest`.
+pub fn recover_public_key(digest: &[u8], signature: &RecoverableSignature) -> Result<Vec<u8>, CryptoError> {
+    check_digest(digest)?;
+    let recovery_id = RecoveryId::from_byte(signature.v).ok_or(CryptoError::InvalidRecoveryId(signature.v))?;
+    let compact = Signature::from_slice(&signature.to_compact()).map_err(|_| CryptoError::InvalidSignature)?;
+    if compact.normalize_s().is_some() {
+        return Err(CryptoError::InvalidSignature);
+    }
+    let verifying_key = VerifyingKey::recover_from_prehash(digest, &compact, recovery_id)
+        .map_err(|_| CryptoError::RecoveryFailed)?;
+    Ok(verifying_key.to_encoded_point(false).as_bytes().to_vec())
+}
+
+/// Signs `message` as an EIP-191 personal message (`personal_sign`).
+pub fn sign_message(secret_key: &SecretKey, message: &[u8]) -> Result<RecoverableSignature, CryptoError> {
+    sign_recoverable(secret_key, &eip191_hash_message(message))
+}
+
+/// Address that signed `message` as an EIP-191 personal message.
+pub fn recover_message_signer(message: &[u8], signature: &RecoverableSignature) -> Result<Address, CryptoError> {
+    let public_key = recover_public_key(&eip191_hash_message(message), signature)?;
+    Address::from_public_key(&public_key).map_err(|_| CryptoError::RecoveryFailed)
+}
+
+pub(crate) fn check_digest(digest: &[u8]) -> Result<(), CryptoError> {
+    if digest.len() != DIGEST_LEN {
+        return Err(CryptoError::InvalidDigestLength(digest.len()));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -14,13 +318,28 @@ mod tests {
     use super::*;
     use k256::PublicKey;
     use elliptic_curve::sec1::FromEncodedPoint;
+    use crate::utils::hashing::{sha3_256_hash, Domain};
+
+    #[test]
+    fn test_keypair_from_seed() {
+        let (secret_key, public_key) = keypair_from_seed(b"validator-0");
+        assert_eq!(keypair_from_seed(b"validator-0"), (secret_key.clone(), public_key.clone()));
+        assert_eq!(public_key, public_key_of(&secret_key, false));
+        assert_ne!(keypair_from_seed(b"validator-1").0, secret_key);
+
+        // Pinned, so fixtures and genesis files built from seeds stay valid.
+        assert_eq!(
+            hex::encode(secret_key.to_bytes()),
+            "3e831560d5f05aa102dc2c2184799d259812a0422ae461589d3dad49a1d75817"
+        );
+    }
 
     #[test]
     fn test_generate_keypair() {
         let (secret_key, public_key_bytes) = generate_keypair();
 
         // Check that the secret key is valid.
-        assert!(secret_key.verify_public_key().is_ok());
+        assert!(SecretKey::from_bytes(&secret_key.to_bytes()).is_ok());
 
         // Check that the public key bytes are the correct length (uncompressed).
         assert_eq!(public_key_bytes.len(), 65);
@@ -30,10 +349,131 @@ mod tests {
             &k256::EncodedPoint::from_bytes(public_key_bytes.clone()).unwrap()
         );
 
-        assert!(public_key_result.is_ok());
+        assert!(bool::from(public_key_result.is_some()));
 
         // Check that the derived public key matches the one from the secret key.
         let derived_public_key = secret_key.public_key();
         assert_eq!(derived_public_key, public_key_result.unwrap());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_public_key_conversions() {
+        let (_, uncompressed) = generate_keypair();
+        let compressed = compress_public_key(&uncompressed).unwrap();
+        assert_eq!(compressed.len(), 33);
+        assert_eq!(decompress_public_key(&compressed).unwrap(), uncompressed);
+        assert_eq!(compress_public_key(&compressed).unwrap(), compressed);
+        assert_eq!(compress_public_key(&[0u8; 33]), Err(CryptoError::InvalidPublicKey));
+
+        let (secret_key, public_key) = generate_keypair();
+        assert_eq!(public_key_of(&secret_key, false), public_key);
+        assert_eq!(public_key_of(&secret_key, true), compress_public_key(&public_key).unwrap());
+    }
+
+    #[test]
+    fn test_sign_and_verify_digest() {
+        let (secret_key, public_key) = generate_keypair();
+        let digest = sha3_256_hash(Domain::Block, b"block");
+        let signature = sign_digest(&secret_key, &digest).unwrap();
+        assert_eq!(signature.len(), SIGNATURE_LEN);
+        assert!(is_low_s(&signature).unwrap());
+
+        // Either key encoding verifies.
+        assert!(verify_digest(&public_key, &digest, &signature));
+        assert!(verify_digest(&compress_public_key(&public_key).unwrap(), &digest, &signature));
+
+        // Wrong digest, key or signature fail.
+        let (_, other_public_key) = generate_keypair();
+        assert!(!verify_digest(&public_key, &sha3_256_hash(Domain::Block, b"other"), &signature));
+        assert!(!verify_digest(&other_public_key, &digest, &signature));
+        assert!(!verify_digest(&public_key, &digest, &signature[1..]));
+
+        // Digests must be exactly 32 bytes.
+        assert_eq!(sign_digest(&secret_key, b"short"), Err(CryptoError::InvalidDigestLength(5)));
+        assert!(!verify_digest(&public_key, &digest[..31], &signature));
+    }
+
+    #[test]
+    fn test_low_s_normalization() {
+        let (secret_key, public_key) = generate_keypair();
+        let digest = sha3_256_hash(Domain::Block, b"malleable");
+        let signature = Signature::from_slice(&sign_digest(&secret_key, &digest).unwrap()).unwrap();
+
+        // Flip s to n - s to get the high-S twin of the signature.
+        let (r, s) = signature.split_scalars();
+        let high = Signature::from_scalars(r.to_bytes(), (-*s).to_bytes()).unwrap().to_bytes().to_vec();
+        assert!(!is_low_s(&high).unwrap());
+        assert!(!verify_digest(&public_key, &digest, &high));
+
+        let normalized = normalize_s(&high).unwrap();
+        assert_eq!(normalized, signature.to_bytes().to_vec());
+        assert!(verify_digest(&public_key, &digest, &normalized));
+    }
+
+    #[test]
+    fn test_recover_public_key() {
+        let (secret_key, public_key) = generate_keypair();
+        let digest = sha3_256_hash(Domain::Block, b"transaction");
+        let signature = sign_recoverable(&secret_key, &digest).unwrap();
+        assert!(signature.v <= 1);
+        assert!(is_low_s(&signature.to_compact()).unwrap());
+        assert!(verify_digest(&public_key, &digest, &signature.to_compact()));
+        assert_eq!(recover_public_key(&digest, &signature).unwrap(), public_key);
+
+        // Round trip through bytes, including legacy v values.
+        let mut bytes = signature.to_bytes();
+        assert_eq!(RecoverableSignature::from_bytes(&bytes).unwrap(), signature);
+        bytes[64] += 27;
+        assert_eq!(RecoverableSignature::from_bytes(&bytes).unwrap(), signature);
+        bytes[64] = 35;
+        assert_eq!(RecoverableSignature::from_bytes(&bytes), Err(CryptoError::InvalidRecoveryId(35)));
+
+        // A different digest recovers a different key; a flipped v recovers another.
+        assert_ne!(recover_public_key(&sha3_256_hash(Domain::Block, b"other"), &signature).unwrap(), public_key);
+        let flipped = RecoverableSignature { v: signature.v ^ 1, ..signature };
+        assert_ne!(recover_public_key(&digest, &flipped).ok(), Some(public_key));
+    }
+
+    #[test]
+    fn test_personal_message_signing() {
+        let secret_key = SecretKey::from_slice(&[0x01; 32]).unwrap();
+        let signer = Address::from(&secret_key);
+        let signature = sign_message(&secret_key, b"hello world").unwrap();
+        assert_eq!(recover_message_signer(b"hello world", &signature).unwrap(), signer);
+        assert_ne!(recover_message_signer(b"hello world!", &signature).ok(), Some(signer));
+
+        // The legacy encoding is accepted back and carries v = 27 or 28.
+        let legacy = signature.to_legacy_bytes();
+        assert!(legacy[64] == 27 || legacy[64] == 28);
+        assert_eq!(RecoverableSignature::from_bytes(&legacy).unwrap(), signature);
+    }
+
+    #[test]
+    fn test_scheme_tags() {
+        for scheme in [SchemeTag::Secp256k1, SchemeTag::MlDsa65] {
+            assert_eq!(SchemeTag::try_from(scheme as u8), Ok(scheme));
+            let encoded = encode_tagged(scheme, b"payload");
+            assert_eq!(decode_tagged(&encoded), Ok((scheme, &b"payload"[..])));
+        }
+        assert_eq!(decode_tagged(&[0x7f, 1, 2]), Err(CryptoError::UnknownScheme(0x7f)));
+        assert_eq!(decode_tagged(&[]), Err(CryptoError::MissingSchemeTag));
+        // Untagged data defaults to secp256k1 so existing encodings stay valid.
+        assert_eq!(SchemeTag::default(), SchemeTag::Secp256k1);
+    }
+
+    #[test]
+    fn test_verify_tagged() {
+        let digest = sha3_256_hash(Domain::Block, b"tagged");
+        let (secret_key, public_key) = Secp256k1::generate_keypair();
+        let signature = Secp256k1::sign(&secret_key, &digest).unwrap();
+        let tagged_key = encode_tagged(Secp256k1::TAG, &public_key);
+        let tagged_signature = encode_tagged(Secp256k1::TAG, &signature);
+        assert!(verify_tagged(&tagged_key, &digest, &tagged_signature));
+        assert!(!verify_tagged(&tagged_key, &sha3_256_hash(Domain::Block, b"other"), &tagged_signature));
+
+        // Mismatched or missing tags are rejected even if the payload is valid.
+        assert!(!verify_tagged(&tagged_key, &digest, &encode_tagged(SchemeTag::MlDsa65, &signature)));
+        assert!(!verify_tagged(&public_key, &digest, &tagged_signature));
+        assert!(!verify_tagged(&tagged_key, &digest, &[]));
+    }
+}
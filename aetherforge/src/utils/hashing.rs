@@ -1,12 +1,142 @@
-use sha3::{Digest, Sha3_256};
+use sha3::{Digest, Keccak256, Sha3_256};
 
-/// Generates a SHA3-256 hash of the input data.
-pub fn sha3_256_hash(input: &[u8]) -> Vec<u8> {
-    let mut hasher = Sha3_256::new();
+pub mod merkle;
+
+/// Prefix of EIP-191 version `0x45` ("personal_sign") messages.
+pub const EIP191_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n";
+
+/// What a consensus hash commits to. Every consensus hash starts with its
+/// domain's tag, so equal bytes hashed for different purposes never collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Domain {
+    /// Final proof-of-work hash of a forged block.
+    Block,
+    /// Hash identifying a block by its header.
+    BlockHeader,
+    /// Header hash without the proof of work, which the work is done over.
+    HeaderSeal,
+    /// Commitment to the Merkle root of a block's transactions.
+    TransactionsRoot,
+    /// Hash identifying a signed transaction.
+    Transaction,
+    /// Digest a sender signs to authorize a transaction.
+    TransactionSignature,
+    /// Block hash attested to by PoS finalization.
+    FinalizedBlock,
+    /// Digest signed by a validator to finalize a block.
+    Finalization,
+    /// Digest signed by a validator for a Casper FFG checkpoint vote.
+    CheckpointVote,
+    /// Digest signed by a validator for a BFT prevote or precommit.
+    BftVote,
+    /// Commitment to the difficulty cost parameters.
+    CostParams,
+    /// Expansion of a seed into the proof-of-work matrix.
+    MatrixSeed,
+    /// Stake-weighted validator selection draw.
+    ValidatorSelection,
+    /// VRF input of the slot lottery.
+    LotteryInput,
+    /// Per-purpose seed derived from an epoch's randomness.
+    EpochSeed,
+    ImagePuzzle,
+    AudioPuzzle,
+    CodingPuzzle,
+}
+
+impl Domain {
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Domain::Block => "aetherforge/block",
+            Domain::BlockHeader => "aetherforge/block-header",
+            Domain::HeaderSeal => "aetherforge/header-seal",
+            Domain::TransactionsRoot => "aetherforge/transactions-root",
+            Domain::Transaction => "aetherforge/transaction",
+            Domain::TransactionSignature => "aetherforge/transaction-signature",
+            Domain::FinalizedBlock => "aetherforge/finalized-block",
+            Domain::Finalization => "aetherforge/finalization",
+            Domain::CheckpointVote => "aetherforge/checkpoint-vote",
+            Domain::BftVote => "aetherforge/bft-vote",
+            Domain::CostParams => "aetherforge/cost-params",
+            Domain::MatrixSeed => "aetherforge/matrix-seed",
+            Domain::ValidatorSelection => "aetherforge/validator-selection",
+            Domain::LotteryInput => "aetherforge/lottery-input",
+            Domain::EpochSeed => "aetherforge/epoch-seed",
+            Domain::ImagePuzzle => "aetherforge/puzzle/image",
+            Domain::AudioPuzzle => "aetherforge/puzzle/audio",
+            Domain::CodingPuzzle => "aetherforge/puzzle/coding",
+        }
+    }
+}
+
+/// SHA3-256 hasher for everything consensus depends on.
+///
+/// The domain tag and every field are prefixed with their length as a
+/// big-endian `u64`, so a digest pins down both the purpose and the exact
+/// field boundaries of its input.
+#[derive(Debug, Clone)]
+pub struct ConsensusHasher {
+    hasher: Sha3_256,
+}
+
+impl ConsensusHasher {
+    pub fn new(domain: Domain) -> Self {
+        let mut hasher = Self { hasher: Sha3_256::new() };
+        hasher.field(domain.tag().as_bytes());
+        hasher
+    }
+
+    /// Hashes `fields` under `domain` in one go.
+    pub fn digest(domain: Domain, fields: &[&[u8]]) -> Vec<u8> {
+        let mut hasher = Self::new(domain);
+        for field in fields {
+            hasher.field(field);
+        }
+        hasher.finish()
+    }
+
+    pub fn field(&mut self, bytes: &[u8]) -> &mut Self {
+        self.hasher.update((bytes.len() as u64).to_be_bytes());
+        self.hasher.update(bytes);
+        self
+    }
+
+    /// Adds an integer field in its 8-byte big-endian encoding.
+    pub fn field_u64(&mut self, value: u64) -> &mut Self {
+        self.field(&value.to_be_bytes())
+    }
+
+    /// The 32-byte digest of everything added so far.
+    pub fn finish(&self) -> Vec<u8> {
+        self.hasher.clone().finalize().to_vec()
+    }
+}
+
+/// SHA3-256 hash of a single field under `domain`.
+pub fn sha3_256_hash(domain: Domain, input: &[u8]) -> Vec<u8> {
+    ConsensusHasher::digest(domain, &[input])
+}
+
+/// Generates a Keccak-256 hash of the input data, as used by Ethereum.
+///
+/// Keccak-256 uses the original Keccak padding and differs from NIST
+/// SHA3-256, so hashes checked on-chain must use this one.
+pub fn keccak256_hash(input: &[u8]) -> Vec<u8> {
+    let mut hasher = Keccak256::new();
     hasher.update(input);
     hasher.finalize().to_vec()
 }
 
+/// EIP-191 personal message hash: Keccak-256 of the prefix, the decimal
+/// message length and the message, matching `eth_sign` and `personal_sign`.
+pub fn eip191_hash_message(message: &[u8]) -> Vec<u8> {
+    let mut hasher = Keccak256::new();
+    hasher.update(EIP191_PREFIX);
+    hasher.update(message.len().to_string().as_bytes());
+    hasher.update(message);
+    hasher.finalize().to_vec()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -14,23 +144,75 @@ mod tests {
     #[test]
     fn test_sha3_256_hash() {
         let input = b"hello world";
-        let hash = sha3_256_hash(input);
+        let hash = sha3_256_hash(Domain::Block, input);
 
         // Check the hash length.
         assert_eq!(hash.len(), 32);
 
         // Verify hash consistency.
-        let hash2 = sha3_256_hash(input);
+        let hash2 = sha3_256_hash(Domain::Block, input);
         assert_eq!(hash, hash2);
 
         // Verify different inputs produce different hashes.
         let input2 = b"hello world!";
-        let hash3 = sha3_256_hash(input2);
+        let hash3 = sha3_256_hash(Domain::Block, input2);
         assert_ne!(hash, hash3);
 
         // Test with empty input
         let empty_input = b"";
-        let empty_hash = sha3_256_hash(empty_input);
+        let empty_hash = sha3_256_hash(Domain::Block, empty_input);
         assert_eq!(empty_hash.len(), 32);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_consensus_hasher_separates_domains_and_fields() {
+        // The same bytes hash differently in different domains.
+        assert_ne!(sha3_256_hash(Domain::Block, b"data"), sha3_256_hash(Domain::ImagePuzzle, b"data"));
+        assert_ne!(sha3_256_hash(Domain::Block, b"data"), Sha3_256::digest(b"data").to_vec());
+
+        // Moving a field boundary changes the digest.
+        assert_ne!(
+            ConsensusHasher::digest(Domain::Block, &[b"ab", b"c"]),
+            ConsensusHasher::digest(Domain::Block, &[b"a", b"bc"])
+        );
+        assert_ne!(
+            ConsensusHasher::digest(Domain::Block, &[b"abc", b""]),
+            ConsensusHasher::digest(Domain::Block, &[b"abc"])
+        );
+
+        // Incremental and one-shot hashing agree.
+        let mut hasher = ConsensusHasher::new(Domain::Finalization);
+        hasher.field_u64(7).field(b"block");
+        assert_eq!(hasher.finish(), ConsensusHasher::digest(Domain::Finalization, &[&7u64.to_be_bytes(), b"block"]));
+
+        // Layout: length-prefixed tag followed by length-prefixed fields.
+        let tag = Domain::Block.tag().as_bytes();
+        let mut expected = Sha3_256::new();
+        expected.update((tag.len() as u64).to_be_bytes());
+        expected.update(tag);
+        expected.update(4u64.to_be_bytes());
+        expected.update(b"data");
+        assert_eq!(sha3_256_hash(Domain::Block, b"data"), expected.finalize().to_vec());
+    }
+
+    #[test]
+    fn test_keccak256_hash() {
+        // Keccak-256 and SHA3-256 disagree even on empty input.
+        assert_eq!(
+            hex::encode(keccak256_hash(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex::encode(Sha3_256::digest(b"")),
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+        );
+    }
+
+    #[test]
+    fn test_eip191_hash_message() {
+        assert_eq!(
+            hex::encode(eip191_hash_message(b"Hello World")),
+            "a1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+        );
+    }
+}
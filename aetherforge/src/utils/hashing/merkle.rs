@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use sha3::digest::{typenum::U32, OutputSizeUser};
+use sha3::{Digest, Sha3_256};
+use std::marker::PhantomData;
+use thiserror::Error;
+
+/// A 32-byte Merkle node.
+pub type Hash = [u8; 32];
+
+/// Prefix of leaf hashes, so a leaf can never be passed off as an inner node.
+pub const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix of inner node hashes.
+pub const NODE_PREFIX: u8 = 0x01;
+
+/// Custom error type for Merkle proof generation
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MerkleError {
+    #[error("Leaf index {0} out of range")]
+    IndexOutOfRange(usize),
+    #[error("No leaves to prove")]
+    NoLeaves,
+}
+
+/// Hash functions a tree can be built with: SHA3-256 or Keccak-256.
+pub trait MerkleHasher: Digest + OutputSizeUser<OutputSize = U32> {}
+
+impl<H: Digest + OutputSizeUser<OutputSize = U32>> MerkleHasher for H {}
+
+/// Hash of a leaf's data.
+pub fn hash_leaf<H: MerkleHasher>(data: &[u8]) -> Hash {
+    H::new().chain_update([LEAF_PREFIX]).chain_update(data).finalize().into()
+}
+
+/// Hash of an inner node.
+pub fn hash_node<H: MerkleHasher>(left: &Hash, right: &Hash) -> Hash {
+    H::new().chain_update([NODE_PREFIX]).chain_update(left).chain_update(right).finalize().into()
+}
+
+/// Root of an empty tree: the hash of no input.
+pub fn empty_root<H: MerkleHasher>() -> Hash {
+    H::digest([]).into()
+}
+
+/// Root over `leaves` without keeping the tree around.
+pub fn merkle_root<H: MerkleHasher, T: AsRef<[u8]>>(leaves: &[T]) -> Hash {
+    MerkleTree::<H>::new(leaves).root()
+}
+
+/// A binary Merkle tree over SHA3-256 (the default) or Keccak-256.
+///
+/// A node without a sibling is promoted to the next level unchanged rather
+/// than paired with itself, so no two leaf lists share a root.
+#[derive(Debug, Clone)]
+pub struct MerkleTree<H: MerkleHasher = Sha3_256> {
+    /// Node hashes level by level, from the leaves up to the root.
+    levels: Vec<Vec<Hash>>,
+    hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T]) -> Self {
+        let mut levels = vec![leaves.iter().map(|leaf| hash_leaf::<H>(leaf.as_ref())).collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let level = &levels[levels.len() - 1];
+            let parents = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node::<H>(left, right),
+                    [single] => *single,
+                    _ => unreachable!("chunks of two"),
+                })
+                .collect();
+            levels.push(parents);
+        }
+        Self { levels, hasher: PhantomData }
+    }
+
+    pub fn root(&self) -> Hash {
+        self.levels[self.levels.len() - 1].first().copied().unwrap_or_else(empty_root::<H>)
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Inclusion proof for the leaf at `index`.
+    pub fn proof(&self, index: usize) -> Result<MerkleProof, MerkleError> {
+        if index >= self.len() {
+            return Err(MerkleError::IndexOutOfRange(index));
+        }
+        let mut siblings = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(position ^ 1) {
+                siblings.push(*sibling);
+            }
+            position /= 2;
+        }
+        Ok(MerkleProof { index, leaf_count: self.len(), siblings })
+    }
+
+    /// A single proof for several leaves, sharing the nodes their paths have in common.
+    /// Indices are sorted and deduplicated.
+    pub fn multiproof(&self, indices: &[usize]) -> Result<MultiProof, MerkleError> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.is_empty() {
+            return Err(MerkleError::NoLeaves);
+        }
+        if let Some(&index) = indices.iter().find(|&&index| index >= self.len()) {
+            return Err(MerkleError::IndexOutOfRange(index));
+        }
+
+        let mut hashes = Vec::new();
+        let mut known = indices.clone();
+        for level in &self.levels[..self.levels.len() - 1] {
+            for (i, &position) in known.iter().enumerate() {
+                let sibling = position ^ 1;
+                let sibling_known = if sibling < position {
+                    i > 0 && known[i - 1] == sibling
+                } else {
+                    known.get(i + 1) == Some(&sibling)
+                };
+                if !sibling_known && sibling < level.len() {
+                    hashes.push(level[sibling]);
+                }
+            }
+            known = parents(&known);
+        }
+        Ok(MultiProof { indices, leaf_count: self.len(), hashes })
+    }
+}
+
+/// Parent positions of a sorted list of positions.
+fn parents(positions: &[usize]) -> Vec<usize> {
+    let mut parents: Vec<usize> = positions.iter().map(|position| position / 2).collect();
+    parents.dedup();
+    parents
+}
+
+/// Proof that a leaf is at `index` in a tree of `leaf_count` leaves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub leaf_count: usize,
+    /// Sibling hashes from the leaf level upwards; promoted levels have none.
+    pub siblings: Vec<Hash>,
+}
+
+impl MerkleProof {
+    pub fn verify<H: MerkleHasher>(&self, root: &Hash, leaf: &[u8]) -> bool {
+        if self.index >= self.leaf_count {
+            return false;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut hash = hash_leaf::<H>(leaf);
+        let mut position = self.index;
+        let mut width = self.leaf_count;
+        while width > 1 {
+            if position ^ 1 < width {
+                let Some(sibling) = siblings.next() else {
+                    return false;
+                };
+                hash = if position & 1 == 0 { hash_node::<H>(&hash, sibling) } else { hash_node::<H>(sibling, &hash) };
+            }
+            position /= 2;
+            width = width.div_ceil(2);
+        }
+        siblings.next().is_none() && hash == *root
+    }
+}
+
+/// Proof that several leaves are at `indices` in a tree of `leaf_count` leaves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiProof {
+    /// Sorted, distinct leaf indices.
+    pub indices: Vec<usize>,
+    pub leaf_count: usize,
+    /// Hashes the verifier cannot compute itself, level by level, left to right.
+    pub hashes: Vec<Hash>,
+}
+
+impl MultiProof {
+    /// Checks `leaves`, given in the order of `indices`, against `root`.
+    pub fn verify<H: MerkleHasher, T: AsRef<[u8]>>(&self, root: &Hash, leaves: &[T]) -> bool {
+        if leaves.len() != self.indices.len()
+            || self.indices.is_empty()
+            || self.indices.windows(2).any(|pair| pair[0] >= pair[1])
+            || self.indices[self.indices.len() - 1] >= self.leaf_count
+        {
+            return false;
+        }
+        let mut hashes = self.hashes.iter();
+        let mut known: Vec<(usize, Hash)> = self
+            .indices
+            .iter()
+            .zip(leaves)
+            .map(|(&index, leaf)| (index, hash_leaf::<H>(leaf.as_ref())))
+            .collect();
+        let mut width = self.leaf_count;
+        while width > 1 {
+            let mut next = Vec::with_capacity(known.len());
+            let mut i = 0;
+            while i < known.len() {
+                let (position, hash) = known[i];
+                let sibling = position ^ 1;
+                let parent = if position & 1 == 0 && known.get(i + 1).map(|(p, _)| *p) == Some(sibling) {
+                    i += 1;
+                    hash_node::<H>(&hash, &known[i].1)
+                } else if sibling >= width {
+                    hash
+                } else {
+                    let Some(sibling_hash) = hashes.next() else {
+                        return false;
+                    };
+                    if position & 1 == 0 {
+                        hash_node::<H>(&hash, sibling_hash)
+                    } else {
+                        hash_node::<H>(sibling_hash, &hash)
+                    }
+                };
+                next.push((position / 2, parent));
+                i += 1;
+            }
+            known = next;
+            width = width.div_ceil(2);
+        }
+        hashes.next().is_none() && known[0].1 == *root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha3::Keccak256;
+
+    fn leaves(count: usize) -> Vec<Vec<u8>> {
+        (0..count).map(|i| format!("tx-{}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_root_structure() {
+        let data = leaves(3);
+        let [a, b, c] = [0, 1, 2].map(|i| hash_leaf::<Sha3_256>(&data[i]));
+
+        // The odd leaf is promoted, not duplicated.
+        let tree = MerkleTree::<Sha3_256>::new(&data);
+        assert_eq!(tree.root(), hash_node::<Sha3_256>(&hash_node::<Sha3_256>(&a, &b), &c));
+        assert_ne!(tree.root(), merkle_root::<Sha3_256, _>(&[&data[0], &data[1], &data[2], &data[2]]));
+
+        assert_eq!(merkle_root::<Sha3_256, _>(&data[..1]), a);
+        assert_eq!(MerkleTree::<Sha3_256>::new::<&[u8]>(&[]).root(), empty_root::<Sha3_256>());
+
+        // Domain separation: an inner node can't be presented as a leaf.
+        let inner = [a, b].concat();
+        assert_ne!(hash_leaf::<Sha3_256>(&inner), hash_node::<Sha3_256>(&a, &b));
+        assert_ne!(merkle_root::<Keccak256, _>(&data), tree.root());
+    }
+
+    fn check_proofs<H: MerkleHasher>() {
+        for count in 1..=17 {
+            let data = leaves(count);
+            let tree = MerkleTree::<H>::new(&data);
+            let root = tree.root();
+            for (index, leaf) in data.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(proof.verify::<H>(&root, leaf), "{} of {}", index, count);
+                assert!(!proof.verify::<H>(&root, b"forged"));
+
+                let moved = MerkleProof { index: (index + 1) % count, ..proof.clone() };
+                assert!(count == 1 || !moved.verify::<H>(&root, leaf));
+            }
+            assert_eq!(tree.proof(count), Err(MerkleError::IndexOutOfRange(count)));
+        }
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        check_proofs::<Sha3_256>();
+        check_proofs::<Keccak256>();
+    }
+
+    #[test]
+    fn test_multiproofs() {
+        for count in 1..=10usize {
+            let data = leaves(count);
+            let tree = MerkleTree::<Keccak256>::new(&data);
+            let root = tree.root();
+
+            // Every non-empty subset of leaves.
+            for mask in 1..(1u32 << count) {
+                let indices: Vec<usize> = (0..count).filter(|i| mask & (1 << i) != 0).collect();
+                let proof = tree.multiproof(&indices).unwrap();
+                let proven: Vec<&Vec<u8>> = indices.iter().map(|&i| &data[i]).collect();
+                assert!(proof.verify::<Keccak256, _>(&root, &proven), "{:?} of {}", indices, count);
+                assert!(!proof.verify::<Sha3_256, _>(&root, &proven));
+
+                let mut forged = proven.clone();
+                let fake = b"forged".to_vec();
+                forged[0] = &fake;
+                assert!(!proof.verify::<Keccak256, _>(&root, &forged));
+            }
+        }
+
+        // Shared path nodes are sent once.
+        let data = leaves(8);
+        let tree = MerkleTree::<Keccak256>::new(&data);
+        assert_eq!(tree.multiproof(&[0, 1]).unwrap().hashes.len(), 2);
+        assert_eq!(tree.multiproof(&[3, 0, 3]).unwrap().indices, vec![0, 3]);
+        assert_eq!(tree.multiproof(&[]), Err(MerkleError::NoLeaves));
+        assert_eq!(tree.multiproof(&[2, 8]), Err(MerkleError::IndexOutOfRange(8)));
+    }
+}
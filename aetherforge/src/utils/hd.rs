@@ -0,0 +1,729 @@
+use elliptic_curve::sec1::ToEncodedPoint;
+use elliptic_curve::PrimeField;
+use hmac::{Hmac, Mac};
+use k256::{FieldBytes, NonZeroScalar, ProjectivePoint, PublicKey, Scalar, SecretKey};
+use rand_core::OsRng;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use super::address::Address;
+
+pub use bip39::Mnemonic;
+
+/// Indices at or above this offset derive hardened children.
+pub const HARDENED_OFFSET: u32 = 1 << 31;
+
+/// SLIP-44 coin type used for account keys, shared with Ethereum wallets.
+pub const COIN_TYPE: u32 = 60;
+
+/// Mnemonic of the well-known development accounts shared by Hardhat,
+/// Anvil and Ganache. Funds sent to these accounts on a public network are lost.
+pub const DEV_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+const MASTER_KEY: &[u8] = b"Bitcoin seed";
+const XPRV_VERSION: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+const SERIALIZED_LEN: usize = 78;
+
+/// Custom error type for hierarchical deterministic key derivation
+#[derive(Error, Debug)]
+pub enum HdError {
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(#[from] bip39::Error),
+    #[error("Seed must be 16 to 64 bytes, got {0}")]
+    InvalidSeedLength(usize),
+    #[error("Invalid derivation path {0}")]
+    InvalidPath(String),
+    #[error("Hardened children cannot be derived from a public key")]
+    HardenedFromPublic,
+    #[error("Derived key is invalid, use the next index")]
+    InvalidChildKey,
+    #[error("Maximum derivation depth reached")]
+    DepthExceeded,
+    #[error("Invalid extended key encoding")]
+    InvalidEncoding,
+}
+
+/// Generates a random English mnemonic of 12, 15, 18, 21 or 24 words.
+pub fn generate_mnemonic(word_count: usize) -> Result<Mnemonic, HdError> {
+    Ok(Mnemonic::generate_in_with(&mut OsRng, bip39::Language::English, word_count)?)
+}
+
+/// Parses an English mnemonic, checking its word list and checksum.
+pub fn parse_mnemonic(phrase: &str) -> Result<Mnemonic, HdError> {
+    Ok(Mnemonic::parse_in(bip39::Language::English, phrase)?)
+}
+
+/// A single step of a derivation path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChildNumber {
+    Normal(u32),
+    Hardened(u32),
+}
+
+impl ChildNumber {
+    pub fn is_hardened(&self) -> bool {
+        matches!(self, ChildNumber::Hardened(_))
+    }
+
+    /// Index within the normal or hardened range.
+    pub fn index(self) -> u32 {
+        match self {
+            ChildNumber::Normal(index) | ChildNumber::Hardened(index) => index,
+        }
+    }
+
+    /// Rejects indices of `2^31` or more, which would alias a child of the other kind.
+    pub fn checked(self) -> Result<Self, HdError> {
+        if self.index() >= HARDENED_OFFSET {
+            return Err(HdError::InvalidPath(self.to_string()));
+        }
+        Ok(self)
+    }
+
+    /// The raw 32-bit index, with the top bit set for hardened children.
+    pub fn to_u32(self) -> u32 {
+        match self {
+            ChildNumber::Normal(index) => index,
+            ChildNumber::Hardened(index) => index | HARDENED_OFFSET,
+        }
+    }
+}
+
+impl From<u32> for ChildNumber {
+    fn from(index: u32) -> Self {
+        if index & HARDENED_OFFSET == 0 {
+            ChildNumber::Normal(index)
+        } else {
+            ChildNumber::Hardened(index & !HARDENED_OFFSET)
+        }
+    }
+}
+
+impl fmt::Display for ChildNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChildNumber::Normal(index) => write!(f, "{}", index),
+            ChildNumber::Hardened(index) => write!(f, "{}'", index),
+        }
+    }
+}
+
+/// A BIP-32 derivation path such as `m/44'/60'/0'/0/0`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<ChildNumber>);
+
+impl DerivationPath {
+    /// BIP-44 path of the `index`-th key of an operator `account`:
+    /// `m/44'/60'/account'/0/index`.
+    pub fn bip44(account: u32, index: u32) -> Result<Self, HdError> {
+        Self::try_from(vec![
+            ChildNumber::Hardened(44),
+            ChildNumber::Hardened(COIN_TYPE),
+            ChildNumber::Hardened(account),
+            ChildNumber::Normal(0),
+            ChildNumber::Normal(index),
+        ])
+    }
+
+    pub fn children(&self) -> &[ChildNumber] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<ChildNumber>> for DerivationPath {
+    type Error = HdError;
+
+    fn try_from(children: Vec<ChildNumber>) -> Result<Self, Self::Error> {
+        children.iter().try_for_each(|child| child.checked().map(drop))?;
+        Ok(Self(children))
+    }
+}
+
+/// Parses `m/...` paths, marking hardened steps with `'`, `h` or `H`.
+impl FromStr for DerivationPath {
+    type Err = HdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || HdError::InvalidPath(s.to_string());
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(invalid());
+        }
+        let children = parts
+            .map(|part| {
+                let (digits, hardened) = match part.strip_suffix(['\'', 'h', 'H']) {
+                    Some(digits) => (digits, true),
+                    None => (part, false),
+                };
+                let index: u32 = digits.parse().map_err(|_| invalid())?;
+                if index >= HARDENED_OFFSET {
+                    return Err(invalid());
+                }
+                Ok(if hardened { ChildNumber::Hardened(index) } else { ChildNumber::Normal(index) })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self(children))
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+        for child in &self.0 {
+            write!(f, "/{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+/// A BIP-32 extended secret key: a secp256k1 key plus the chain code needed
+/// to derive its children.
+#[derive(Debug, Clone)]
+pub struct ExtendedPrivateKey {
+    pub secret_key: SecretKey,
+    pub chain_code: [u8; 32],
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: ChildNumber,
+}
+
+impl ExtendedPrivateKey {
+    /// Master key of a BIP-32 tree.
+    pub fn new_master(seed: &[u8]) -> Result<Self, HdError> {
+        if !(16..=64).contains(&seed.len()) {
+            return Err(HdError::InvalidSeedLength(seed.len()));
+        }
+        let (key, chain_code) = hmac_sha512(MASTER_KEY, &[seed]);
+        let secret_key = SecretKey::from_slice(&key).map_err(|_| HdError::InvalidChildKey)?;
+        Ok(Self {
+            secret_key,
+            chain_code,
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_number: ChildNumber::Normal(0),
+        })
+    }
+
+    /// Master key of the BIP-39 seed of `mnemonic` and `passphrase`.
+    pub fn from_mnemonic(mnemonic: &Mnemonic, passphrase: &str) -> Result<Self, HdError> {
+        Self::new_master(&mnemonic.to_seed(passphrase))
+    }
+
+    pub fn derive_child(&self, child: ChildNumber) -> Result<Self, HdError> {
+        let child = child.checked()?;
+        let depth = self.depth.checked_add(1).ok_or(HdError::DepthExceeded)?;
+        let index = child.to_u32().to_be_bytes();
+        let (tweak, chain_code) = if child.is_hardened() {
+            hmac_sha512(&self.chain_code, &[&[0], &self.secret_key.to_bytes(), &index])
+        } else {
+            hmac_sha512(&self.chain_code, &[&self.compressed_public_key(), &index])
+        };
+        let tweak = parse_scalar(&tweak)?;
+        let scalar = Option::<NonZeroScalar>::from(NonZeroScalar::new(tweak + *self.secret_key.to_nonzero_scalar()))
+            .ok_or(HdError::InvalidChildKey)?;
+        Ok(Self {
+            secret_key: SecretKey::from(scalar),
+            chain_code,
+            depth,
+            parent_fingerprint: self.fingerprint(),
+            child_number: child,
+        })
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, HdError> {
+        path.children().iter().try_fold(self.clone(), |key, child| key.derive_child(*child))
+    }
+
+    /// The matching extended public key, which can derive normal children only.
+    pub fn public_key(&self) -> ExtendedPublicKey {
+        ExtendedPublicKey {
+            public_key: self.secret_key.public_key(),
+            chain_code: self.chain_code,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        Address::from(&self.secret_key)
+    }
+
+    /// First four bytes of the key identifier, as recorded in its children.
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(&self.compressed_public_key())
+    }
+
+    /// Base58Check `xprv` serialization.
+    pub fn to_xprv(&self) -> String {
+        let mut key = vec![0];
+        key.extend_from_slice(&self.secret_key.to_bytes());
+        serialize(XPRV_VERSION, self.depth, self.parent_fingerprint, self.child_number, &self.chain_code, &key)
+    }
+
+    fn compressed_public_key(&self) -> Vec<u8> {
+        self.secret_key.public_key().to_encoded_point(true).as_bytes().to_vec()
+    }
+}
+
+impl FromStr for ExtendedPrivateKey {
+    type Err = HdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (version, depth, parent_fingerprint, child_number, chain_code, key) = deserialize(s)?;
+        if version != XPRV_VERSION || key[0] != 0 {
+            return Err(HdError::InvalidEncoding);
+        }
+        let secret_key = SecretKey::from_slice(&key[1..]).map_err(|_| HdError::InvalidEncoding)?;
+        Ok(Self { secret_key, chain_code, depth, parent_fingerprint, child_number })
+    }
+}
+
+/// A BIP-32 extended public key, for watch-only derivation of normal children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPublicKey {
+    pub public_key: PublicKey,
+    pub chain_code: [u8; 32],
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: ChildNumber,
+}
+
+impl ExtendedPublicKey {
+    pub fn derive_child(&self, child: ChildNumber) -> Result<Self, HdError> {
+        if child.checked()?.is_hardened() {
+            return Err(HdError::HardenedFromPublic);
+        }
+        let depth = self.depth.checked_add(1).ok_or(HdError::DepthExceeded)?;
+        let (tweak, chain_code) =
+            hmac_sha512(&self.chain_code, &[&self.compressed_public_key(), &child.to_u32().to_be_bytes()]);
+        let point = ProjectivePoint::GENERATOR * parse_scalar(&tweak)? + self.public_key.to_projective();
+        let public_key = PublicKey::from_affine(point.to_affine()).map_err(|_| HdError::InvalidChildKey)?;
+        Ok(Self { public_key, chain_code, depth, parent_fingerprint: self.fingerprint(), child_number: child })
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, HdError> {
+        path.children().iter().try_fold(self.clone(), |key, child| key.derive_child(*child))
+    }
+
+    pub fn address(&self) -> Address {
+        Address::from(&self.public_key)
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(&self.compressed_public_key())
+    }
+
+    fn compressed_public_key(&self) -> Vec<u8> {
+        self.public_key.to_encoded_point(true).as_bytes().to_vec()
+    }
+}
+
+/// Base58Check `xpub` serialization.
+impl fmt::Display for ExtendedPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = self.compressed_public_key();
+        let encoded =
+            serialize(XPUB_VERSION, self.depth, self.parent_fingerprint, self.child_number, &self.chain_code, &key);
+        f.write_str(&encoded)
+    }
+}
+
+impl FromStr for ExtendedPublicKey {
+    type Err = HdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (version, depth, parent_fingerprint, child_number, chain_code, key) = deserialize(s)?;
+        if version != XPUB_VERSION {
+            return Err(HdError::InvalidEncoding);
+        }
+        let public_key = PublicKey::from_sec1_bytes(&key).map_err(|_| HdError::InvalidEncoding)?;
+        Ok(Self { public_key, chain_code, depth, parent_fingerprint, child_number })
+    }
+}
+
+/// Derives the well-known development accounts, so genesis files and
+/// integration tests can refer to stable identities.
+///
+/// Account `n` is the key at `m/44'/60'/0'/0/n`, matching Hardhat's default
+/// accounts for [`DEV_MNEMONIC`].
+#[derive(Debug, Clone)]
+pub struct DevKeys {
+    /// Key at `m/44'/60'/0'/0`, the parent of every account.
+    parent: ExtendedPrivateKey,
+}
+
+impl DevKeys {
+    /// Development accounts of another mnemonic, e.g. a devnet's own.
+    pub fn new(mnemonic: &Mnemonic) -> Result<Self, HdError> {
+        let path = DerivationPath::try_from(vec![
+            ChildNumber::Hardened(44),
+            ChildNumber::Hardened(COIN_TYPE),
+            ChildNumber::Hardened(0),
+            ChildNumber::Normal(0),
+        ])?;
+        Ok(Self { parent: ExtendedPrivateKey::from_mnemonic(mnemonic, "")?.derive_path(&path)? })
+    }
+
+    /// Secret key of account `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is not below [`HARDENED_OFFSET`].
+    pub fn secret_key(&self, index: u32) -> SecretKey {
+        assert!(index < HARDENED_OFFSET, "dev account index {} out of range", index);
+        self.parent.derive_child(ChildNumber::Normal(index)).expect("dev account derivation").secret_key
+    }
+
+    /// Secret key and uncompressed SEC1 public key of account `index`,
+    /// in the form returned by `generate_keypair`.
+    pub fn keypair(&self, index: u32) -> (SecretKey, Vec<u8>) {
+        let secret_key = self.secret_key(index);
+        let public_key = secret_key.public_key().to_encoded_point(false).as_bytes().to_vec();
+        (secret_key, public_key)
+    }
+
+    pub fn address(&self, index: u32) -> Address {
+        Address::from(&self.secret_key(index))
+    }
+
+    /// Addresses of the first `count` accounts.
+    pub fn addresses(&self, count: u32) -> Vec<Address> {
+        (0..count).map(|index| self.address(index)).collect()
+    }
+}
+
+impl Default for DevKeys {
+    fn default() -> Self {
+        let mnemonic = parse_mnemonic(DEV_MNEMONIC).expect("DEV_MNEMONIC is valid");
+        Self::new(&mnemonic).expect("dev accounts derive from DEV_MNEMONIC")
+    }
+}
+
+/// Splits HMAC-SHA512 output into the left (key material) and right (chain code) halves.
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in data {
+        mac.update(part);
+    }
+    let output = mac.finalize().into_bytes();
+    let mut left = [0u8; 32];
+    let mut right = [0u8; 32];
+    left.copy_from_slice(&output[..32]);
+    right.copy_from_slice(&output[32..]);
+    (left, right)
+}
+
+/// Interprets 32 bytes as a scalar, rejecting values not below the curve order.
+fn parse_scalar(bytes: &[u8; 32]) -> Result<Scalar, HdError> {
+    Option::from(Scalar::from_repr(FieldBytes::from(*bytes))).ok_or(HdError::InvalidChildKey)
+}
+
+fn fingerprint(compressed_public_key: &[u8]) -> [u8; 4] {
+    let identifier = Ripemd160::digest(Sha256::digest(compressed_public_key));
+    [identifier[0], identifier[1], identifier[2], identifier[3]]
+}
+
+fn serialize(
+    version: [u8; 4],
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: ChildNumber,
+    chain_code: &[u8; 32],
+    key: &[u8],
+) -> String {
+    let mut data = Vec::with_capacity(SERIALIZED_LEN);
+    data.extend_from_slice(&version);
+    data.push(depth);
+    data.extend_from_slice(&parent_fingerprint);
+    data.extend_from_slice(&child_number.to_u32().to_be_bytes());
+    data.extend_from_slice(chain_code);
+    data.extend_from_slice(key);
+    bs58::encode(data).with_check().into_string()
+}
+
+type Serialized = ([u8; 4], u8, [u8; 4], ChildNumber, [u8; 32], Vec<u8>);
+
+fn deserialize(s: &str) -> Result<Serialized, HdError> {
+    let data = bs58::decode(s).with_check(None).into_vec().map_err(|_| HdError::InvalidEncoding)?;
+    if data.len() != SERIALIZED_LEN {
+        return Err(HdError::InvalidEncoding);
+    }
+    let version = data[0..4].try_into().expect("slice is 4 bytes");
+    let parent_fingerprint = data[5..9].try_into().expect("slice is 4 bytes");
+    let child_number = u32::from_be_bytes(data[9..13].try_into().expect("slice is 4 bytes"));
+    // A master key has no parent.
+    if data[4] == 0 && (parent_fingerprint != [0; 4] || child_number != 0) {
+        return Err(HdError::InvalidEncoding);
+    }
+    let chain_code = data[13..45].try_into().expect("slice is 32 bytes");
+    Ok((version, data[4], parent_fingerprint, child_number.into(), chain_code, data[45..].to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rows of a BIP-32 test vector: (path, xpub, xprv).
+    type Vector<'a> = &'a [(&'a str, &'a str, &'a str)];
+
+    /// BIP-32 test vector 1: (path, xpub, xprv).
+    const BIP32_VECTOR_1: [(&str, &str, &str); 5] = [
+        (
+            "m",
+            "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+            "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+        ),
+        (
+            "m/0H",
+            "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+            "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+        ),
+        (
+            "m/0H/1",
+            "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+            "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+        ),
+        (
+            "m/0H/1/2H",
+            "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+            "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+        ),
+        (
+            "m/0H/1/2H/2",
+            "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+            "xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334",
+        ),
+    ];
+
+    /// BIP-32 test vector 2.
+    const BIP32_VECTOR_2: [(&str, &str, &str); 6] = [
+        (
+            "m",
+            "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+            "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U",
+        ),
+        (
+            "m/0",
+            "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH",
+            "xprv9vHkqa6EV4sPZHYqZznhT2NPtPCjKuDKGY38FBWLvgaDx45zo9WQRUT3dKYnjwih2yJD9mkrocEZXo1ex8G81dwSM1fwqWpWkeS3v86pgKt",
+        ),
+        (
+            "m/0/2147483647H",
+            "xpub6ASAVgeehLbnwdqV6UKMHVzgqAG8Gr6riv3Fxxpj8ksbH9ebxaEyBLZ85ySDhKiLDBrQSARLq1uNRts8RuJiHjaDMBU4Zn9h8LZNnBC5y4a",
+            "xprv9wSp6B7kry3Vj9m1zSnLvN3xH8RdsPP1Mh7fAaR7aRLcQMKTR2vidYEeEg2mUCTAwCd6vnxVrcjfy2kRgVsFawNzmjuHc2YmYRmagcEPdU9",
+        ),
+        (
+            "m/0/2147483647H/1",
+            "xpub6DF8uhdarytz3FWdA8TvFSvvAh8dP3283MY7p2V4SeE2wyWmG5mg5EwVvmdMVCQcoNJxGoWaU9DCWh89LojfZ537wTfunKau47EL2dhHKon",
+            "xprv9zFnWC6h2cLgpmSA46vutJzBcfJ8yaJGg8cX1e5StJh45BBciYTRXSd25UEPVuesF9yog62tGAQtHjXajPPdbRCHuWS6T8XA2ECKADdw4Ef",
+        ),
+        (
+            "m/0/2147483647H/1/2147483646H",
+            "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL",
+            "xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc",
+        ),
+        (
+            "m/0/2147483647H/1/2147483646H/2",
+            "xpub6FnCn6nSzZAw5Tw7cgR9bi15UV96gLZhjDstkXXxvCLsUXBGXPdSnLFbdpq8p9HmGsApME5hQTZ3emM2rnY5agb9rXpVGyy3bdW6EEgAtqt",
+            "xprvA2nrNbFZABcdryreWet9Ea4LvTJcGsqrMzxHx98MMrotbir7yrKCEXw7nadnHM8Dq38EGfSh6dqA9QWTyefMLEcBYJUuekgW4BYPJcr9E7j",
+        ),
+    ];
+
+    /// BIP-32 test vector 3, covering retention of leading zeros.
+    const BIP32_VECTOR_3: [(&str, &str, &str); 2] = [
+        (
+            "m",
+            "xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13",
+            "xprv9s21ZrQH143K25QhxbucbDDuQ4naNntJRi4KUfWT7xo4EKsHt2QJDu7KXp1A3u7Bi1j8ph3EGsZ9Xvz9dGuVrtHHs7pXeTzjuxBrCmmhgC6",
+        ),
+        (
+            "m/0H",
+            "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y",
+            "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L",
+        ),
+    ];
+
+    /// BIP-32 test vector 4, covering retention of leading zeros in hardened derivation.
+    const BIP32_VECTOR_4: [(&str, &str, &str); 3] = [
+        (
+            "m",
+            "xpub661MyMwAqRbcGczjuMoRm6dXaLDEhW1u34gKenbeYqAix21mdUKJyuyu5F1rzYGVxyL6tmgBUAEPrEz92mBXjByMRiJdba9wpnN37RLLAXa",
+            "xprv9s21ZrQH143K48vGoLGRPxgo2JNkJ3J3fqkirQC2zVdk5Dgd5w14S7fRDyHH4dWNHUgkvsvNDCkvAwcSHNAQwhwgNMgZhLtQC63zxwhQmRv",
+        ),
+        (
+            "m/0H",
+            "xpub69AUMk3qDBi3uW1sXgjCmVjJ2G6WQoYSnNHyzkmdCHEhSZ4tBok37xfFEqHd2AddP56Tqp4o56AePAgCjYdvpW2PU2jbUPFKsav5ut6Ch1m",
+            "xprv9vB7xEWwNp9kh1wQRfCCQMnZUEG21LpbR9NPCNN1dwhiZkjjeGRnaALmPXCX7SgjFTiCTT6bXes17boXtjq3xLpcDjzEuGLQBM5ohqkao9G",
+        ),
+        (
+            "m/0H/1H",
+            "xpub6BJA1jSqiukeaesWfxe6sNK9CCGaujFFSJLomWHprUL9DePQ4JDkM5d88n49sMGJxrhpjazuXYWdMf17C9T5XnxkopaeS7jGk1GyyVziaMt",
+            "xprv9xJocDuwtYCMNAo3Zw76WENQeAS6WGXQ55RCy7tDJ8oALr4FWkuVoHJeHVAcAqiZLE7Je3vZJHxspZdFHfnBEjHqU5hG1Jaj32dVoS6XLT1",
+        ),
+    ];
+
+    /// BIP-32 test vector 5: extended keys that must be rejected.
+    const BIP32_INVALID_KEYS: [&str; 15] = [
+        // Public key version with a private key, and vice versa.
+        "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6LBpB85b3D2yc8sfvZU521AAwdZafEz7mnzBBsz4wKY5fTtTQBm",
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGTQQD3dC4H2D5GBj7vWvSQaaBv5cxi9gafk7NF3pnBju6dwKvH",
+        // Invalid key prefixes 04 and 01.
+        "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Txnt3siSujt9RCVYsx4qHZGc62TG4McvMGcAUjeuwZdduYEvFn",
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGpWnsj83BHtEy5Zt8CcDr1UiRXuWCmTQLxEK9vbz5gPstX92JQ",
+        "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6N8ZMMXctdiCjxTNq964yKkwrkBJJwpzZS4HS2fxvyYUA4q2Xe4",
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD9y5gkZ6Eq3Rjuahrv17fEQ3Qen6J",
+        // Zero depth with a parent fingerprint or a child index.
+        "xpub661no6RGEX3uJkY4bNnPcw4URcQTrSibUZ4NqJEw5eBkv7ovTwgiT91XX27VbEXGENhYRCf7hyEbWrR3FewATdCEebj6znwMfQkhRYHRLpJ",
+        "xprv9s21ZrQH4r4TsiLvyLXqM9P7k1K3EYhA1kkD6xuquB5i39AU8KF42acDyL3qsDbU9NmZn6MsGSUYZEsuoePmjzsB3eFKSUEh3Gu1N3cqVUN",
+        "xpub661MyMwAuDcm6CRQ5N4qiHKrJ39Xe1R1NyfouMKTTWcguwVcfrZJaNvhpebzGerh7gucBvzEQWRugZDuDXjNDRmXzSZe4c7mnTK97pTvGS8",
+        // Unknown version.
+        "DMwo58pR1QLEFihHiXPVykYB6fJmsTeHvyTp7hRThAtCX8CvYzgPcn8XnmdfHGMQzT7ayAmfo4z3gY5KfbrZWZ6St24UVf2Qgo6oujFktLHdHY4",
+        "DMwo58pR1QLEFihHiXPVykYB6fJmsTeHvyTp7hRThAtCX8CvYzgPcn8XnmdfHPmHJiEDXkTiJTVV9rHEBUem2mwVbbNfvT2MTcAqj3nesx8uBf9",
+        // Private keys 0 and n.
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzF93Y5wvzdUayhgkkFoicQZcP3y52uPPxFnfoLZB21Teqt1VvEHx",
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD5SDKr24z3aiUvKr9bJpdrcLg1y3G",
+        // Public key not on the curve.
+        "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Q5JXayek4PRsn35jii4veMimro1xefsM58PgBMrvdYre8QyULY",
+        // Invalid checksum.
+        "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHL",
+    ];
+
+    #[test]
+    fn test_bip32_vectors() {
+        let vectors: [(&str, Vector); 4] = [
+            ("000102030405060708090a0b0c0d0e0f", &BIP32_VECTOR_1),
+            (
+                "fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542",
+                &BIP32_VECTOR_2,
+            ),
+            (
+                "4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be",
+                &BIP32_VECTOR_3,
+            ),
+            ("3ddd5602285899a946114506157c7997e5444528f3003f6134712147db19b678", &BIP32_VECTOR_4),
+        ];
+        for (seed, vector) in vectors {
+            let master = ExtendedPrivateKey::new_master(&hex::decode(seed).unwrap()).unwrap();
+            for (path, xpub, xprv) in vector {
+                let key = master.derive_path(&path.parse().unwrap()).unwrap();
+                assert_eq!(key.to_xprv(), *xprv, "{}", path);
+                assert_eq!(key.public_key().to_string(), *xpub, "{}", path);
+
+                // Both serializations round-trip.
+                assert_eq!(xprv.parse::<ExtendedPrivateKey>().unwrap().to_xprv(), *xprv);
+                assert_eq!(xpub.parse::<ExtendedPublicKey>().unwrap(), key.public_key());
+            }
+        }
+    }
+
+    #[test]
+    fn test_bip32_invalid_keys() {
+        for key in BIP32_INVALID_KEYS {
+            assert!(key.parse::<ExtendedPrivateKey>().is_err(), "{}", key);
+            assert!(key.parse::<ExtendedPublicKey>().is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_public_derivation_matches_private() {
+        let master = ExtendedPrivateKey::new_master(&[7u8; 32]).unwrap();
+        let account = master.derive_path(&"m/44'/60'/0'".parse().unwrap()).unwrap();
+        let path: DerivationPath = "m/0/5".parse().unwrap();
+        assert_eq!(
+            account.public_key().derive_path(&path).unwrap(),
+            account.derive_path(&path).unwrap().public_key()
+        );
+        assert!(matches!(
+            account.public_key().derive_child(ChildNumber::Hardened(0)),
+            Err(HdError::HardenedFromPublic)
+        ));
+    }
+
+    #[test]
+    fn test_bip39_vectors() {
+        let phrase = ["abandon"; 11].join(" ");
+        let mnemonic = parse_mnemonic(&format!("{} about", phrase)).unwrap();
+        assert_eq!(mnemonic.to_entropy(), vec![0u8; 16]);
+        assert_eq!(
+            hex::encode(mnemonic.to_seed("TREZOR")),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+
+        // Wrong checksum word.
+        assert!(matches!(
+            parse_mnemonic(&format!("{} abandon", phrase)),
+            Err(HdError::InvalidMnemonic(_))
+        ));
+
+        let generated = generate_mnemonic(24).unwrap();
+        assert_eq!(generated.word_count(), 24);
+        assert_eq!(parse_mnemonic(&generated.to_string()).unwrap(), generated);
+        assert!(generate_mnemonic(13).is_err());
+    }
+
+    #[test]
+    fn test_operator_keys_from_mnemonic() {
+        let mnemonic = parse_mnemonic(DEV_MNEMONIC).unwrap();
+        let master = ExtendedPrivateKey::from_mnemonic(&mnemonic, "").unwrap();
+        let first = master.derive_path(&DerivationPath::bip44(0, 0).unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(first.secret_key.to_bytes()),
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        );
+        assert_eq!(first.address().to_string(), "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+        assert_ne!(master.derive_path(&DerivationPath::bip44(0, 1).unwrap()).unwrap().address(), first.address());
+    }
+
+    #[test]
+    fn test_dev_keys_match_hardhat_accounts() {
+        let dev_keys = DevKeys::default();
+        assert_eq!(
+            hex::encode(dev_keys.secret_key(1).to_bytes()),
+            "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+        );
+        assert_eq!(
+            dev_keys.addresses(2),
+            vec![
+                "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse::<Address>().unwrap(),
+                "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse::<Address>().unwrap(),
+            ]
+        );
+        let (secret_key, public_key) = dev_keys.keypair(7);
+        assert_eq!(secret_key, dev_keys.secret_key(7));
+        assert_eq!(Address::from_public_key(&public_key).unwrap(), dev_keys.address(7));
+
+        // Another mnemonic gives other accounts.
+        let other = DevKeys::new(&generate_mnemonic(12).unwrap()).unwrap();
+        assert_ne!(other.address(0), dev_keys.address(0));
+    }
+
+    #[test]
+    fn test_derivation_path_parsing() {
+        let path: DerivationPath = "m/44'/60'/0'/0/7".parse().unwrap();
+        assert_eq!(path, DerivationPath::bip44(0, 7).unwrap());
+        assert_eq!(path.to_string(), "m/44'/60'/0'/0/7");
+        assert_eq!("m/0h/1H".parse::<DerivationPath>().unwrap().to_string(), "m/0'/1'");
+        assert_eq!("m".parse::<DerivationPath>().unwrap(), DerivationPath::default());
+        assert_eq!(ChildNumber::from(HARDENED_OFFSET + 3), ChildNumber::Hardened(3));
+        for invalid in ["", "44'/0", "m/", "m/x", "m/2147483648"] {
+            assert!(invalid.parse::<DerivationPath>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_child_numbers() {
+        let master = ExtendedPrivateKey::new_master(&[7u8; 32]).unwrap();
+        for child in [ChildNumber::Normal(HARDENED_OFFSET), ChildNumber::Hardened(HARDENED_OFFSET + 3)] {
+            assert!(matches!(master.derive_child(child), Err(HdError::InvalidPath(_))));
+            assert!(matches!(master.public_key().derive_child(child), Err(HdError::InvalidPath(_))));
+            assert!(matches!(DerivationPath::try_from(vec![child]), Err(HdError::InvalidPath(_))));
+        }
+        assert!(matches!(DerivationPath::bip44(HARDENED_OFFSET, 0), Err(HdError::InvalidPath(_))));
+        assert!(matches!(DerivationPath::bip44(0, u32::MAX), Err(HdError::InvalidPath(_))));
+    }
+}
@@ -0,0 +1,410 @@
+use aes::cipher::{KeyIvInit, StreamCipher};
+use k256::SecretKey;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+use std::fs;
+use std::io;
+use std::path::Path;
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use super::address::Address;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// The only keystore version this module reads and writes.
+pub const KEYSTORE_VERSION: u32 = 3;
+
+const CIPHER: &str = "aes-128-ctr";
+const PRF: &str = "hmac-sha256";
+const DERIVED_KEY_LEN: usize = 32;
+const IV_LEN: usize = 16;
+
+/// Custom error type for keystore operations
+#[derive(Error, Debug)]
+pub enum KeystoreError {
+    #[error("Unsupported keystore version {0}")]
+    UnsupportedVersion(u32),
+    #[error("Unsupported cipher {0}")]
+    UnsupportedCipher(String),
+    #[error("Unsupported pseudo-random function {0}")]
+    UnsupportedPrf(String),
+    #[error("Invalid key derivation parameters")]
+    InvalidKdfParams,
+    #[error("Invalid cipher IV length {0}, expected 16 bytes")]
+    InvalidIvLength(usize),
+    #[error("MAC mismatch, wrong passphrase or corrupted keystore")]
+    MacMismatch,
+    #[error("Decrypted data is not a valid secret key")]
+    InvalidSecretKey,
+    #[error("Keystore address does not match its key")]
+    AddressMismatch,
+    #[error("Failed to read passphrase")]
+    Passphrase(#[source] io::Error),
+    #[error("Failed to access keystore file")]
+    IoError(#[from] io::Error),
+    #[error("Failed to (de)serialize keystore")]
+    SerdeError(#[from] serde_json::Error),
+}
+
+/// Supplies the passphrase for a keystore, e.g. by prompting the operator.
+///
+/// Closures taking the prompt text implement this, so a CLI can hook in its
+/// own terminal prompt and tests can pass a fixed passphrase.
+pub trait PassphraseProvider {
+    fn passphrase(&self, prompt: &str) -> io::Result<String>;
+}
+
+impl<F: Fn(&str) -> io::Result<String>> PassphraseProvider for F {
+    fn passphrase(&self, prompt: &str) -> io::Result<String> {
+        self(prompt)
+    }
+}
+
+/// Key derivation function used to turn the passphrase into the encryption key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    /// scrypt with cost `2^log_n`.
+    Scrypt { log_n: u8, r: u32, p: u32 },
+    /// PBKDF2-HMAC-SHA256 with `c` iterations.
+    Pbkdf2 { c: u32 },
+}
+
+impl Kdf {
+    /// geth's "light" scrypt parameters, for keys that are unlocked often.
+    pub const LIGHT: Kdf = Kdf::Scrypt { log_n: 12, r: 8, p: 6 };
+}
+
+impl Default for Kdf {
+    /// geth's standard scrypt parameters.
+    fn default() -> Self {
+        Kdf::Scrypt { log_n: 18, r: 8, p: 1 }
+    }
+}
+
+/// KDF name and parameters as stored in the `kdf` and `kdfparams` fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kdf", content = "kdfparams", rename_all = "lowercase")]
+pub enum KdfParams {
+    Scrypt {
+        dklen: usize,
+        n: u64,
+        r: u32,
+        p: u32,
+        #[serde(with = "hex")]
+        salt: Vec<u8>,
+    },
+    Pbkdf2 {
+        c: u32,
+        dklen: usize,
+        prf: String,
+        #[serde(with = "hex")]
+        salt: Vec<u8>,
+    },
+}
+
+impl KdfParams {
+    fn new(kdf: Kdf, salt: Vec<u8>) -> Result<Self, KeystoreError> {
+        Ok(match kdf {
+            Kdf::Scrypt { log_n, r, p } => {
+                let n = 1u64.checked_shl(log_n.into()).ok_or(KeystoreError::InvalidKdfParams)?;
+                KdfParams::Scrypt { dklen: DERIVED_KEY_LEN, n, r, p, salt }
+            }
+            Kdf::Pbkdf2 { c } => KdfParams::Pbkdf2 { c, dklen: DERIVED_KEY_LEN, prf: PRF.to_string(), salt },
+        })
+    }
+
+    /// Derives the encryption key, which is wiped from memory when dropped.
+    fn derive_key(&self, passphrase: &[u8]) -> Result<Zeroizing<Vec<u8>>, KeystoreError> {
+        match self {
+            KdfParams::Scrypt { dklen, n, r, p, salt } => {
+                if *dklen != DERIVED_KEY_LEN || !n.is_power_of_two() {
+                    return Err(KeystoreError::InvalidKdfParams);
+                }
+                let params = scrypt::Params::new(n.trailing_zeros() as u8, *r, *p, *dklen)
+                    .map_err(|_| KeystoreError::InvalidKdfParams)?;
+                let mut key = Zeroizing::new(vec![0u8; *dklen]);
+                scrypt::scrypt(passphrase, salt, &params, &mut key).map_err(|_| KeystoreError::InvalidKdfParams)?;
+                Ok(key)
+            }
+            KdfParams::Pbkdf2 { c, dklen, prf, salt } => {
+                if prf != PRF {
+                    return Err(KeystoreError::UnsupportedPrf(prf.clone()));
+                }
+                if *dklen != DERIVED_KEY_LEN || *c == 0 {
+                    return Err(KeystoreError::InvalidKdfParams);
+                }
+                let mut key = Zeroizing::new(vec![0u8; *dklen]);
+                pbkdf2::pbkdf2_hmac::<Sha256>(passphrase, salt, *c, &mut key);
+                Ok(key)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CipherParams {
+    #[serde(with = "hex")]
+    pub iv: Vec<u8>,
+}
+
+/// The `crypto` section of a keystore.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeystoreCrypto {
+    pub cipher: String,
+    pub cipherparams: CipherParams,
+    #[serde(with = "hex")]
+    pub ciphertext: Vec<u8>,
+    #[serde(flatten)]
+    pub kdf: KdfParams,
+    #[serde(with = "hex")]
+    pub mac: Vec<u8>,
+}
+
+/// An encrypted secret key in the Web3 Secret Storage (keystore v3) format
+/// used by geth, MetaMask and other Ethereum wallets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    /// Lowercase hex address of the key without `0x`, as written by geth.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(alias = "Crypto")]
+    pub crypto: KeystoreCrypto,
+    pub id: String,
+    pub version: u32,
+}
+
+impl Keystore {
+    /// Encrypts `secret_key` under `passphrase` with a fresh salt and IV.
+    pub fn encrypt(secret_key: &SecretKey, passphrase: &str, kdf: Kdf) -> Result<Self, KeystoreError> {
+        let mut salt = vec![0u8; 32];
+        let mut iv = vec![0u8; IV_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut iv);
+
+        let kdf = KdfParams::new(kdf, salt)?;
+        let derived_key = kdf.derive_key(passphrase.as_bytes())?;
+        let mut ciphertext = Zeroizing::new(secret_key.to_bytes()).to_vec();
+        Aes128Ctr::new(derived_key[..16].into(), iv.as_slice().into()).apply_keystream(&mut ciphertext);
+        let mac = mac(&derived_key, &ciphertext);
+
+        Ok(Self {
+            address: Some(hex::encode(Address::from(secret_key).as_bytes())),
+            crypto: KeystoreCrypto {
+                cipher: CIPHER.to_string(),
+                cipherparams: CipherParams { iv },
+                ciphertext,
+                kdf,
+                mac,
+            },
+            id: uuid::Uuid::new_v4().to_string(),
+            version: KEYSTORE_VERSION,
+        })
+    }
+
+    /// Checks the MAC and decrypts the secret key.
+    pub fn decrypt(&self, passphrase: &str) -> Result<SecretKey, KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(self.version));
+        }
+        let crypto = &self.crypto;
+        if crypto.cipher != CIPHER {
+            return Err(KeystoreError::UnsupportedCipher(crypto.cipher.clone()));
+        }
+        if crypto.cipherparams.iv.len() != IV_LEN {
+            return Err(KeystoreError::InvalidIvLength(crypto.cipherparams.iv.len()));
+        }
+
+        let derived_key = crypto.kdf.derive_key(passphrase.as_bytes())?;
+        if !bool::from(mac(&derived_key, &crypto.ciphertext).ct_eq(&crypto.mac)) {
+            return Err(KeystoreError::MacMismatch);
+        }
+        let mut plaintext = Zeroizing::new(crypto.ciphertext.clone());
+        Aes128Ctr::new(derived_key[..16].into(), crypto.cipherparams.iv.as_slice().into())
+            .apply_keystream(&mut plaintext);
+        let secret_key = SecretKey::from_slice(&plaintext).map_err(|_| KeystoreError::InvalidSecretKey)?;
+
+        if self.address().is_some_and(|address| address != Address::from(&secret_key)) {
+            return Err(KeystoreError::AddressMismatch);
+        }
+        Ok(secret_key)
+    }
+
+    /// Asks `provider` for the passphrase and decrypts the secret key.
+    pub fn decrypt_with(&self, provider: &impl PassphraseProvider) -> Result<SecretKey, KeystoreError> {
+        let prompt = match self.address() {
+            Some(address) => format!("Passphrase for {address}: "),
+            None => "Passphrase: ".to_string(),
+        };
+        let passphrase = provider.passphrase(&prompt).map_err(KeystoreError::Passphrase)?;
+        self.decrypt(&passphrase)
+    }
+
+    /// Address recorded in the keystore, if any.
+    pub fn address(&self) -> Option<Address> {
+        self.address.as_deref().and_then(|address| address.parse().ok())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, KeystoreError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, KeystoreError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KeystoreError> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), KeystoreError> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+/// Reads a keystore file and decrypts it with a passphrase from `provider`.
+pub fn import_key(path: impl AsRef<Path>, provider: &impl PassphraseProvider) -> Result<SecretKey, KeystoreError> {
+    Keystore::load(path)?.decrypt_with(provider)
+}
+
+/// Encrypts `secret_key` with a passphrase from `provider` and writes it to `path`.
+pub fn export_key(
+    path: impl AsRef<Path>,
+    secret_key: &SecretKey,
+    provider: &impl PassphraseProvider,
+    kdf: Kdf,
+) -> Result<Keystore, KeystoreError> {
+    let prompt = format!("New passphrase for {}: ", Address::from(secret_key));
+    let passphrase = provider.passphrase(&prompt).map_err(KeystoreError::Passphrase)?;
+    let keystore = Keystore::encrypt(secret_key, &passphrase, kdf)?;
+    keystore.save(path)?;
+    Ok(keystore)
+}
+
+/// Keccak-256 of the second half of the derived key and the ciphertext.
+fn mac(derived_key: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    let mut hasher = Keccak256::new();
+    hasher.update(&derived_key[16..32]);
+    hasher.update(ciphertext);
+    hasher.finalize().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto::generate_keypair;
+
+    /// Test vector from the Web3 Secret Storage definition.
+    const PBKDF2_VECTOR: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "6087dab2f9fdbbfaddc31a909735c1e6" },
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {
+                "c": 262144,
+                "dklen": 32,
+                "prf": "hmac-sha256",
+                "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+            },
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    #[test]
+    fn test_decrypt_specification_vector() {
+        let keystore = Keystore::from_json(PBKDF2_VECTOR).unwrap();
+        let secret_key = keystore.decrypt("testpassword").unwrap();
+        assert_eq!(
+            hex::encode(secret_key.to_bytes()),
+            "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d"
+        );
+        assert!(matches!(keystore.decrypt("wrongpassword"), Err(KeystoreError::MacMismatch)));
+    }
+
+    #[test]
+    fn test_scrypt_kdf() {
+        // RFC 7914 vector, truncated to the 32-byte derived key.
+        let kdf = KdfParams::Scrypt { dklen: 32, n: 1024, r: 8, p: 16, salt: b"NaCl".to_vec() };
+        assert_eq!(
+            hex::encode(kdf.derive_key(b"password").unwrap()),
+            "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162"
+        );
+
+        // N must stay below 2^(16r), which rules out the specification's r = 1 scrypt vector.
+        let kdf = KdfParams::Scrypt { dklen: 32, n: 1 << 18, r: 1, p: 8, salt: vec![0; 32] };
+        assert!(matches!(kdf.derive_key(b"testpassword"), Err(KeystoreError::InvalidKdfParams)));
+    }
+
+    #[test]
+    fn test_scrypt_cost_overflow() {
+        let (secret_key, _) = generate_keypair();
+        let kdf = Kdf::Scrypt { log_n: 64, r: 8, p: 1 };
+        assert!(matches!(Keystore::encrypt(&secret_key, "pass", kdf), Err(KeystoreError::InvalidKdfParams)));
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let (secret_key, _) = generate_keypair();
+        for kdf in [Kdf::Scrypt { log_n: 10, r: 8, p: 1 }, Kdf::Pbkdf2 { c: 1024 }] {
+            let keystore = Keystore::encrypt(&secret_key, "correct horse", kdf).unwrap();
+            assert_eq!(keystore.address(), Some(Address::from(&secret_key)));
+
+            let restored = Keystore::from_json(&keystore.to_json().unwrap()).unwrap();
+            assert_eq!(restored, keystore);
+            assert_eq!(restored.decrypt("correct horse").unwrap(), secret_key);
+            assert!(matches!(restored.decrypt("battery staple"), Err(KeystoreError::MacMismatch)));
+        }
+    }
+
+    #[test]
+    fn test_rejects_tampering_and_unsupported_formats() {
+        let (secret_key, _) = generate_keypair();
+        let keystore = Keystore::encrypt(&secret_key, "pass", Kdf::Pbkdf2 { c: 16 }).unwrap();
+
+        let mut tampered = keystore.clone();
+        tampered.crypto.ciphertext[0] ^= 1;
+        assert!(matches!(tampered.decrypt("pass"), Err(KeystoreError::MacMismatch)));
+
+        let (other_key, _) = generate_keypair();
+        let mut relabeled = keystore.clone();
+        relabeled.address = Some(hex::encode(Address::from(&other_key).as_bytes()));
+        assert!(matches!(relabeled.decrypt("pass"), Err(KeystoreError::AddressMismatch)));
+
+        let mut version = keystore.clone();
+        version.version = 4;
+        assert!(matches!(version.decrypt("pass"), Err(KeystoreError::UnsupportedVersion(4))));
+
+        let mut iv = keystore.clone();
+        iv.crypto.cipherparams.iv.truncate(12);
+        assert!(matches!(iv.decrypt("pass"), Err(KeystoreError::InvalidIvLength(12))));
+
+        let mut cipher = keystore;
+        cipher.crypto.cipher = "aes-128-cbc".to_string();
+        assert!(matches!(cipher.decrypt("pass"), Err(KeystoreError::UnsupportedCipher(_))));
+    }
+
+    #[test]
+    fn test_import_and_export_with_prompt() {
+        let (secret_key, _) = generate_keypair();
+        let path = std::env::temp_dir().join(format!("aetherforge_keystore_{}.json", std::process::id()));
+        let prompt = |prompt: &str| {
+            assert!(prompt.contains(&Address::from(&secret_key).to_string()));
+            Ok("operator passphrase".to_string())
+        };
+
+        export_key(&path, &secret_key, &prompt, Kdf::Pbkdf2 { c: 1024 }).unwrap();
+        let imported = import_key(&path, &prompt);
+        let cancelled = import_key(&path, &|_: &str| Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled")));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(imported.unwrap(), secret_key);
+        assert!(matches!(cancelled, Err(KeystoreError::Passphrase(_))));
+    }
+}
@@ -0,0 +1,97 @@
+use fips204::ml_dsa_65;
+use fips204::traits::{SerDes, Signer, Verifier};
+
+use super::crypto::{check_digest, CryptoError, SchemeTag, SignatureScheme, DIGEST_LEN};
+
+/// FIPS 204 context string binding signatures to this chain.
+const ML_DSA_CONTEXT: &[u8] = b"aetherforge";
+
+/// Length of an ML-DSA-65 public key.
+pub const ML_DSA_65_PUBLIC_KEY_LEN: usize = ml_dsa_65::PK_LEN;
+
+/// Length of an ML-DSA-65 signature.
+pub const ML_DSA_65_SIGNATURE_LEN: usize = ml_dsa_65::SIG_LEN;
+
+/// ML-DSA-65 (Dilithium, NIST security category 3) signatures. Keys and
+/// signatures are far larger than secp256k1's, but survive a quantum adversary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MlDsa65;
+
+impl SignatureScheme for MlDsa65 {
+    const TAG: SchemeTag = SchemeTag::MlDsa65;
+    type SecretKey = ml_dsa_65::PrivateKey;
+
+    fn generate_keypair() -> (Self::SecretKey, Vec<u8>) {
+        let (public_key, secret_key) = ml_dsa_65::try_keygen().expect("OS random number generator is available");
+        (secret_key, public_key.into_bytes().to_vec())
+    }
+
+    fn public_key(secret_key: &Self::SecretKey) -> Vec<u8> {
+        secret_key.get_public_key().into_bytes().to_vec()
+    }
+
+    fn sign(secret_key: &Self::SecretKey, digest: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        check_digest(digest)?;
+        let signature = secret_key
+            .try_sign(digest, ML_DSA_CONTEXT)
+            .map_err(|_| CryptoError::InvalidSignature)?;
+        Ok(signature.to_vec())
+    }
+
+    fn verify(public_key: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+        if digest.len() != DIGEST_LEN {
+            return false;
+        }
+        let Ok(public_key) = public_key.try_into().map(ml_dsa_65::PublicKey::try_from_bytes) else {
+            return false;
+        };
+        let (Ok(public_key), Ok(signature)) = (public_key, signature.try_into()) else {
+            return false;
+        };
+        public_key.verify(digest, &signature, ML_DSA_CONTEXT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto::{encode_tagged, verify_tagged, verify_with_scheme, Secp256k1};
+    use crate::utils::hashing::{sha3_256_hash, Domain};
+
+    #[test]
+    fn test_sign_and_verify() {
+        let digest = sha3_256_hash(Domain::Block, b"post-quantum");
+        let (secret_key, public_key) = MlDsa65::generate_keypair();
+        assert_eq!(public_key.len(), ML_DSA_65_PUBLIC_KEY_LEN);
+        assert_eq!(MlDsa65::public_key(&secret_key), public_key);
+
+        let signature = MlDsa65::sign(&secret_key, &digest).unwrap();
+        assert_eq!(signature.len(), ML_DSA_65_SIGNATURE_LEN);
+        assert!(MlDsa65::verify(&public_key, &digest, &signature));
+        assert!(verify_with_scheme(SchemeTag::MlDsa65, &public_key, &digest, &signature));
+        assert!(!verify_with_scheme(SchemeTag::Secp256k1, &public_key, &digest, &signature));
+
+        assert!(!MlDsa65::verify(&public_key, &sha3_256_hash(Domain::Block, b"other"), &signature));
+        let (_, other_public_key) = MlDsa65::generate_keypair();
+        assert!(!MlDsa65::verify(&other_public_key, &digest, &signature));
+        assert!(!MlDsa65::verify(&public_key, &digest, &signature[1..]));
+        assert!(!MlDsa65::verify(&public_key[1..], &digest, &signature));
+        assert_eq!(MlDsa65::sign(&secret_key, &digest[1..]), Err(CryptoError::InvalidDigestLength(31)));
+    }
+
+    #[test]
+    fn test_tagged_schemes_coexist() {
+        let digest = sha3_256_hash(Domain::Block, b"migration");
+        let (pq_secret, pq_public) = MlDsa65::generate_keypair();
+        let (ec_secret, ec_public) = Secp256k1::generate_keypair();
+        let pq_key = encode_tagged(MlDsa65::TAG, &pq_public);
+        let pq_signature = encode_tagged(MlDsa65::TAG, &MlDsa65::sign(&pq_secret, &digest).unwrap());
+        let ec_key = encode_tagged(Secp256k1::TAG, &ec_public);
+        let ec_signature = encode_tagged(Secp256k1::TAG, &Secp256k1::sign(&ec_secret, &digest).unwrap());
+
+        assert!(verify_tagged(&pq_key, &digest, &pq_signature));
+        assert!(verify_tagged(&ec_key, &digest, &ec_signature));
+        assert!(!verify_tagged(&pq_key, &digest, &ec_signature));
+        assert!(!verify_tagged(&ec_key, &digest, &pq_signature));
+    }
+}
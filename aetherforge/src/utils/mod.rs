@@ -1,2 +1,10 @@
 pub mod hashing;
-pub mod crypto;
\ No newline at end of file
+pub mod crypto;
+pub mod vrf;
+pub mod address;
+pub mod eip712;
+pub mod keystore;
+pub mod hd;
+pub mod mldsa;
+pub mod schnorr;
+pub mod musig2;
@@ -0,0 +1,424 @@
+//! MuSig2 multi-signatures (BIP-327) over secp256k1.
+//!
+//! A committee aggregates its keys into one x-only key and runs two rounds:
+//! every signer publishes a [`PublicNonce`], then, once all nonces are in,
+//! a partial signature. The aggregate is an ordinary BIP-340 signature that
+//! [`schnorr_verify`](super::schnorr::schnorr_verify) accepts under the
+//! aggregate key. Key tweaking is not supported.
+
+use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::AffineCoordinates;
+use elliptic_curve::sec1::ToEncodedPoint;
+use elliptic_curve::PrimeField;
+use k256::{FieldBytes, ProjectivePoint, PublicKey, Scalar, SecretKey, U256};
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use thiserror::Error;
+use zeroize::Zeroize;
+
+/// Length of a compressed SEC1 public key, the key format signers use.
+pub const PUBLIC_KEY_LEN: usize = 33;
+
+/// Length of a public (or aggregate) nonce: two compressed points.
+pub const PUBLIC_NONCE_LEN: usize = 66;
+
+/// Length of a partial signature.
+pub const PARTIAL_SIGNATURE_LEN: usize = 32;
+
+/// Custom error type for MuSig2 operations
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MusigError {
+    #[error("No public keys to aggregate")]
+    NoKeys,
+    #[error("Invalid public key encoding")]
+    InvalidPublicKey,
+    #[error("Aggregate public key is the point at infinity")]
+    InfiniteAggregateKey,
+    #[error("Public key is not part of the aggregate")]
+    UnknownSigner,
+    #[error("Invalid nonce encoding")]
+    InvalidNonce,
+    #[error("Secret nonce belongs to a different key")]
+    NonceKeyMismatch,
+    #[error("Invalid partial signature")]
+    InvalidPartialSignature,
+}
+
+/// BIP-340 tagged hash: `SHA256(SHA256(tag) || SHA256(tag) || data...)`.
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new().chain_update(tag_hash).chain_update(tag_hash);
+    for chunk in data {
+        hasher.update(chunk);
+    }
+    hasher.finalize().into()
+}
+
+fn hash_to_scalar(tag: &str, data: &[&[u8]]) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(&FieldBytes::from(tagged_hash(tag, data)))
+}
+
+fn parse_point(bytes: &[u8]) -> Option<ProjectivePoint> {
+    if bytes.len() != PUBLIC_KEY_LEN || !matches!(bytes[0], 0x02 | 0x03) {
+        return None;
+    }
+    PublicKey::from_sec1_bytes(bytes).ok().map(|key| key.to_projective())
+}
+
+/// Compressed encoding, with 33 zero bytes standing for the point at infinity.
+fn point_bytes_ext(point: &ProjectivePoint) -> [u8; PUBLIC_KEY_LEN] {
+    let mut bytes = [0u8; PUBLIC_KEY_LEN];
+    if *point != ProjectivePoint::IDENTITY {
+        bytes.copy_from_slice(point.to_affine().to_encoded_point(true).as_bytes());
+    }
+    bytes
+}
+
+fn parse_point_ext(bytes: &[u8]) -> Option<ProjectivePoint> {
+    if bytes.iter().all(|&byte| byte == 0) {
+        return Some(ProjectivePoint::IDENTITY);
+    }
+    parse_point(bytes)
+}
+
+fn has_even_y(point: &ProjectivePoint) -> bool {
+    !bool::from(point.to_affine().y_is_odd())
+}
+
+fn x_bytes(point: &ProjectivePoint) -> [u8; 32] {
+    point.to_affine().x().into()
+}
+
+/// Negates `scalar` unless `keep` is set.
+fn negate_unless(scalar: Scalar, keep: bool) -> Scalar {
+    if keep {
+        scalar
+    } else {
+        -scalar
+    }
+}
+
+/// The signers' public keys and their aggregate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyAggContext {
+    public_keys: Vec<[u8; PUBLIC_KEY_LEN]>,
+    coefficients: Vec<Scalar>,
+    aggregate: ProjectivePoint,
+}
+
+impl KeyAggContext {
+    /// Aggregates compressed SEC1 public keys. Order matters: every signer
+    /// must use the same list to arrive at the same aggregate key.
+    pub fn new<T: AsRef<[u8]>>(public_keys: &[T]) -> Result<Self, MusigError> {
+        let public_keys = public_keys
+            .iter()
+            .map(|key| key.as_ref().try_into().map_err(|_| MusigError::InvalidPublicKey))
+            .collect::<Result<Vec<[u8; PUBLIC_KEY_LEN]>, _>>()?;
+        let first_key = public_keys.first().ok_or(MusigError::NoKeys)?;
+
+        let list_hash = tagged_hash("KeyAgg list", &[&public_keys.concat()]);
+        // The second distinct key gets coefficient 1, which saves a scalar multiplication.
+        let second_key = public_keys.iter().find(|key| *key != first_key);
+
+        let mut coefficients = Vec::with_capacity(public_keys.len());
+        let mut aggregate = ProjectivePoint::IDENTITY;
+        for key in &public_keys {
+            let point = parse_point(key).ok_or(MusigError::InvalidPublicKey)?;
+            let coefficient = if Some(key) == second_key {
+                Scalar::ONE
+            } else {
+                hash_to_scalar("KeyAgg coefficient", &[&list_hash, key])
+            };
+            aggregate += point * coefficient;
+            coefficients.push(coefficient);
+        }
+        if aggregate == ProjectivePoint::IDENTITY {
+            return Err(MusigError::InfiniteAggregateKey);
+        }
+        Ok(Self { public_keys, coefficients, aggregate })
+    }
+
+    /// BIP-340 x-only aggregate public key.
+    pub fn aggregate_public_key(&self) -> [u8; 32] {
+        x_bytes(&self.aggregate)
+    }
+
+    pub fn public_keys(&self) -> &[[u8; PUBLIC_KEY_LEN]] {
+        &self.public_keys
+    }
+
+    fn coefficient(&self, public_key: &[u8]) -> Option<Scalar> {
+        let position = self.public_keys.iter().position(|key| key.as_slice() == public_key)?;
+        Some(self.coefficients[position])
+    }
+}
+
+/// A signer's secret nonce. It is consumed by [`Session::partial_sign`] and
+/// deliberately not `Clone`: signing twice with one nonce leaks the key.
+/// The scalars are wiped on drop and never printed.
+pub struct SecretNonce {
+    k1: Scalar,
+    k2: Scalar,
+    public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+impl fmt::Debug for SecretNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretNonce")
+            .field("public_key", &hex::encode(self.public_key))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SecretNonce {
+    fn drop(&mut self) {
+        self.k1.zeroize();
+        self.k2.zeroize();
+    }
+}
+
+/// A signer's public nonce, shared with the other signers in the first round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicNonce(pub [u8; PUBLIC_NONCE_LEN]);
+
+/// Sum of all signers' public nonces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregateNonce(pub [u8; PUBLIC_NONCE_LEN]);
+
+fn parse_nonce_points(
+    bytes: &[u8; PUBLIC_NONCE_LEN],
+    allow_infinity: bool,
+) -> Result<[ProjectivePoint; 2], MusigError> {
+    let parse = if allow_infinity { parse_point_ext } else { parse_point };
+    let r1 = parse(&bytes[..PUBLIC_KEY_LEN]).ok_or(MusigError::InvalidNonce)?;
+    let r2 = parse(&bytes[PUBLIC_KEY_LEN..]).ok_or(MusigError::InvalidNonce)?;
+    Ok([r1, r2])
+}
+
+/// Generates a fresh nonce pair for signing `message` under `key_agg`.
+///
+/// Fresh randomness is mixed with the secret key, aggregate key and message,
+/// so a weak RNG alone does not lead to nonce reuse.
+pub fn generate_nonce(secret_key: &SecretKey, key_agg: &KeyAggContext, message: &[u8]) -> (SecretNonce, PublicNonce) {
+    let mut rand_prime = [0u8; 32];
+    OsRng.fill_bytes(&mut rand_prime);
+    let aux_hash = tagged_hash("MuSig/aux", &[&rand_prime]);
+    let secret_bytes = secret_key.to_bytes();
+    let rand: Vec<u8> = secret_bytes.iter().zip(aux_hash).map(|(secret, mask)| secret ^ mask).collect();
+
+    let public_key: [u8; PUBLIC_KEY_LEN] = point_bytes_ext(&secret_key.public_key().to_projective());
+    let aggregate_key = key_agg.aggregate_public_key();
+    let nonce = |index: u8| {
+        hash_to_scalar(
+            "MuSig/nonce",
+            &[
+                &rand,
+                &[PUBLIC_KEY_LEN as u8],
+                &public_key,
+                &[aggregate_key.len() as u8],
+                &aggregate_key,
+                &[1],
+                &(message.len() as u64).to_be_bytes(),
+                message,
+                &0u32.to_be_bytes(),
+                &[index],
+            ],
+        )
+    };
+    // A zero nonce has probability ~2^-256; it would only mean a broken hash.
+    let (k1, k2) = (nonce(0), nonce(1));
+    assert!(!bool::from(k1.is_zero()) && !bool::from(k2.is_zero()), "nonce is nonzero");
+
+    let mut public_nonce = [0u8; PUBLIC_NONCE_LEN];
+    public_nonce[..PUBLIC_KEY_LEN].copy_from_slice(&point_bytes_ext(&(ProjectivePoint::GENERATOR * k1)));
+    public_nonce[PUBLIC_KEY_LEN..].copy_from_slice(&point_bytes_ext(&(ProjectivePoint::GENERATOR * k2)));
+    (SecretNonce { k1, k2, public_key }, PublicNonce(public_nonce))
+}
+
+/// Sums the signers' public nonces. Any party, e.g. the block proposer, can
+/// do this and hand the result back to the signers.
+pub fn aggregate_nonces(public_nonces: &[PublicNonce]) -> Result<AggregateNonce, MusigError> {
+    let mut sums = [ProjectivePoint::IDENTITY; 2];
+    for nonce in public_nonces {
+        let [r1, r2] = parse_nonce_points(&nonce.0, false)?;
+        sums[0] += r1;
+        sums[1] += r2;
+    }
+    let mut aggregate = [0u8; PUBLIC_NONCE_LEN];
+    aggregate[..PUBLIC_KEY_LEN].copy_from_slice(&point_bytes_ext(&sums[0]));
+    aggregate[PUBLIC_KEY_LEN..].copy_from_slice(&point_bytes_ext(&sums[1]));
+    Ok(AggregateNonce(aggregate))
+}
+
+/// Values shared by all signers of one message under one aggregate nonce.
+#[derive(Debug, Clone)]
+pub struct Session<'a> {
+    key_agg: &'a KeyAggContext,
+    nonce_coefficient: Scalar,
+    nonce_point: ProjectivePoint,
+    challenge: Scalar,
+}
+
+impl<'a> Session<'a> {
+    /// Derives the session values every signer and verifier agrees on.
+    pub fn new(
+        key_agg: &'a KeyAggContext,
+        aggregate_nonce: &AggregateNonce,
+        message: &[u8],
+    ) -> Result<Self, MusigError> {
+        let [r1, r2] = parse_nonce_points(&aggregate_nonce.0, true)?;
+        let aggregate_key = key_agg.aggregate_public_key();
+        let nonce_coefficient = hash_to_scalar("MuSig/noncecoef", &[&aggregate_nonce.0, &aggregate_key, message]);
+        let mut nonce_point = r1 + r2 * nonce_coefficient;
+        if nonce_point == ProjectivePoint::IDENTITY {
+            nonce_point = ProjectivePoint::GENERATOR;
+        }
+        let challenge = hash_to_scalar("BIP0340/challenge", &[&x_bytes(&nonce_point), &aggregate_key, message]);
+        Ok(Self { key_agg, nonce_coefficient, nonce_point, challenge })
+    }
+
+    /// Produces this signer's partial signature, consuming its secret nonce.
+    pub fn partial_sign(
+        &self,
+        secret_nonce: SecretNonce,
+        secret_key: &SecretKey,
+    ) -> Result<[u8; PARTIAL_SIGNATURE_LEN], MusigError> {
+        let public_key = point_bytes_ext(&secret_key.public_key().to_projective());
+        if public_key != secret_nonce.public_key {
+            return Err(MusigError::NonceKeyMismatch);
+        }
+        let coefficient = self.key_agg.coefficient(&public_key).ok_or(MusigError::UnknownSigner)?;
+
+        let nonce_even = has_even_y(&self.nonce_point);
+        let k1 = negate_unless(secret_nonce.k1, nonce_even);
+        let k2 = negate_unless(secret_nonce.k2, nonce_even);
+        let d = negate_unless(*secret_key.to_nonzero_scalar(), has_even_y(&self.key_agg.aggregate));
+        let s = k1 + self.nonce_coefficient * k2 + self.challenge * coefficient * d;
+        Ok(s.to_bytes().into())
+    }
+
+    /// Checks one signer's partial signature against their public nonce, so
+    /// a faulty signer can be identified before aggregation.
+    pub fn verify_partial(&self, partial: &[u8], public_nonce: &PublicNonce, public_key: &[u8]) -> bool {
+        let Some(s) = parse_partial(partial) else {
+            return false;
+        };
+        let (Some(coefficient), Some(point)) = (self.key_agg.coefficient(public_key), parse_point(public_key)) else {
+            return false;
+        };
+        let Ok([r1, r2]) = parse_nonce_points(&public_nonce.0, false) else {
+            return false;
+        };
+        let nonce = r1 + r2 * self.nonce_coefficient;
+        let nonce = if has_even_y(&self.nonce_point) { nonce } else { -nonce };
+        let g = negate_unless(Scalar::ONE, has_even_y(&self.key_agg.aggregate));
+        ProjectivePoint::GENERATOR * s == nonce + point * (self.challenge * coefficient * g)
+    }
+
+    /// Combines the partial signatures into a BIP-340 signature.
+    pub fn aggregate_partials<T: AsRef<[u8]>>(&self, partials: &[T]) -> Result<[u8; 64], MusigError> {
+        let s = partials.iter().try_fold(Scalar::ZERO, |sum, partial| {
+            parse_partial(partial.as_ref()).map(|s| sum + s).ok_or(MusigError::InvalidPartialSignature)
+        })?;
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(&x_bytes(&self.nonce_point));
+        signature[32..].copy_from_slice(&s.to_bytes());
+        Ok(signature)
+    }
+}
+
+fn parse_partial(partial: &[u8]) -> Option<Scalar> {
+    let bytes: [u8; PARTIAL_SIGNATURE_LEN] = partial.try_into().ok()?;
+    Option::from(Scalar::from_repr(bytes.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto::{keypair_from_seed, public_key_of};
+    use crate::utils::schnorr::schnorr_verify;
+
+    const X1: &str = "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9";
+    const X2: &str = "03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659";
+    const X3: &str = "023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66";
+
+    fn aggregate_hex(keys: &[&str]) -> String {
+        let keys: Vec<Vec<u8>> = keys.iter().map(|key| hex::decode(key).unwrap()).collect();
+        hex::encode_upper(KeyAggContext::new(&keys).unwrap().aggregate_public_key())
+    }
+
+    #[test]
+    fn test_key_aggregation_vectors() {
+        // From the BIP-327 key aggregation test vectors.
+        assert_eq!(aggregate_hex(&[X1, X2, X3]), "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C");
+        assert_eq!(aggregate_hex(&[X3, X2, X1]), "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B");
+        assert_eq!(aggregate_hex(&[X1, X1, X1]), "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935");
+        assert_eq!(aggregate_hex(&[X1, X1, X2, X2]), "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E");
+    }
+
+    #[test]
+    fn test_key_aggregation_errors() {
+        assert_eq!(KeyAggContext::new::<Vec<u8>>(&[]), Err(MusigError::NoKeys));
+        let mut invalid = hex::decode(X1).unwrap();
+        invalid[0] = 0x05;
+        assert_eq!(KeyAggContext::new(&[invalid]), Err(MusigError::InvalidPublicKey));
+        assert_eq!(KeyAggContext::new(&[&hex::decode(X1).unwrap()[1..]]), Err(MusigError::InvalidPublicKey));
+    }
+
+    #[test]
+    fn test_sign_and_aggregate() {
+        let message = b"finalize block 42";
+        let secret_keys: Vec<SecretKey> =
+            (0..4).map(|i| keypair_from_seed(format!("validator-{i}").as_bytes()).0).collect();
+        let public_keys: Vec<Vec<u8>> = secret_keys.iter().map(|key| public_key_of(key, true)).collect();
+        let key_agg = KeyAggContext::new(&public_keys).unwrap();
+
+        let (secret_nonces, public_nonces): (Vec<_>, Vec<_>) =
+            secret_keys.iter().map(|key| generate_nonce(key, &key_agg, message)).unzip();
+        let session = Session::new(&key_agg, &aggregate_nonces(&public_nonces).unwrap(), message).unwrap();
+
+        let partials: Vec<_> = secret_nonces
+            .into_iter()
+            .zip(&secret_keys)
+            .map(|(nonce, key)| session.partial_sign(nonce, key).unwrap())
+            .collect();
+        for ((partial, nonce), key) in partials.iter().zip(&public_nonces).zip(&public_keys) {
+            assert!(session.verify_partial(partial, nonce, key));
+        }
+        assert!(!session.verify_partial(&partials[0], &public_nonces[1], &public_keys[0]));
+        assert!(!session.verify_partial(&partials[0], &public_nonces[0], &public_keys[1]));
+
+        let signature = session.aggregate_partials(&partials).unwrap();
+        assert!(schnorr_verify(&key_agg.aggregate_public_key(), message, &signature));
+        assert!(!schnorr_verify(&key_agg.aggregate_public_key(), b"finalize block 43", &signature));
+
+        // Leaving out a signer's share breaks the aggregate.
+        let signature = session.aggregate_partials(&partials[1..]).unwrap();
+        assert!(!schnorr_verify(&key_agg.aggregate_public_key(), message, &signature));
+    }
+
+    #[test]
+    fn test_partial_sign_rejects_foreign_key() {
+        let (signer, _) = keypair_from_seed(b"validator-0");
+        let (outsider, _) = keypair_from_seed(b"outsider");
+        let key_agg = KeyAggContext::new(&[public_key_of(&signer, true)]).unwrap();
+
+        let (secret_nonce, public_nonce) = generate_nonce(&signer, &key_agg, b"msg");
+        let session = Session::new(&key_agg, &aggregate_nonces(&[public_nonce]).unwrap(), b"msg").unwrap();
+        assert_eq!(session.partial_sign(secret_nonce, &outsider), Err(MusigError::NonceKeyMismatch));
+
+        let (secret_nonce, _) = generate_nonce(&outsider, &key_agg, b"msg");
+        assert_eq!(session.partial_sign(secret_nonce, &outsider), Err(MusigError::UnknownSigner));
+    }
+
+    #[test]
+    fn test_secret_nonce_debug_redacts_scalars() {
+        let (signer, _) = keypair_from_seed(b"validator-0");
+        let key_agg = KeyAggContext::new(&[public_key_of(&signer, true)]).unwrap();
+        let (secret_nonce, _) = generate_nonce(&signer, &key_agg, b"msg");
+        let debug = format!("{:?}", secret_nonce);
+        assert!(debug.contains(&hex::encode(secret_nonce.public_key)));
+        assert!(!debug.contains("k1") && !debug.contains(&hex::encode(secret_nonce.k1.to_bytes())));
+    }
+}
@@ -0,0 +1,106 @@
+use k256::schnorr::{Signature, SigningKey, VerifyingKey};
+use k256::SecretKey;
+use rand_core::{OsRng, RngCore};
+
+use super::crypto::CryptoError;
+
+/// Length of a BIP-340 `R.x || s` signature.
+pub const SCHNORR_SIGNATURE_LEN: usize = 64;
+
+/// Length of a BIP-340 x-only public key.
+pub const X_ONLY_KEY_LEN: usize = 32;
+
+/// BIP-340 x-only public key of `secret_key`.
+pub fn x_only_public_key(secret_key: &SecretKey) -> [u8; X_ONLY_KEY_LEN] {
+    SigningKey::from(secret_key).verifying_key().to_bytes().into()
+}
+
+/// Signs `message` with BIP-340 Schnorr, using fresh auxiliary randomness.
+///
+/// The message is signed as is; callers pass a domain-separated digest.
+pub fn schnorr_sign(secret_key: &SecretKey, message: &[u8]) -> Result<[u8; SCHNORR_SIGNATURE_LEN], CryptoError> {
+    let mut aux_rand = [0u8; 32];
+    OsRng.fill_bytes(&mut aux_rand);
+    schnorr_sign_with_aux_rand(secret_key, message, &aux_rand)
+}
+
+/// Signs `message` with BIP-340 Schnorr and the given auxiliary randomness.
+/// Deterministic, for test vectors and reproducible fixtures.
+pub fn schnorr_sign_with_aux_rand(
+    secret_key: &SecretKey,
+    message: &[u8],
+    aux_rand: &[u8; 32],
+) -> Result<[u8; SCHNORR_SIGNATURE_LEN], CryptoError> {
+    let signature = SigningKey::from(secret_key)
+        .sign_raw(message, aux_rand)
+        .map_err(|_| CryptoError::InvalidSignature)?;
+    Ok(signature.to_bytes())
+}
+
+/// Verifies a BIP-340 signature over `message` against an x-only public key.
+pub fn schnorr_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    if public_key.len() != X_ONLY_KEY_LEN || signature.len() != SCHNORR_SIGNATURE_LEN {
+        return false;
+    }
+    let Ok(verifying_key) = VerifyingKey::from_bytes(public_key) else {
+        return false;
+    };
+    let Ok(signature) = Signature::try_from(signature) else {
+        return false;
+    };
+    verifying_key.verify_raw(message, &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto::generate_keypair;
+
+    /// Test vectors 0 and 1 from BIP-340: (secret key, public key, aux_rand, message, signature).
+    const BIP340_VECTORS: [(&str, &str, &str, &str, &str); 2] = [
+        (
+            "0000000000000000000000000000000000000000000000000000000000000003",
+            "F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA8215\
+             25F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0",
+        ),
+        (
+            "B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF",
+            "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+            "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE3341\
+             8906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A",
+        ),
+    ];
+
+    #[test]
+    fn test_bip340_vectors() {
+        for (secret_key, public_key, aux_rand, message, signature) in BIP340_VECTORS {
+            let secret_key = SecretKey::from_slice(&hex::decode(secret_key).unwrap()).unwrap();
+            let public_key = hex::decode(public_key).unwrap();
+            let aux_rand: [u8; 32] = hex::decode(aux_rand).unwrap().try_into().unwrap();
+            let message = hex::decode(message).unwrap();
+            let signature = hex::decode(signature).unwrap();
+
+            assert_eq!(x_only_public_key(&secret_key).to_vec(), public_key);
+            assert_eq!(schnorr_sign_with_aux_rand(&secret_key, &message, &aux_rand).unwrap().to_vec(), signature);
+            assert!(schnorr_verify(&public_key, &message, &signature));
+        }
+    }
+
+    #[test]
+    fn test_schnorr_sign_and_verify() {
+        let (secret_key, _) = generate_keypair();
+        let public_key = x_only_public_key(&secret_key);
+        let signature = schnorr_sign(&secret_key, b"finalize block 7").unwrap();
+        assert!(schnorr_verify(&public_key, b"finalize block 7", &signature));
+        assert!(!schnorr_verify(&public_key, b"finalize block 8", &signature));
+
+        let (other_key, _) = generate_keypair();
+        assert!(!schnorr_verify(&x_only_public_key(&other_key), b"finalize block 7", &signature));
+        assert!(!schnorr_verify(&public_key, b"finalize block 7", &signature[..63]));
+    }
+}